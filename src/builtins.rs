@@ -0,0 +1,95 @@
+//! Names the compiler knows about without them being declared in a `.sun` file
+
+/// functions every file can call without a `use` declaration
+pub const PRELUDE_FUNCTIONS: [&str; 6] = ["print", "println", "prompt", "readIn", "exit", "clamp"];
+
+pub const PRIMITIVE_TYPES: [&str; 17] = [
+	"u8", "u16", "u32", "u64", "usize", "i8", "i16", "i32", "i64", "isize", "f32", "f64", "bool",
+	"char", "string", "Unit", "Any",
+];
+
+/// classes provided by the standard library, `String` and `Char` wrap the primitives so they
+/// can be extended
+pub const BUILTIN_CLASSES: [&str; 3] = ["Vector", "String", "Char"];
+
+/// error classes every program can catch without declaring them, (name, parent)
+pub const BUILTIN_ERRORS: [(&str, Option<&str>); 4] = [
+	("Error", None),
+	("IllegalArgument", Some("Error")),
+	("IllegalState", Some("Error")),
+	("IndexOutOfBounds", Some("Error")),
+];
+
+/// modules that can be pulled in with `use`, (path, exported names)
+///
+/// `use stdlib;` imports everything below `stdlib`, `use stdlib.random;` only that module
+pub const MODULES: [(&str, &[&str]); 5] = [
+	("stdlib", &[]),
+	("stdlib.print", &["print", "println"]),
+	("stdlib.random", &["random"]),
+	(
+		"stdlib.math",
+		&["ceil", "floor", "log2", "sqrt", "abs", "pow"],
+	),
+	("threads", &["Thread"]),
+];
+
+/// names exported by `use <path>`, `None` when the path isn't a known module
+pub fn module_exports(path: &str) -> Option<Vec<&'static str>> {
+	let prefix = format!("{path}.");
+	let mut found = false;
+	let mut exports = Vec::new();
+	for (module, names) in MODULES {
+		if module == path || module.starts_with(&prefix) {
+			found = true;
+			exports.extend_from_slice(names);
+		}
+	}
+	found.then_some(exports)
+}
+
+/// signatures of the prelude and stdlib functions, (name, parameter types, return type)
+///
+/// a `...` prefix marks a variadic parameter, `Any` accepts and produces every type
+pub const FUNCTION_SIGNATURES: [(&str, &[&str], &str); 13] = [
+	("print", &["...Any"], "Unit"),
+	("println", &["...Any"], "Unit"),
+	("prompt", &["string"], "string"),
+	("readIn", &[], "string"),
+	("exit", &["i32"], "Unit"),
+	("clamp", &["Any", "Any", "Any"], "Any"),
+	("random", &["Any", "Any"], "Any"),
+	("ceil", &["Any"], "u32"),
+	("floor", &["Any"], "u32"),
+	("log2", &["Any"], "f64"),
+	("sqrt", &["Any"], "f64"),
+	("abs", &["Any"], "Any"),
+	("pow", &["Any", "Any"], "Any"),
+];
+
+/// methods on builtin types, (receiver, name, parameter types, return type)
+///
+/// `T` is the element type of the receiver, members missing here are typed as `Any`
+pub const METHOD_SIGNATURES: [(&str, &str, &[&str], &str); 11] = [
+	("string", "truncate", &["usize"], "string"),
+	("string", "strip", &[], "string"),
+	("string", "toLowercase", &[], "string"),
+	("string", "slice", &[], "[char]"),
+	("string", "chars", &[], "[char]"),
+	("string", "append", &["string"], "Unit"),
+	("string", "parseInt", &[], "u32"),
+	("char", "isDigit", &[], "bool"),
+	("Vector", "add", &["T"], "Unit"),
+	("Vector", "contains", &["T"], "bool"),
+	("Vector", "shuffle", &[], "Unit"),
+];
+
+/// annotations the compiler understands, (name, declarations it may be placed on, argument kinds)
+///
+/// declarations are named by their keyword, `method` is a `fn` inside a `class` or `extend`
+/// block, `string` arguments are string literals and `name` arguments identifiers or types
+pub const ANNOTATIONS: [(&str, &[&str], &[&str]); 3] = [
+	("entry", &["fn"], &[]),
+	("extern", &["fn"], &["string"]),
+	("link", &["class"], &["name"]),
+];
@@ -0,0 +1,2738 @@
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::path::Path;
+
+use crate::builtins;
+use crate::lexer::{NumberValue, TokenType};
+use crate::parser;
+use crate::typechecker;
+use crate::util;
+
+use builtins::BUILTIN_ERRORS;
+use parser::{
+	AnnotationArgument, Block, Class, Enum, Expression, ExpressionKind, FormatPart, Function, Item,
+	ItemKind, Literal, MatchArm, Param, Pattern, Program, Statement, StatementKind, Struct, Type,
+	Variable,
+};
+use typechecker::{Primitive, Ty, TypeChecker};
+use util::ApolloError;
+use util::print_debug;
+
+/// headers every generated file includes, `#[extern]` functions can add more
+const HEADERS: [&str; 11] = [
+	"ctype.h",
+	"math.h",
+	"setjmp.h",
+	"stdarg.h",
+	"stdbool.h",
+	"stddef.h",
+	"stdint.h",
+	"stdio.h",
+	"stdlib.h",
+	"string.h",
+	"time.h",
+];
+
+/// C keywords and library names a generated identifier must not collide with
+const RESERVED: [&str; 59] = [
+	"auto", "break", "case", "char", "const", "continue", "default", "do", "double", "else", "enum",
+	"extern", "float", "for", "goto", "if", "inline", "int", "long", "register", "restrict",
+	"return", "short", "signed", "sizeof", "static", "struct", "switch", "typedef", "union",
+	"unsigned", "void", "volatile", "while", "bool", "true", "false", "main", "exit", "printf",
+	"puts", "putchar", "getchar", "malloc", "free", "strlen", "strcmp", "memcpy", "memset", "rand",
+	"srand", "time", "ceil", "floor", "log2", "sqrt", "pow", "index", "random",
+];
+
+/// support code placed in front of every generated file, it only uses the error ids and tables
+/// emitted right before it
+const RUNTIME: &str = r#"typedef struct {
+	char* data;
+	size_t len;
+} slice_char;
+
+typedef struct {
+	int type;
+	const char* message;
+} apollo_error;
+
+typedef struct apollo_handler {
+	jmp_buf env;
+	struct apollo_handler* prev;
+} apollo_handler;
+
+static apollo_handler* apollo_handlers = NULL;
+static apollo_error apollo_current_error;
+
+static inline apollo_error apollo_make_error(int type, const char* message) {
+	apollo_error error = { type, message };
+	return error;
+}
+
+static inline _Noreturn void apollo_throw_error(apollo_error error) {
+	apollo_current_error = error;
+	if (apollo_handlers == NULL) {
+		fprintf(stderr, "Uncaught %s: %s\n", apollo_error_name(error.type), error.message);
+		exit(1);
+	}
+	longjmp(apollo_handlers->env, 1);
+}
+
+static inline _Noreturn void apollo_throw(int type, const char* message) {
+	apollo_throw_error(apollo_make_error(type, message));
+}
+
+static inline bool apollo_error_is(int type, int ancestor) {
+	for (; type >= 0; type = apollo_error_parent(type)) {
+		if (type == ancestor) {
+			return true;
+		}
+	}
+	return false;
+}
+
+static inline char* apollo_format(const char* format, ...) {
+	va_list args;
+	va_start(args, format);
+	int length = vsnprintf(NULL, 0, format, args);
+	va_end(args);
+	char* result = malloc((size_t)length + 1);
+	va_start(args, format);
+	vsnprintf(result, (size_t)length + 1, format, args);
+	va_end(args);
+	return result;
+}
+
+static inline size_t apollo_check_index(int64_t index, size_t length) {
+	if (index < 0 || (uint64_t)index >= length) {
+		apollo_throw(APOLLO_IndexOutOfBounds, apollo_format("Index %lld is out of bounds for a length of %zu", (long long)index, length));
+	}
+	return (size_t)index;
+}
+
+static inline char* apollo_string_new(size_t length) {
+	return calloc(length + 1, 1);
+}
+
+static inline char* apollo_string_copy(const char* text, size_t length) {
+	char* result = apollo_string_new(length);
+	memcpy(result, text, length);
+	return result;
+}
+
+static inline char* apollo_string_concat(const char* left, const char* right) {
+	size_t left_length = strlen(left);
+	size_t right_length = strlen(right);
+	char* result = apollo_string_new(left_length + right_length);
+	memcpy(result, left, left_length);
+	memcpy(result + left_length, right, right_length);
+	return result;
+}
+
+static inline char* apollo_string_truncate(const char* text, int64_t length) {
+	size_t current = strlen(text);
+	size_t limit = length < 0 ? 0 : (size_t)length;
+	return apollo_string_copy(text, current < limit ? current : limit);
+}
+
+static inline char* apollo_string_strip(const char* text) {
+	size_t start = 0;
+	size_t end = strlen(text);
+	while (start < end && isspace((unsigned char)text[start])) {
+		start++;
+	}
+	while (end > start && isspace((unsigned char)text[end - 1])) {
+		end--;
+	}
+	return apollo_string_copy(text + start, end - start);
+}
+
+static inline char* apollo_string_lowercase(const char* text) {
+	char* result = apollo_string_copy(text, strlen(text));
+	for (char* c = result; *c != '\0'; c++) {
+		*c = (char)tolower((unsigned char)*c);
+	}
+	return result;
+}
+
+static inline slice_char apollo_string_slice(const char* text) {
+	slice_char slice = { apollo_string_copy(text, strlen(text)), strlen(text) };
+	return slice;
+}
+
+static inline char* apollo_string_at(char* text, int64_t index) {
+	return &text[apollo_check_index(index, strlen(text))];
+}
+
+static inline char* slice_char_at(slice_char slice, int64_t index) {
+	return &slice.data[apollo_check_index(index, slice.len)];
+}
+
+static inline uint32_t apollo_string_parse_int(const char* text) {
+	uint32_t value = 0;
+	if (*text == '\0') {
+		apollo_throw(APOLLO_IllegalArgument, "Cannot parse an empty string into an integer");
+	}
+	for (const char* c = text; *c != '\0'; c++) {
+		if (!isdigit((unsigned char)*c)) {
+			apollo_throw(APOLLO_IllegalArgument, apollo_format("\"%s\" is not an integer", text));
+		}
+		value = value * 10 + (uint32_t)(*c - '0');
+	}
+	return value;
+}
+
+static inline char* apollo_read_line(void) {
+	size_t length = 0;
+	size_t capacity = 64;
+	char* line = malloc(capacity);
+	int c;
+	while ((c = getchar()) != EOF && c != '\n') {
+		if (length + 1 == capacity) {
+			capacity *= 2;
+			line = realloc(line, capacity);
+		}
+		line[length++] = (char)c;
+	}
+	line[length] = '\0';
+	return line;
+}
+
+static inline char* apollo_prompt(const char* message) {
+	fputs(message, stdout);
+	fflush(stdout);
+	return apollo_read_line();
+}
+
+static inline char* apollo_char_to_string(char c) {
+	char* result = apollo_string_new(1);
+	result[0] = c;
+	return result;
+}
+
+static inline char* apollo_int_to_string(long long value) {
+	return apollo_format("%lld", value);
+}
+
+static inline char* apollo_uint_to_string(unsigned long long value) {
+	return apollo_format("%llu", value);
+}
+
+static inline char* apollo_float_to_string(double value) {
+	return apollo_format("%g", value);
+}
+
+static inline bool apollo_char_is_digit(char c) {
+	return isdigit((unsigned char)c) != 0;
+}
+
+static inline int64_t apollo_clamp(int64_t value, int64_t low, int64_t high) {
+	return value < low ? low : value > high ? high : value;
+}
+
+static inline int64_t apollo_random(int64_t low, int64_t high) {
+	if (high <= low) {
+		return low;
+	}
+	return low + rand() % (high - low + 1);
+}
+
+static inline void apollo_shuffle(void* data, size_t length, size_t size) {
+	unsigned char* bytes = data;
+	unsigned char* temp = malloc(size);
+	for (size_t i = length; i > 1; i--) {
+		size_t j = (size_t)rand() % i;
+		memcpy(temp, bytes + (i - 1) * size, size);
+		memcpy(bytes + (i - 1) * size, bytes + j * size, size);
+		memcpy(bytes + j * size, temp, size);
+	}
+	free(temp);
+}
+"#;
+
+/// Walks the checked AST and writes it out as a C11 source file
+pub struct Compiler<'a> {
+	program: &'a Program,
+	debug_mode: u8,
+	logging: bool,
+	output_dir: String,
+
+	types: TypeChecker<'a>,
+	classes: HashMap<String, &'a Class>,
+	structs: HashMap<String, &'a Struct>,
+	enums: Vec<&'a Enum>,
+	functions: HashMap<String, &'a Function>,
+	extensions: HashMap<String, Vec<&'a Function>>, // extend target -> added methods
+	externs: HashMap<String, String>,               // apollo name -> C name
+	globals: HashSet<String>,
+	errors: Vec<(String, Option<String>)>, // error classes in id order, (name, parent)
+	entry: Option<String>,
+	includes: BTreeSet<String>,
+
+	// sections of the generated file
+	definitions: Vec<String>, // structs and wrapper types, in dependency order
+	helpers: Vec<String>,     // functions of the wrapper types
+	defined: HashSet<String>,
+	prototypes: Vec<String>,
+	variables: Vec<String>,
+	initializers: Vec<String>, // statements of `apollo_init_globals`
+	output: Vec<String>,       // emitted functions
+
+	// state of the function being emitted
+	body: String,
+	indent: usize,
+	locals: Vec<HashMap<String, String>>, // apollo name -> C expression
+	reads: HashSet<String>,
+	owner: Option<String>, // class or extend target of the current method
+	self_pointer: bool,
+	in_entry: bool,
+	returns: Ty,
+	handlers: Vec<String>,     // catch handlers whose body is being emitted
+	retry_labels: Vec<String>, // catch handlers whose handler block is being emitted
+	loop_exits: Vec<(String, usize, bool)>, // (label, handler depth, used) of inlined closures
+	counter: usize,
+	position: (usize, usize),
+}
+
+impl<'a> Compiler<'a> {
+	pub fn new(program: &'a Program, debug_mode: u8, logging: bool, output_dir: String) -> Self {
+		Compiler {
+			program,
+			debug_mode,
+			logging,
+			output_dir,
+			types: TypeChecker::new(program, 0, false, String::new()),
+			classes: HashMap::new(),
+			structs: HashMap::new(),
+			enums: Vec::new(),
+			functions: HashMap::new(),
+			extensions: HashMap::new(),
+			externs: HashMap::new(),
+			globals: HashSet::new(),
+			errors: Vec::new(),
+			entry: None,
+			includes: BTreeSet::new(),
+			definitions: Vec::new(),
+			helpers: Vec::new(),
+			defined: HashSet::new(),
+			prototypes: Vec::new(),
+			variables: Vec::new(),
+			initializers: Vec::new(),
+			output: Vec::new(),
+			body: String::new(),
+			indent: 0,
+			locals: Vec::new(),
+			reads: HashSet::new(),
+			owner: None,
+			self_pointer: false,
+			in_entry: false,
+			returns: Ty::Unit,
+			handlers: Vec::new(),
+			retry_labels: Vec::new(),
+			loop_exits: Vec::new(),
+			counter: 0,
+			position: (0, 0),
+		}
+	}
+
+	/// writes `<output dir>/<file name>.c` and returns its path
+	pub fn begin(&mut self) -> Result<String, ApolloError> {
+		if self.debug_mode > 0 {
+			print_debug(
+				"Generating C for file: ",
+				&self.program.filepath,
+				self.logging,
+				&self.output_dir,
+			);
+		}
+		self.types.declare();
+		self.collect();
+
+		let program = self.program;
+		for item in &program.items {
+			self.position = (item.line, item.column);
+			match &item.kind {
+				ItemKind::Struct(s) => self.define_record(&s.name)?,
+				ItemKind::Class(class) if self.classes.contains_key(&class.name) => {
+					self.define_record(&class.name)?
+				}
+				_ => {}
+			}
+		}
+		for item in &program.items {
+			self.item(item)?;
+		}
+		if self.entry.is_none() {
+			return Err(ApolloError::new(
+				format!(
+					"{} has no #[entry] function to turn into main",
+					self.program.filepath
+				),
+				None,
+				None,
+				None,
+			));
+		}
+
+		let name = Path::new(&self.program.filepath)
+			.file_stem()
+			.map(|s| s.to_string_lossy().to_string())
+			.unwrap_or_else(|| "main".to_string());
+		let path = format!("{}/{name}.c", self.output_dir.trim_end_matches('/'));
+		std::fs::write(&path, self.assemble())
+			.map_err(|e| ApolloError::new(format!("Failed to write {path}: {e}"), None, None, None))?;
+		if self.debug_mode > 1 {
+			print_debug("Wrote C source to: ", &path, self.logging, &self.output_dir);
+		}
+		Ok(path)
+	}
+
+	/// indexes the declarations of the file and assigns every error class an id
+	fn collect(&mut self) {
+		let program = self.program;
+		let mut classes = Vec::new();
+		for item in &program.items {
+			match &item.kind {
+				// `class String` adds methods to the primitive instead of declaring a record
+				ItemKind::Class(class) if typechecker::receiver_primitive(&class.name).is_some() => {
+					let methods = self.extensions.entry(class.name.clone()).or_default();
+					methods.extend(class.members.iter().filter_map(|m| match &m.kind {
+						ItemKind::Function(function) => Some(function),
+						_ => None,
+					}));
+				}
+				ItemKind::Class(class) => {
+					self.classes.insert(class.name.clone(), class);
+					classes.push(class.name.clone());
+				}
+				ItemKind::Struct(s) => {
+					self.structs.insert(s.name.clone(), s);
+				}
+				ItemKind::Enum(e) => self.enums.push(e),
+				ItemKind::Extend(extend) => {
+					if let Type::Named { name, .. } = &extend.target {
+						let methods = self.extensions.entry(name.clone()).or_default();
+						methods.extend(extend.members.iter().filter_map(|m| match &m.kind {
+							ItemKind::Function(function) => Some(function),
+							_ => None,
+						}));
+					}
+				}
+				ItemKind::Function(function) => {
+					for annotation in &item.annotations {
+						match annotation.name.as_str() {
+							"entry" => self.entry = Some(function.name.clone()),
+							"extern" => {
+								// `stdio.printf` is `printf` from `<stdio.h>`, the analyzer already rejected other arguments
+								let Some(AnnotationArgument::String(path)) = annotation.arguments.first() else {
+									continue;
+								};
+								let (header, symbol) = path.rsplit_once('.').unwrap_or(("", path));
+								if !header.is_empty() {
+									self
+										.includes
+										.insert(format!("{}.h", header.replace('.', "/")));
+								}
+								self
+									.externs
+									.insert(function.name.clone(), symbol.to_string());
+							}
+							_ => {}
+						}
+					}
+					self.functions.insert(function.key(), function);
+				}
+				ItemKind::Variable(variable) => {
+					self.globals.insert(variable.name.clone());
+				}
+				ItemKind::Use(_) | ItemKind::Error => {}
+			}
+		}
+
+		self.errors = BUILTIN_ERRORS
+			.iter()
+			.map(|(name, parent)| (name.to_string(), parent.map(str::to_string)))
+			.collect();
+		for class in classes {
+			if self.is_error(&class) {
+				let parent = self.parent_of(&class);
+				self.errors.push((class, parent));
+			}
+		}
+	}
+
+	fn item(&mut self, item: &'a Item) -> Result<(), ApolloError> {
+		self.position = (item.line, item.column);
+		match &item.kind {
+			ItemKind::Function(function) => self.function(function, None, item),
+			ItemKind::Class(class) => {
+				if self.classes.contains_key(&class.name) {
+					self.constructor(class)?;
+				}
+				for member in &class.members {
+					if let ItemKind::Function(function) = &member.kind {
+						self.position = (member.line, member.column);
+						self.function(function, Some(&class.name), member)?;
+					}
+				}
+				Ok(())
+			}
+			ItemKind::Extend(extend) => {
+				let Type::Named { name, .. } = &extend.target else {
+					return Err(self.error("The C backend can only extend named types"));
+				};
+				for member in &extend.members {
+					if let ItemKind::Function(function) = &member.kind {
+						self.position = (member.line, member.column);
+						self.function(function, Some(name), member)?;
+					}
+				}
+				Ok(())
+			}
+			ItemKind::Variable(variable) => self.global(variable),
+			ItemKind::Struct(_) | ItemKind::Enum(_) | ItemKind::Use(_) => Ok(()),
+			ItemKind::Error => Err(self.error("Can't compile a declaration that failed to parse")),
+		}
+	}
+
+	/// top-level variables are initialized at the start of `main` unless they are literals
+	fn global(&mut self, variable: &Variable) -> Result<(), ApolloError> {
+		let ty = match (&variable.ty, &variable.value) {
+			(Some(ty), _) => Ty::from_type(ty),
+			(None, Some(value)) => self.type_of(value).concrete(),
+			(None, None) => Ty::Unknown,
+		};
+		self
+			.types
+			.define(&variable.name, ty.clone(), variable.constant);
+		let c_type = self.c_type(&ty)?;
+		let name = c_ident(&variable.name);
+		match &variable.value {
+			Some(Expression {
+				kind: ExpressionKind::Literal(literal),
+				..
+			}) => {
+				let value = self.literal(literal);
+				self.variables.push(format!("{c_type} {name} = {value};"));
+			}
+			Some(value) => {
+				let value = self.value(value, Some(&ty))?;
+				self.variables.push(format!("{c_type} {name};"));
+				self.initializers.push(format!("\t{name} = {value};"));
+			}
+			None => {
+				let value = self.zero_value(&ty)?;
+				self.variables.push(format!("{c_type} {name};"));
+				self.initializers.push(format!("\t{name} = {value};"));
+			}
+		}
+		Ok(())
+	}
+
+	/// the implicit constructor of a class, it takes the class parameters in order
+	fn constructor(&mut self, class: &'a Class) -> Result<(), ApolloError> {
+		let name = &class.name;
+		let params = self.params(&class.params)?;
+		let signature = format!("{name} {name}_create({params})");
+		self.prototypes.push(format!("{signature};"));
+
+		self.start_function(Some(name), true);
+		for param in &class.params {
+			self.define_local(
+				&param.name,
+				c_ident(&param.name),
+				Ty::from_type(&param.ty),
+				true,
+			);
+		}
+		let mut assignments = Vec::new();
+		for param in &class.params {
+			let field = c_ident(&param.name);
+			assignments.push(format!("self->{field} = {field};"));
+		}
+		for member in &class.members {
+			let ItemKind::Variable(variable) = &member.kind else {
+				continue;
+			};
+			if class.params.iter().any(|p| p.name == variable.name) && variable.value.is_none() {
+				continue;
+			}
+			self.position = (member.line, member.column);
+			let ty = self.field_type(variable);
+			let value = match &variable.value {
+				Some(value) => self.value(value, Some(&ty))?,
+				None if ty == Ty::Primitive(Primitive::String) => self.zero_value(&ty)?,
+				None => continue,
+			};
+			assignments.push(format!("self->{} = {value};", c_ident(&variable.name)));
+		}
+
+		self.line(&format!("{name} created;"));
+		self.line("memset(&created, 0, sizeof created);");
+		if !assignments.is_empty() {
+			self.line(&format!("{name}* self = &created;"));
+			for assignment in &assignments {
+				self.line(assignment);
+			}
+		}
+		self.line("return created;");
+		self.finish_function(&signature);
+		Ok(())
+	}
+
+	fn function(
+		&mut self,
+		function: &'a Function,
+		owner: Option<&str>,
+		item: &Item,
+	) -> Result<(), ApolloError> {
+		if owner.is_none() && self.externs.contains_key(&function.name) {
+			return Ok(());
+		}
+		let Some(body) = &function.body else {
+			return Err(ApolloError::at(
+				format!(
+					"{} has no body, only #[extern] functions can leave it out",
+					function.name
+				),
+				item.line,
+				item.column,
+			));
+		};
+		let is_entry = owner.is_none() && self.entry.as_deref() == Some(function.name.as_str());
+		let returns = function
+			.return_type
+			.as_ref()
+			.map(Ty::from_type)
+			.unwrap_or(Ty::Unit);
+
+		let receiver = owner.map(|o| (o, typechecker::receiver_primitive(o)));
+		let is_static = function.name == "new" && function.operator.is_none();
+		let mut params = Vec::new();
+		match receiver {
+			Some((_, Some(primitive))) => params.push(format!("{} self", primitive_c(primitive))),
+			Some((owner, None)) if !is_static => params.push(format!("{owner}* self")),
+			_ => {}
+		}
+		if !function.params.is_empty() {
+			if is_entry {
+				return Err(ApolloError::at(
+					"The #[entry] function cannot take parameters".to_string(),
+					item.line,
+					item.column,
+				));
+			}
+			params.push(self.params(&function.params)?);
+		}
+		let params = if params.is_empty() {
+			"void".to_string()
+		} else {
+			params.join(", ")
+		};
+		let signature = if is_entry {
+			"int main(void)".to_string()
+		} else {
+			let name = match owner {
+				Some(owner) => method_name(owner, function),
+				None => c_ident(&function.name),
+			};
+			format!("{} {name}({params})", self.c_type(&returns)?)
+		};
+		if !is_entry {
+			self.prototypes.push(format!("{signature};"));
+		}
+
+		self.start_function(owner, !is_static);
+		self.in_entry = is_entry;
+		if let Some((owner, primitive)) = receiver {
+			let self_type = match primitive {
+				Some(primitive) => Ty::Primitive(primitive),
+				None => Ty::Named {
+					name: owner.to_string(),
+					generics: Vec::new(),
+				},
+			};
+			self.types.set_self_type(Some(self_type));
+			self.self_pointer = primitive.is_none();
+		}
+		self.returns = returns.clone();
+		for param in &function.params {
+			self.define_local(
+				&param.name,
+				c_ident(&param.name),
+				Ty::from_type(&param.ty),
+				param.constant,
+			);
+		}
+		collect_reads(body, &mut self.reads);
+		if is_entry {
+			self.line("srand((unsigned)time(NULL));");
+			if !self.initializers.is_empty() {
+				self.line("apollo_init_globals();");
+			}
+		}
+		let result = self.statements(body);
+		self.types.set_self_type(None);
+		result?;
+
+		let ends = body
+			.statements
+			.last()
+			.is_some_and(|s| matches!(s.kind, StatementKind::Return(_) | StatementKind::Panic(_)));
+		if is_entry {
+			if !ends {
+				self.line("return 0;");
+			}
+		} else if returns != Ty::Unit && !ends {
+			self.line(&format!(
+				"apollo_throw(APOLLO_IllegalState, \"{} ended without returning a value\");",
+				function.name
+			));
+		}
+		self.finish_function(&signature);
+		Ok(())
+	}
+
+	fn params(&mut self, params: &[Param]) -> Result<String, ApolloError> {
+		let mut out = Vec::new();
+		for param in params {
+			if param.variadic {
+				return Err(ApolloError::at(
+					"The C backend only supports variadic parameters on #[extern] functions".to_string(),
+					param.line,
+					param.column,
+				));
+			}
+			let ty = self.c_type(&Ty::from_type(&param.ty))?;
+			out.push(format!("{ty} {}", c_ident(&param.name)));
+		}
+		Ok(out.join(", "))
+	}
+
+	fn start_function(&mut self, owner: Option<&str>, self_pointer: bool) {
+		self.body.clear();
+		self.indent = 1;
+		self.reads.clear();
+		self.owner = owner.map(str::to_string);
+		self.self_pointer = self_pointer && owner.is_some();
+		self.in_entry = false;
+		self.push_scope();
+	}
+
+	fn finish_function(&mut self, signature: &str) {
+		self.pop_scope();
+		self.owner = None;
+		let body = std::mem::take(&mut self.body);
+		self.output.push(format!("{signature} {{\n{body}}}\n"));
+	}
+
+	// statements
+
+	fn block(&mut self, block: &Block) -> Result<(), ApolloError> {
+		self.indent += 1;
+		self.push_scope();
+		let result = self.statements(block);
+		self.pop_scope();
+		self.indent -= 1;
+		result
+	}
+
+	fn statements(&mut self, block: &Block) -> Result<(), ApolloError> {
+		for statement in &block.statements {
+			self.statement(statement)?;
+		}
+		Ok(())
+	}
+
+	fn statement(&mut self, statement: &Statement) -> Result<(), ApolloError> {
+		self.position = (statement.line, statement.column);
+		match &statement.kind {
+			StatementKind::Variable(variable) => self.variable(variable),
+			StatementKind::Expression(expression) => self.expression_statement(expression),
+			StatementKind::Return(value) => self.return_statement(value.as_ref()),
+			StatementKind::If { .. } => self.if_statement(statement, ""),
+			StatementKind::While { condition, body } => {
+				let condition = self.value(condition, None)?;
+				self.line(&format!("while ({}) {{", strip_parens(&condition)));
+				self.block(body)?;
+				self.line("}");
+				Ok(())
+			}
+			StatementKind::For { arguments, body } => self.for_statement(arguments, body),
+			StatementKind::Panic(value) => self.panic(value),
+			StatementKind::Block(block) => {
+				self.line("{");
+				self.block(block)?;
+				self.line("}");
+				Ok(())
+			}
+			StatementKind::Catch {
+				body,
+				error_type,
+				binding,
+				handler,
+			} => self.catch(body, error_type, binding, handler),
+			StatementKind::Retry => match self.retry_labels.last() {
+				Some(label) => {
+					let label = label.clone();
+					self.line(&format!("goto {label};"));
+					Ok(())
+				}
+				None => Err(self.error("'retry' can only be used inside of a catch handler")),
+			},
+			StatementKind::Error => Err(self.error("Can't compile a statement that failed to parse")),
+		}
+	}
+
+	fn variable(&mut self, variable: &Variable) -> Result<(), ApolloError> {
+		let ty = match (&variable.ty, &variable.value) {
+			(Some(ty), _) => Ty::from_type(ty),
+			(None, Some(value)) => self.type_of(value).concrete(),
+			(None, None) => Ty::Unknown,
+		};
+		let c_type = self.c_type(&ty)?;
+		let value = match (&variable.value, &variable.ty) {
+			// `var prog: string[progLen] = new;` reserves the string up front
+			(
+				Some(Expression {
+					kind: ExpressionKind::New { ty: None, .. },
+					..
+				}),
+				Some(Type::Sized { length, .. }),
+			) => format!("apollo_string_new((size_t){})", self.value(length, None)?),
+			(Some(value), _) => self.value(value, Some(&ty))?,
+			(None, _) => self.zero_value(&ty)?,
+		};
+		let name = self.local_name(&variable.name);
+		self.line(&format!("{c_type} {name} = {};", strip_parens(&value)));
+		if !self.reads.contains(&variable.name) {
+			self.line(&format!("(void){name};"));
+		}
+		self.define_local(&variable.name, name, ty, variable.constant);
+		Ok(())
+	}
+
+	fn expression_statement(&mut self, expression: &Expression) -> Result<(), ApolloError> {
+		match &expression.kind {
+			ExpressionKind::Match { subject, arms } => self.match_statement(subject, arms),
+			ExpressionKind::Call { callee, arguments } => {
+				if let ExpressionKind::Member { object, name } = &callee.kind
+					&& (name == "foreach" || name == "map")
+					&& let [
+						Expression {
+							kind: ExpressionKind::Closure { params, body },
+							..
+						},
+					] = arguments.as_slice()
+					&& matches!(
+						self.type_of(object).referent(),
+						Ty::Slice(_) | Ty::Array { .. } | Ty::Named { .. }
+					) {
+					return self.each(object, params, body, expression);
+				}
+				let call = self.expression(expression, None)?;
+				self.line(&format!("{};", strip_parens(&call)));
+				Ok(())
+			}
+			ExpressionKind::Assign { .. }
+			| ExpressionKind::Unary {
+				operator: TokenType::INCREMENT | TokenType::DECREMENT,
+				..
+			}
+			| ExpressionKind::Postfix { .. } => {
+				let value = self.expression(expression, None)?;
+				self.line(&format!("{};", strip_parens(&value)));
+				Ok(())
+			}
+			_ => {
+				let value = self.expression(expression, None)?;
+				self.line(&format!("(void){value};"));
+				Ok(())
+			}
+		}
+	}
+
+	fn return_statement(&mut self, value: Option<&Expression>) -> Result<(), ApolloError> {
+		// inside an inlined closure `return` moves on to the next element
+		if let Some((label, depth, _)) = self.loop_exits.last().cloned() {
+			if let Some(value) = value {
+				let value = self.expression(value, None)?;
+				self.line(&format!("(void){value};"));
+			}
+			if self.handlers.len() > depth {
+				let handler = self.handlers[depth].clone();
+				self.line(&format!("apollo_handlers = {handler}.prev;"));
+			}
+			if let Some(exit) = self.loop_exits.last_mut() {
+				exit.2 = true;
+			}
+			self.line(&format!("goto {label};"));
+			return Ok(());
+		}
+
+		let returns = self.returns.clone();
+		let value = match value {
+			Some(value) => Some(self.value(value, Some(&returns))?),
+			None => None,
+		};
+		let restore = self
+			.handlers
+			.first()
+			.map(|handler| format!("apollo_handlers = {handler}.prev;"));
+		match (value, restore) {
+			(None, None) if self.in_entry => self.line("return 0;"),
+			(None, None) => self.line("return;"),
+			(None, Some(restore)) => {
+				self.line(&restore);
+				self.line(if self.in_entry {
+					"return 0;"
+				} else {
+					"return;"
+				});
+			}
+			(Some(value), None) if self.in_entry => {
+				self.line(&format!("return (int){value};"));
+			}
+			(Some(value), None) => self.line(&format!("return {};", strip_parens(&value))),
+			(Some(value), Some(restore)) => {
+				let c_type = if self.in_entry {
+					"int".to_string()
+				} else {
+					self.c_type(&returns)?
+				};
+				let result = self.temp("result");
+				self.line("{");
+				self.indent += 1;
+				self.line(&format!("{c_type} {result} = {};", strip_parens(&value)));
+				self.line(&restore);
+				self.line(&format!("return {result};"));
+				self.indent -= 1;
+				self.line("}");
+			}
+		}
+		Ok(())
+	}
+
+	/// `else if` chains stay flat instead of nesting a block per branch
+	fn if_statement(&mut self, statement: &Statement, prefix: &str) -> Result<(), ApolloError> {
+		let StatementKind::If {
+			condition,
+			then_block,
+			else_branch,
+		} = &statement.kind
+		else {
+			return Ok(());
+		};
+		let condition = self.value(condition, None)?;
+		self.line(&format!("{prefix}if ({}) {{", strip_parens(&condition)));
+		self.block(then_block)?;
+		match else_branch.as_deref() {
+			None => self.line("}"),
+			Some(
+				branch @ Statement {
+					kind: StatementKind::If { .. },
+					..
+				},
+			) => self.if_statement(branch, "} else ")?,
+			Some(Statement {
+				kind: StatementKind::Block(block),
+				..
+			}) => {
+				self.line("} else {");
+				self.block(block)?;
+				self.line("}");
+			}
+			Some(other) => {
+				self.line("} else {");
+				self.indent += 1;
+				self.statement(other)?;
+				self.indent -= 1;
+				self.line("}");
+			}
+		}
+		Ok(())
+	}
+
+	/// `for(@i, n => n < times) { ... }` counts `i` up while the lambda holds
+	fn for_statement(&mut self, arguments: &[Expression], body: &Block) -> Result<(), ApolloError> {
+		let [counter, condition] = arguments else {
+			return Err(
+				self.error("The C backend only supports for loops of the form for(@i, n => ...)"),
+			);
+		};
+		let ExpressionKind::Lambda { param, body: test } = &condition.kind else {
+			return Err(self.error("The second argument of a for loop has to be a lambda"));
+		};
+		let counter = match &counter.kind {
+			ExpressionKind::Reference(inner) => inner,
+			_ => counter,
+		};
+		let ty = self.type_of(counter).referent().clone();
+		let counter = self.value(counter, None)?;
+
+		self.push_scope();
+		self.define_local(param, counter.clone(), ty, true);
+		let test = self.value(test, None);
+		self.pop_scope();
+		let test = test?;
+
+		self.line(&format!("for (; {}; {counter}++) {{", strip_parens(&test)));
+		self.block(body)?;
+		self.line("}");
+		Ok(())
+	}
+
+	/// `cmds.foreach { |cmd: char| ... }` and `map` are inlined as a loop over the elements
+	fn each(
+		&mut self,
+		object: &Expression,
+		params: &[Param],
+		body: &Block,
+		at: &Expression,
+	) -> Result<(), ApolloError> {
+		let object_ty = self.type_of(object);
+		let collection = object_ty.referent().clone();
+		let element = match &collection {
+			Ty::Slice(element) | Ty::Array { element, .. } => (**element).clone(),
+			Ty::Named { name, generics } if name == "Vector" => {
+				generics.first().cloned().unwrap_or(Ty::Unknown)
+			}
+			_ => return Err(unsupported(&format!("iterating over {collection}"), at)),
+		};
+		let [param] = params else {
+			return Err(unsupported("closures that take more than one element", at));
+		};
+
+		let items = self.temp("items");
+		let index = self.temp("i");
+		let next = self.temp("next");
+		let c_type = self.c_type(&collection)?;
+		self.line("{");
+		self.indent += 1;
+		let (data, length) = match &collection {
+			Ty::Array { length, .. } => {
+				let pointer = match object_ty {
+					Ty::Reference(_) => self.expression(object, None)?,
+					_ if is_lvalue(object) => format!("&{}", self.expression(object, None)?),
+					_ => return Err(unsupported("iterating over temporary arrays", at)),
+				};
+				self.line(&format!("{c_type}* {items} = {pointer};"));
+				(format!("{items}->data"), length.unwrap_or(0).to_string())
+			}
+			_ => {
+				let value = self.value(object, None)?;
+				self.line(&format!("{c_type} {items} = {};", strip_parens(&value)));
+				(format!("{items}.data"), format!("{items}.len"))
+			}
+		};
+		self.line(&format!(
+			"for (size_t {index} = 0; {index} < {length}; {index}++) {{"
+		));
+		self.indent += 1;
+		self.push_scope();
+		let declared = Ty::from_type(&param.ty);
+		let element_c = self.c_type(&element)?;
+		let name = self.local_name(&param.name);
+		let ty = match declared {
+			Ty::Reference(_) => {
+				self.line(&format!("{element_c}* {name} = &{data}[{index}];"));
+				Ty::Reference(Box::new(element))
+			}
+			_ => {
+				self.line(&format!("{element_c} {name} = {data}[{index}];"));
+				element
+			}
+		};
+		if !self.reads.contains(&param.name) {
+			self.line(&format!("(void){name};"));
+		}
+		self.define_local(&param.name, name, ty, param.constant);
+		self
+			.loop_exits
+			.push((next.clone(), self.handlers.len(), false));
+		let result = self.statements(body);
+		let (_, _, used) = self.loop_exits.pop().unwrap_or_default();
+		self.pop_scope();
+		result?;
+		if used {
+			self.line(&format!("{next}:;"));
+		}
+		self.indent -= 1;
+		self.line("}");
+		self.indent -= 1;
+		self.line("}");
+		Ok(())
+	}
+
+	/// `match` becomes an if chain over the subject
+	fn match_statement(
+		&mut self,
+		subject: &Expression,
+		arms: &[MatchArm],
+	) -> Result<(), ApolloError> {
+		let ty = self.type_of(subject).referent().clone();
+		let value = self.value(subject, None)?;
+		let simple = matches!(
+			subject.kind,
+			ExpressionKind::Identifier(_) | ExpressionKind::Literal(_) | ExpressionKind::SelfValue
+		);
+		let subject = if simple {
+			value
+		} else {
+			let name = self.temp("subject");
+			let c_type = self.c_type(&ty)?;
+			self.line("{");
+			self.indent += 1;
+			self.line(&format!("{c_type} {name} = {};", strip_parens(&value)));
+			name
+		};
+
+		let mut open = false;
+		for arm in arms {
+			self.position = (arm.line, arm.column);
+			let condition = match &arm.pattern {
+				Pattern::Wildcard => None,
+				Pattern::Literal(Literal::String(text)) => Some(format!(
+					"strcmp({subject}, \"{}\") == 0",
+					c_escape(text, '"')
+				)),
+				Pattern::Literal(literal) => Some(format!("{subject} == {}", self.literal(literal))),
+				Pattern::Variant(path) => {
+					let variant = path.last().cloned().unwrap_or_default();
+					let owner = match (path.len(), &ty) {
+						(2, _) => path[0].clone(),
+						(_, Ty::Named { name, .. }) => name.clone(),
+						_ => return Err(self.error("Cannot tell which enum this pattern belongs to")),
+					};
+					Some(format!("{subject} == {owner}_{variant}"))
+				}
+			};
+			match (condition, open) {
+				(Some(condition), false) => self.line(&format!("if ({condition}) {{")),
+				(Some(condition), true) => self.line(&format!("}} else if ({condition}) {{")),
+				(None, false) => self.line("{"),
+				(None, true) => self.line("} else {"),
+			}
+			self.arm_body(&arm.body)?;
+			open = true;
+			if matches!(arm.pattern, Pattern::Wildcard) {
+				break;
+			}
+		}
+		if open {
+			self.line("}");
+		}
+		if !simple {
+			self.indent -= 1;
+			self.line("}");
+		}
+		Ok(())
+	}
+
+	fn arm_body(&mut self, body: &Statement) -> Result<(), ApolloError> {
+		match &body.kind {
+			StatementKind::Block(block) => self.block(block),
+			_ => {
+				self.indent += 1;
+				self.push_scope();
+				let result = self.statement(body);
+				self.pop_scope();
+				self.indent -= 1;
+				result
+			}
+		}
+	}
+
+	fn panic(&mut self, value: &Expression) -> Result<(), ApolloError> {
+		let ty = self.type_of(value);
+		match ty.referent() {
+			Ty::Primitive(Primitive::String) => {
+				let message = self.value(value, None)?;
+				self.line(&format!(
+					"apollo_throw(APOLLO_Error, {});",
+					strip_parens(&message)
+				));
+			}
+			Ty::Named { name, .. } if self.is_builtin_error(name) => {
+				let error = self.value(value, None)?;
+				self.line(&format!("apollo_throw_error({});", strip_parens(&error)));
+			}
+			Ty::Named { name, .. } if self.classes.contains_key(name) => {
+				let name = name.clone();
+				let id = if self.is_error(&name) {
+					name.as_str()
+				} else {
+					"Error"
+				};
+				let error = self.temp("error");
+				let value = self.value(value, None)?;
+				let message = match self.find_method(&name, "panic") {
+					Some((owner, function)) if owner == name => {
+						format!("{}(&{error})", method_name(&owner, function))
+					}
+					Some((owner, function)) => {
+						format!("{}(({owner}*)&{error})", method_name(&owner, function))
+					}
+					None => format!("\"{name}\""),
+				};
+				self.line("{");
+				self.indent += 1;
+				self.line(&format!("{name} {error} = {};", strip_parens(&value)));
+				self.line(&format!("apollo_throw(APOLLO_{id}, {message});"));
+				self.indent -= 1;
+				self.line("}");
+			}
+			other => return Err(unsupported(&format!("panicking with {other}"), value)),
+		}
+		Ok(())
+	}
+
+	/// the body runs under a `setjmp` handler, `retry` jumps back to re-arm it
+	fn catch(
+		&mut self,
+		body: &Block,
+		error_type: &Type,
+		binding: &str,
+		handler: &Block,
+	) -> Result<(), ApolloError> {
+		let Type::Named { name: error, .. } = error_type else {
+			return Err(self.error("Only error classes can be caught"));
+		};
+		if !self.errors.iter().any(|(name, _)| name == error) {
+			return Err(self.error(&format!("{error} is not an error class")));
+		}
+		let id = self.temp("handler");
+		let label = format!("retry_{}", self.counter);
+		let retries = contains_retry(handler);
+
+		self.line("{");
+		self.indent += 1;
+		self.line(&format!("apollo_handler {id};"));
+		self.line(&format!("{id}.prev = apollo_handlers;"));
+		if retries {
+			self.indent -= 1;
+			self.line(&format!("{label}:"));
+			self.indent += 1;
+		}
+		self.line(&format!("apollo_handlers = &{id};"));
+		self.line(&format!("if (setjmp({id}.env) == 0) {{"));
+		self.handlers.push(id.clone());
+		let result = self.block(body);
+		self.handlers.pop();
+		result?;
+		self.indent += 1;
+		self.line(&format!("apollo_handlers = {id}.prev;"));
+		self.indent -= 1;
+		self.line("} else {");
+		self.indent += 1;
+		self.line(&format!("apollo_handlers = {id}.prev;"));
+		self.line(&format!(
+			"if (!apollo_error_is(apollo_current_error.type, APOLLO_{error})) {{"
+		));
+		self.line("\tapollo_throw_error(apollo_current_error);");
+		self.line("}");
+		self.indent -= 1;
+
+		self.push_scope();
+		let name = self.local_name(binding);
+		self.indent += 1;
+		self.line(&format!("apollo_error {name} = apollo_current_error;"));
+		if !self.reads.contains(binding) {
+			self.line(&format!("(void){name};"));
+		}
+		self.indent -= 1;
+		let error_ty = Ty::Named {
+			name: "Error".to_string(),
+			generics: Vec::new(),
+		};
+		self.define_local(binding, name, error_ty, true);
+		if retries {
+			self.retry_labels.push(label);
+		}
+		let result = self.block(handler);
+		if retries {
+			self.retry_labels.pop();
+		}
+		self.pop_scope();
+		result?;
+		self.line("}");
+		self.indent -= 1;
+		self.line("}");
+		Ok(())
+	}
+
+	// expressions
+
+	/// like `expression`, but looks through references, `@Player` parameters become `(*player)`
+	fn value(
+		&mut self,
+		expression: &Expression,
+		expected: Option<&Ty>,
+	) -> Result<String, ApolloError> {
+		let value = self.expression(expression, expected)?;
+		let is_reference = matches!(self.type_of(expression), Ty::Reference(_));
+		if is_reference && !matches!(expression.kind, ExpressionKind::Reference(_)) {
+			return Ok(format!("(*{value})"));
+		}
+		Ok(value)
+	}
+
+	fn expression(
+		&mut self,
+		expression: &Expression,
+		expected: Option<&Ty>,
+	) -> Result<String, ApolloError> {
+		match &expression.kind {
+			ExpressionKind::Literal(literal) => Ok(self.literal(literal)),
+			ExpressionKind::Identifier(name) => self.identifier(name, expression),
+			ExpressionKind::SelfValue => match (&self.owner, self.self_pointer) {
+				(Some(_), true) => Ok("(*self)".to_string()),
+				(Some(_), false) => Ok("self".to_string()),
+				(None, _) => Err(unsupported("'self' outside of a method", expression)),
+			},
+			ExpressionKind::Unary { operator, operand } => {
+				self.operator(*operator, operand, false, expression)
+			}
+			ExpressionKind::Postfix { operator, operand } => {
+				self.operator(*operator, operand, true, expression)
+			}
+			ExpressionKind::Binary {
+				left,
+				operator,
+				right,
+			} => self.binary(left, *operator, right, expression),
+			ExpressionKind::Assign {
+				target,
+				operator,
+				value,
+			} => self.assign(target, *operator, value),
+			ExpressionKind::Call { callee, arguments } => self.call(callee, arguments, expression),
+			ExpressionKind::Member { object, name } => self.member(object, name, expression),
+			ExpressionKind::Index { object, index } => self.index(object, index, expression),
+			ExpressionKind::New { ty, arguments } => {
+				let ty = match (ty, expected) {
+					(Some(ty), _) => Ty::from_type(ty),
+					(None, Some(expected)) => expected.clone(),
+					(None, None) => return Err(unsupported("'new' without a known type", expression)),
+				};
+				self.new_value(&ty, arguments, expression)
+			}
+			ExpressionKind::StructLiteral { name, fields } => {
+				let types = self.record_fields(name);
+				let mut values = Vec::new();
+				for (field, value) in fields {
+					let ty = types
+						.iter()
+						.find(|(n, _)| n == field)
+						.map(|(_, ty)| ty.clone());
+					let value = self.value(value, ty.as_ref())?;
+					values.push(format!(".{} = {}", c_ident(field), strip_parens(&value)));
+				}
+				Ok(format!("(({name}){{ {} }})", values.join(", ")))
+			}
+			ExpressionKind::ArrayLiteral(elements) => {
+				let ty = match expected {
+					Some(ty) => ty.clone(),
+					None => self.type_of(expression).concrete(),
+				};
+				let Ty::Array { element, .. } = &ty else {
+					return Err(unsupported(
+						&format!("array literals of type {ty}"),
+						expression,
+					));
+				};
+				let c_type = self.c_type(&ty)?;
+				let mut values = Vec::new();
+				for value in elements {
+					values.push(strip_parens(&self.value(value, Some(element))?).to_string());
+				}
+				Ok(format!("(({c_type}){{ {{ {} }} }})", values.join(", ")))
+			}
+			ExpressionKind::Cast { value, ty } => self.cast(value, &Ty::from_type(ty)),
+			ExpressionKind::Reference(inner) => {
+				if matches!(self.type_of(inner), Ty::Reference(_)) {
+					return self.expression(inner, None);
+				}
+				if matches!(inner.kind, ExpressionKind::SelfValue) && self.self_pointer {
+					return Ok("self".to_string());
+				}
+				if !is_lvalue(inner) {
+					return Err(unsupported("references to temporary values", expression));
+				}
+				Ok(format!("(&{})", self.expression(inner, None)?))
+			}
+			ExpressionKind::Closure { .. } | ExpressionKind::Lambda { .. } => Err(unsupported(
+				"closures outside of foreach, map and for loops",
+				expression,
+			)),
+			ExpressionKind::Match { .. } => Err(unsupported("match as a value", expression)),
+			ExpressionKind::Format(parts) => self.format(parts),
+		}
+	}
+
+	/// interpolated strings go through `apollo_format`, every embedded value is stringified first
+	fn format(&mut self, parts: &[FormatPart]) -> Result<String, ApolloError> {
+		let mut pattern = String::new();
+		let mut values = Vec::new();
+		for part in parts {
+			match part {
+				FormatPart::Text(text) => pattern.push_str(&c_escape(text, '"').replace('%', "%%")),
+				FormatPart::Expression(value) => {
+					let ty = self.type_of(value);
+					values.push(self.stringify(value, &ty)?);
+					pattern.push_str("%s");
+				}
+			}
+		}
+		Ok(format!(
+			"apollo_format(\"{pattern}\", {})",
+			values.join(", ")
+		))
+	}
+
+	fn literal(&self, literal: &Literal) -> String {
+		match literal {
+			Literal::Number { number, .. } => match *number {
+				NumberValue::Float(number) => format!("{number:?}"),
+				NumberValue::Integer(number) if number > i64::MAX as u64 => format!("{number}ULL"),
+				NumberValue::Integer(number) if number > i32::MAX as u64 => format!("{number}LL"),
+				NumberValue::Integer(number) => number.to_string(),
+			},
+			Literal::String(text) => format!("\"{}\"", c_escape(text, '"')),
+			// the lexer only lets ASCII through, so the literal fits a C char
+			Literal::Character(c) => format!("'{}'", c_escape(c, '\'')),
+			Literal::Boolean(b) => b.to_string(),
+		}
+	}
+
+	fn identifier(&mut self, name: &str, at: &Expression) -> Result<String, ApolloError> {
+		if let Some(local) = self.local(name) {
+			return Ok(local);
+		}
+		if let Some(owner) = self.owner.clone()
+			&& self.self_pointer
+			&& self
+				.record_fields(&owner)
+				.iter()
+				.any(|(field, _)| field == name)
+		{
+			return Ok(format!("self->{}", c_ident(name)));
+		}
+		if self.globals.contains(name) {
+			return Ok(c_ident(name));
+		}
+		Err(unsupported(&format!("using {name} as a value"), at))
+	}
+
+	fn operator(
+		&mut self,
+		operator: TokenType,
+		operand: &Expression,
+		postfix: bool,
+		at: &Expression,
+	) -> Result<String, ApolloError> {
+		let symbol = typechecker::operator_symbol(operator);
+		if let Ty::Named { name, .. } = self.type_of(operand).referent()
+			&& self.classes.contains_key(name)
+		{
+			let key = format!("{symbol}Unary");
+			let Some((owner, function)) = self.find_method(name, &key) else {
+				return Err(unsupported(&format!("'{symbol}' on {name}"), at));
+			};
+			let receiver = self.receiver(operand, name, &owner, at)?;
+			return Ok(format!("{}({receiver})", method_name(&owner, function)));
+		}
+		let value = self.value(operand, None)?;
+		Ok(if postfix {
+			format!("({value}{symbol})")
+		} else {
+			format!("({symbol}{value})")
+		})
+	}
+
+	fn binary(
+		&mut self,
+		left: &Expression,
+		operator: TokenType,
+		right: &Expression,
+		at: &Expression,
+	) -> Result<String, ApolloError> {
+		let left_ty = self.type_of(left).referent().clone();
+		let right_ty = self.type_of(right).referent().clone();
+		let string = Ty::Primitive(Primitive::String);
+		let symbol = typechecker::operator_symbol(operator);
+		if left_ty == string || right_ty == string {
+			if operator == TokenType::PLUS {
+				let left = self.stringify(left, &left_ty)?;
+				let right = self.stringify(right, &right_ty)?;
+				return Ok(format!("apollo_string_concat({left}, {right})"));
+			}
+			if matches!(
+				operator,
+				TokenType::EQL
+					| TokenType::BANGASSIGN
+					| TokenType::LESS
+					| TokenType::LESSEQL
+					| TokenType::GREATER
+					| TokenType::GREATEREQL
+			) {
+				let left = self.value(left, None)?;
+				let right = self.value(right, None)?;
+				return Ok(format!("(strcmp({left}, {right}) {symbol} 0)"));
+			}
+			return Err(unsupported(&format!("'{symbol}' on strings"), at));
+		}
+		let left = self.value(left, None)?;
+		let right = self.value(right, None)?;
+		Ok(format!("({left} {symbol} {right})"))
+	}
+
+	fn assign(
+		&mut self,
+		target: &Expression,
+		operator: TokenType,
+		value: &Expression,
+	) -> Result<String, ApolloError> {
+		let ty = self.type_of(target).referent().clone();
+		let target = self.value(target, None)?;
+		if operator == TokenType::ADDASSIGN && ty == Ty::Primitive(Primitive::String) {
+			let value_ty = self.type_of(value).referent().clone();
+			let value = self.stringify(value, &value_ty)?;
+			return Ok(format!(
+				"({target} = apollo_string_concat({target}, {value}))"
+			));
+		}
+		let value = self.value(value, Some(&ty))?;
+		let symbol = typechecker::operator_symbol(operator);
+		Ok(format!("({target} {symbol} {})", strip_parens(&value)))
+	}
+
+	fn call(
+		&mut self,
+		callee: &Expression,
+		arguments: &[Expression],
+		at: &Expression,
+	) -> Result<String, ApolloError> {
+		match &callee.kind {
+			ExpressionKind::Member { object, name } => self.method_call(object, name, arguments, at),
+			ExpressionKind::Identifier(name) => self.named_call(name, arguments, at),
+			_ => Err(unsupported("calling this kind of expression", at)),
+		}
+	}
+
+	fn named_call(
+		&mut self,
+		name: &str,
+		arguments: &[Expression],
+		at: &Expression,
+	) -> Result<String, ApolloError> {
+		if self.local(name).is_some() {
+			return Err(unsupported("calling closures", at));
+		}
+		// methods of the enclosing class or extension
+		if let Some(owner) = self.owner.clone()
+			&& let Some((method_owner, function)) = self.find_method(&owner, name)
+		{
+			let params = param_types(&function.params);
+			let mut values = self.arguments(arguments, &params)?;
+			let is_static = function.name == "new" && function.operator.is_none();
+			if !is_static {
+				let receiver = match (self.self_pointer, owner == method_owner) {
+					(true, true) => "self".to_string(),
+					(true, false) => format!("({method_owner}*)self"),
+					(false, _) => "self".to_string(),
+				};
+				values.insert(0, receiver);
+			}
+			return Ok(format!(
+				"{}({})",
+				method_name(&method_owner, function),
+				values.join(", ")
+			));
+		}
+		if let Some(function) = self.functions.get(name).copied() {
+			if let Some(symbol) = self.externs.get(name).cloned() {
+				let mut values = Vec::new();
+				for argument in arguments {
+					values.push(strip_parens(&self.value(argument, None)?).to_string());
+				}
+				return Ok(format!("{symbol}({})", values.join(", ")));
+			}
+			let params = param_types(&function.params);
+			let values = self.arguments(arguments, &params)?;
+			let name = if self.entry.as_deref() == Some(name) {
+				"main".to_string()
+			} else {
+				c_ident(name)
+			};
+			return Ok(format!("{name}({})", values.join(", ")));
+		}
+		if self.classes.contains_key(name) {
+			let ty = Ty::Named {
+				name: name.to_string(),
+				generics: Vec::new(),
+			};
+			return self.new_value(&ty, arguments, at);
+		}
+		if self.is_builtin_error(name) {
+			return self.make_error(name, arguments);
+		}
+		self.builtin_call(name, arguments, at)
+	}
+
+	/// the prelude and the `stdlib` modules
+	fn builtin_call(
+		&mut self,
+		name: &str,
+		arguments: &[Expression],
+		at: &Expression,
+	) -> Result<String, ApolloError> {
+		let mut values = Vec::new();
+		if name != "print" && name != "println" {
+			for argument in arguments {
+				values.push(strip_parens(&self.value(argument, None)?).to_string());
+			}
+		}
+		let value = match (name, values.as_slice()) {
+			("print" | "println", _) => {
+				let mut parts = Vec::new();
+				for argument in arguments {
+					parts.push(self.print(argument)?);
+				}
+				if name == "println" {
+					parts.push("putchar('\\n')".to_string());
+				}
+				if parts.is_empty() {
+					parts.push("(void)0".to_string());
+				}
+				format!("({})", parts.join(", "))
+			}
+			("prompt", [message]) => format!("apollo_prompt({message})"),
+			("readIn", []) => "apollo_read_line()".to_string(),
+			("exit", [code]) => format!("exit((int)({code}))"),
+			("clamp", [value, low, high]) => format!("apollo_clamp({value}, {low}, {high})"),
+			("random", [low, high]) => format!("apollo_random({low}, {high})"),
+			("ceil" | "floor", [value]) => format!("((uint32_t){name}((double)({value})))"),
+			("log2" | "sqrt", [value]) => format!("{name}((double)({value}))"),
+			("abs", [value]) => format!("llabs((long long)({value}))"),
+			("pow", [base, exponent]) => format!("pow((double)({base}), (double)({exponent}))"),
+			_ => return Err(unsupported(&format!("calling {name}"), at)),
+		};
+		Ok(value)
+	}
+
+	/// `print` writes each argument with the conversion for its type
+	fn print(&mut self, argument: &Expression) -> Result<String, ApolloError> {
+		let ty = self.type_of(argument).referent().clone();
+		let value = self.value(argument, None)?;
+		let value = strip_parens(&value);
+		Ok(match &ty {
+			Ty::Primitive(Primitive::String) => format!("fputs({value}, stdout)"),
+			Ty::Primitive(Primitive::Char) => format!("putchar({value})"),
+			Ty::Primitive(Primitive::Bool) => format!("fputs(({value}) ? \"true\" : \"false\", stdout)"),
+			Ty::Primitive(p) if p.is_unsigned() => {
+				format!("printf(\"%llu\", (unsigned long long)({value}))")
+			}
+			Ty::Primitive(p) if p.is_float() => format!("printf(\"%g\", (double)({value}))"),
+			Ty::Primitive(_) | Ty::IntLiteral => format!("printf(\"%lld\", (long long)({value}))"),
+			Ty::FloatLiteral => format!("printf(\"%g\", {value})"),
+			Ty::Named { name, .. } if self.enums.iter().any(|e| &e.name == name) => {
+				format!("fputs({name}_name({value}), stdout)")
+			}
+			_ => {
+				return Err(unsupported(
+					&format!("printing values of type {ty}"),
+					argument,
+				));
+			}
+		})
+	}
+
+	fn method_call(
+		&mut self,
+		object: &Expression,
+		name: &str,
+		arguments: &[Expression],
+		at: &Expression,
+	) -> Result<String, ApolloError> {
+		let object_ty = self.type_of(object);
+		match object_ty.referent().clone() {
+			Ty::Named { name: class, .. } if self.classes.contains_key(&class) => {
+				let Some((owner, function)) = self.find_method(&class, name) else {
+					return Err(unsupported(&format!("calling {name} on {class}"), at));
+				};
+				let params = param_types(&function.params);
+				let mut values = self.arguments(arguments, &params)?;
+				if !(function.name == "new" && function.operator.is_none()) {
+					values.insert(0, self.receiver(object, &class, &owner, at)?);
+				}
+				Ok(format!(
+					"{}({})",
+					method_name(&owner, function),
+					values.join(", ")
+				))
+			}
+			Ty::Primitive(primitive) => {
+				let owners = match primitive {
+					Primitive::String => vec!["String", "string"],
+					Primitive::Char => vec!["Char", "char"],
+					other => vec![other.name()],
+				};
+				for owner in owners {
+					if let Some((owner, function)) = self.find_method(owner, name) {
+						let params = param_types(&function.params);
+						let mut values = self.arguments(arguments, &params)?;
+						values.insert(0, strip_parens(&self.value(object, None)?).to_string());
+						return Ok(format!(
+							"{}({})",
+							method_name(&owner, function),
+							values.join(", ")
+						));
+					}
+				}
+				self.primitive_method(primitive, object, name, arguments, at)
+			}
+			Ty::Named {
+				name: vector,
+				generics,
+			} if vector == "Vector" => {
+				let element = generics.first().cloned().unwrap_or(Ty::Unknown);
+				let c_type = self.c_type(object_ty.referent())?;
+				match (name, arguments) {
+					("add", [value]) => {
+						let pointer = match object_ty {
+							Ty::Reference(_) => self.expression(object, None)?,
+							_ if is_lvalue(object) => format!("&{}", self.expression(object, None)?),
+							_ => return Err(unsupported("adding to a temporary vector", at)),
+						};
+						let value = self.value(value, Some(&element))?;
+						Ok(format!("{c_type}_add({pointer}, {})", strip_parens(&value)))
+					}
+					("shuffle", []) => {
+						let vector = self.value(object, None)?;
+						Ok(format!(
+							"apollo_shuffle({vector}.data, {vector}.len, sizeof *{vector}.data)"
+						))
+					}
+					_ => Err(unsupported(&format!("calling {name} on vectors"), at)),
+				}
+			}
+			other => Err(unsupported(&format!("calling {name} on {other}"), at)),
+		}
+	}
+
+	fn primitive_method(
+		&mut self,
+		primitive: Primitive,
+		object: &Expression,
+		name: &str,
+		arguments: &[Expression],
+		at: &Expression,
+	) -> Result<String, ApolloError> {
+		let value = self.value(object, None)?;
+		let mut values = Vec::new();
+		for argument in arguments {
+			values.push(strip_parens(&self.value(argument, None)?).to_string());
+		}
+		let value = strip_parens(&value);
+		Ok(match (primitive, name, values.as_slice()) {
+			(Primitive::String, "truncate", [length]) => {
+				format!("apollo_string_truncate({value}, (int64_t)({length}))")
+			}
+			(Primitive::String, "strip", []) => format!("apollo_string_strip({value})"),
+			(Primitive::String, "toLowercase", []) => format!("apollo_string_lowercase({value})"),
+			(Primitive::String, "slice" | "chars", []) => format!("apollo_string_slice({value})"),
+			(Primitive::String, "parseInt", []) => format!("apollo_string_parse_int({value})"),
+			(Primitive::String, "append", [suffix]) if is_lvalue(object) => {
+				let suffix_ty = self.type_of(&arguments[0]).referent().clone();
+				let suffix = match suffix_ty {
+					Ty::Primitive(Primitive::Char) => format!("apollo_char_to_string({suffix})"),
+					_ => suffix.clone(),
+				};
+				format!("({value} = apollo_string_concat({value}, {suffix}))")
+			}
+			(Primitive::Char, "isDigit", []) => format!("apollo_char_is_digit({value})"),
+			_ => {
+				return Err(unsupported(
+					&format!("calling {name} on {}", primitive.name()),
+					at,
+				));
+			}
+		})
+	}
+
+	/// the pointer a method of `owner` is called with, `class` is the type of `object`
+	fn receiver(
+		&mut self,
+		object: &Expression,
+		class: &str,
+		owner: &str,
+		at: &Expression,
+	) -> Result<String, ApolloError> {
+		let pointer = if matches!(object.kind, ExpressionKind::SelfValue) && self.self_pointer {
+			"self".to_string()
+		} else if matches!(self.type_of(object), Ty::Reference(_)) {
+			self.expression(object, None)?
+		} else if is_lvalue(object) {
+			format!("&{}", self.expression(object, None)?)
+		} else {
+			return Err(unsupported("calling methods on temporary values", at));
+		};
+		Ok(if class == owner {
+			pointer
+		} else {
+			format!("({owner}*){pointer}")
+		})
+	}
+
+	/// references are passed through, everything else is passed by value
+	fn arguments(
+		&mut self,
+		arguments: &[Expression],
+		params: &[Ty],
+	) -> Result<Vec<String>, ApolloError> {
+		let mut values = Vec::new();
+		for (i, argument) in arguments.iter().enumerate() {
+			let param = params.get(i);
+			let value = match param {
+				Some(Ty::Reference(_)) => {
+					if matches!(argument.kind, ExpressionKind::Reference(_))
+						|| matches!(self.type_of(argument), Ty::Reference(_))
+					{
+						self.expression(argument, None)?
+					} else if is_lvalue(argument) {
+						format!("&{}", self.expression(argument, None)?)
+					} else {
+						return Err(unsupported(
+							"passing a temporary value by reference",
+							argument,
+						));
+					}
+				}
+				_ => self.value(argument, param)?,
+			};
+			values.push(strip_parens(&value).to_string());
+		}
+		Ok(values)
+	}
+
+	fn member(
+		&mut self,
+		object: &Expression,
+		name: &str,
+		at: &Expression,
+	) -> Result<String, ApolloError> {
+		let object_ty = self.type_of(object);
+		match object_ty.referent().clone() {
+			Ty::TypeName(owner) if self.enums.iter().any(|e| e.name == owner) => {
+				Ok(format!("{owner}_{name}"))
+			}
+			Ty::Named { name: record, .. }
+				if self
+					.record_fields(&record)
+					.iter()
+					.any(|(field, _)| field == name) =>
+			{
+				let field = c_ident(name);
+				if matches!(object.kind, ExpressionKind::SelfValue) && self.self_pointer {
+					return Ok(format!("self->{field}"));
+				}
+				let value = self.expression(object, None)?;
+				Ok(match object_ty {
+					Ty::Reference(_) => format!("{value}->{field}"),
+					_ => format!("{value}.{field}"),
+				})
+			}
+			Ty::Named { name: owner, .. } if self.enums.iter().any(|e| e.name == owner) => {
+				let value = self.value(object, None)?;
+				match name {
+					"value" => Ok(format!("{owner}_value({value})")),
+					"name" => Ok(format!("{owner}_name({value})")),
+					_ => Err(unsupported(&format!("{owner}.{name}"), at)),
+				}
+			}
+			Ty::Named { name: error, .. } if self.is_builtin_error(&error) => {
+				let value = self.value(object, None)?;
+				match name {
+					"message" => Ok(format!("{value}.message")),
+					"name" => Ok(format!("apollo_error_name({value}.type)")),
+					_ => Err(unsupported(&format!("{error}.{name}"), at)),
+				}
+			}
+			Ty::Array {
+				length: Some(length),
+				..
+			} if matches!(name, "size" | "len" | "length") => Ok(length.to_string()),
+			Ty::Slice(_) if matches!(name, "size" | "len" | "length") => {
+				Ok(format!("{}.len", self.value(object, None)?))
+			}
+			Ty::Named { name: vector, .. }
+				if vector == "Vector" && matches!(name, "size" | "len" | "length") =>
+			{
+				Ok(format!("{}.len", self.value(object, None)?))
+			}
+			Ty::Primitive(Primitive::String) if matches!(name, "size" | "len" | "length") => {
+				Ok(format!("strlen({})", self.value(object, None)?))
+			}
+			other => Err(unsupported(&format!("the member {name} of {other}"), at)),
+		}
+	}
+
+	fn index(
+		&mut self,
+		object: &Expression,
+		index: &Expression,
+		at: &Expression,
+	) -> Result<String, ApolloError> {
+		let object_ty = self.type_of(object);
+		let index = self.value(index, None)?;
+		let index = strip_parens(&index);
+		match object_ty.referent().clone() {
+			Ty::TypeName(owner) if self.enums.iter().any(|e| e.name == owner) => Ok(format!(
+				"(({owner})apollo_check_index({index}, {owner}_COUNT))"
+			)),
+			Ty::Array {
+				length: Some(length),
+				..
+			} => {
+				let value = self.value(object, None)?;
+				Ok(format!(
+					"{value}.data[apollo_check_index({index}, {length})]"
+				))
+			}
+			ty @ (Ty::Slice(_) | Ty::Named { .. }) if matches!(&ty, Ty::Slice(_)) || is_vector(&ty) => {
+				let c_type = self.c_type(&ty)?;
+				let value = self.value(object, None)?;
+				Ok(format!("(*{c_type}_at({}, {index}))", strip_parens(&value)))
+			}
+			Ty::Primitive(Primitive::String) => {
+				let value = self.value(object, None)?;
+				Ok(format!(
+					"(*apollo_string_at({}, {index}))",
+					strip_parens(&value)
+				))
+			}
+			other => Err(unsupported(&format!("indexing into {other}"), at)),
+		}
+	}
+
+	fn new_value(
+		&mut self,
+		ty: &Ty,
+		arguments: &[Expression],
+		at: &Expression,
+	) -> Result<String, ApolloError> {
+		match ty {
+			Ty::Named { name, .. } if self.classes.contains_key(name) => {
+				let class = self.classes[name];
+				let custom = class.members.iter().find_map(|m| match &m.kind {
+					ItemKind::Function(f) if f.name == "new" && f.operator.is_none() => Some(f),
+					_ => None,
+				});
+				// the implicit constructor wins unless only the custom one fits the arguments
+				let (function, params) = match custom {
+					Some(f) if f.params.len() == arguments.len() && class.params.len() != arguments.len() => {
+						(format!("{name}_new"), param_types(&f.params))
+					}
+					_ => (format!("{name}_create"), param_types(&class.params)),
+				};
+				let values = self.arguments(arguments, &params)?;
+				Ok(format!("{function}({})", values.join(", ")))
+			}
+			Ty::Named { name, .. } if self.is_builtin_error(name) => self.make_error(name, arguments),
+			Ty::Primitive(Primitive::String) => Ok("apollo_string_new(0)".to_string()),
+			Ty::Primitive(_)
+			| Ty::Array { .. }
+			| Ty::Slice(_)
+			| Ty::Named { .. }
+			| Ty::IntLiteral
+			| Ty::FloatLiteral => self.zero_value(ty),
+			other => Err(unsupported(&format!("creating values of type {other}"), at)),
+		}
+	}
+
+	fn make_error(&mut self, name: &str, arguments: &[Expression]) -> Result<String, ApolloError> {
+		let message = match arguments.first() {
+			Some(message) => strip_parens(&self.value(message, None)?).to_string(),
+			None => "\"\"".to_string(),
+		};
+		Ok(format!("apollo_make_error(APOLLO_{name}, {message})"))
+	}
+
+	fn cast(&mut self, value: &Expression, to: &Ty) -> Result<String, ApolloError> {
+		let from = self.type_of(value).referent().clone();
+		if from == *to {
+			return self.value(value, None);
+		}
+		if *to == Ty::Primitive(Primitive::String) {
+			return self.stringify(value, &from);
+		}
+		let c_type = self.c_type(to)?;
+		let value = self.value(value, None)?;
+		Ok(format!("(({c_type}){value})"))
+	}
+
+	/// the string form of a value for `+` and `as string`
+	fn stringify(&mut self, value: &Expression, ty: &Ty) -> Result<String, ApolloError> {
+		let text = self.value(value, None)?;
+		let text = strip_parens(&text);
+		Ok(match ty {
+			Ty::Primitive(Primitive::String) => text.to_string(),
+			Ty::Primitive(Primitive::Char) => format!("apollo_char_to_string({text})"),
+			Ty::Primitive(Primitive::Bool) => format!("(({text}) ? \"true\" : \"false\")"),
+			Ty::Primitive(p) if p.is_unsigned() => {
+				format!("apollo_uint_to_string((unsigned long long)({text}))")
+			}
+			Ty::Primitive(p) if p.is_float() => format!("apollo_float_to_string((double)({text}))"),
+			Ty::Primitive(_) | Ty::IntLiteral => {
+				format!("apollo_int_to_string((long long)({text}))")
+			}
+			Ty::FloatLiteral => format!("apollo_float_to_string({text})"),
+			Ty::Named { name, .. } if self.enums.iter().any(|e| &e.name == name) => {
+				format!("{name}_name({text})")
+			}
+			other => {
+				return Err(unsupported(
+					&format!("converting {other} to a string"),
+					value,
+				));
+			}
+		})
+	}
+
+	fn zero_value(&mut self, ty: &Ty) -> Result<String, ApolloError> {
+		Ok(match ty {
+			Ty::Primitive(Primitive::String) => "apollo_string_new(0)".to_string(),
+			Ty::Primitive(Primitive::Bool) => "false".to_string(),
+			Ty::Primitive(_) | Ty::IntLiteral | Ty::FloatLiteral => "0".to_string(),
+			Ty::Reference(_) => "NULL".to_string(),
+			Ty::Named { name, .. } if self.enums.iter().any(|e| &e.name == name) => "0".to_string(),
+			_ => format!("(({}){{0}})", self.c_type(ty)?),
+		})
+	}
+
+	// types
+
+	/// the type checker's view of an expression, plus the builtin members it leaves open
+	fn type_of(&mut self, expression: &Expression) -> Ty {
+		let ty = self.types.type_of(expression);
+		let (Ty::Unknown, ExpressionKind::Member { object, name }) = (&ty, &expression.kind) else {
+			return ty;
+		};
+		let object = self.types.type_of(object).referent().clone();
+		match (&object, name.as_str()) {
+			(Ty::Named { name: owner, .. }, "value") if self.enums.iter().any(|e| &e.name == owner) => {
+				Ty::Primitive(Primitive::I64)
+			}
+			(Ty::Named { name: owner, .. }, "name")
+				if self.enums.iter().any(|e| &e.name == owner) || self.is_builtin_error(owner) =>
+			{
+				Ty::Primitive(Primitive::String)
+			}
+			(Ty::Named { name: owner, .. }, "message") if self.is_builtin_error(owner) => {
+				Ty::Primitive(Primitive::String)
+			}
+			(
+				Ty::Slice(_) | Ty::Array { .. } | Ty::Named { .. } | Ty::Primitive(Primitive::String),
+				"size" | "len" | "length",
+			) => Ty::Primitive(Primitive::Usize),
+			_ => ty,
+		}
+	}
+
+	/// the C spelling of a type, defining wrapper structs the first time they are used
+	fn c_type(&mut self, ty: &Ty) -> Result<String, ApolloError> {
+		match ty {
+			Ty::Primitive(primitive) => Ok(primitive_c(*primitive).to_string()),
+			Ty::Unit => Ok("void".to_string()),
+			Ty::IntLiteral => Ok("int32_t".to_string()),
+			Ty::FloatLiteral => Ok("double".to_string()),
+			Ty::Reference(inner) => Ok(format!("{}*", self.pointee(inner)?)),
+			Ty::Array {
+				element,
+				length: Some(length),
+			} => {
+				let element_c = self.c_type(element)?;
+				let name = format!("arr_{}_{length}", mangle(element));
+				if self.defined.insert(name.clone()) {
+					self.definitions.push(format!(
+						"typedef struct {{\n\t{element_c} data[{length}];\n}} {name};\n"
+					));
+				}
+				Ok(name)
+			}
+			Ty::Array { length: None, .. } => {
+				Err(self.error("The C backend needs array lengths to be integer literals"))
+			}
+			Ty::Slice(element) => {
+				let element_c = self.pointee(element)?;
+				let name = format!("slice_{}", mangle(element));
+				if name != "slice_char" && self.defined.insert(name.clone()) {
+					self.definitions.push(format!(
+						"typedef struct {{\n\t{element_c}* data;\n\tsize_t len;\n}} {name};\n"
+					));
+					self.helpers.push(format!(
+						"static inline {element_c}* {name}_at({name} slice, int64_t index) {{\n\treturn &slice.data[apollo_check_index(index, slice.len)];\n}}\n"
+					));
+				}
+				Ok(name)
+			}
+			Ty::Named { name, generics } if name == "Vector" => {
+				let element = generics.first().cloned().unwrap_or(Ty::Unknown);
+				let element_c = self.pointee(&element)?;
+				let name = format!("vec_{}", mangle(&element));
+				if self.defined.insert(name.clone()) {
+					self.definitions.push(format!(
+						"typedef struct {{\n\t{element_c}* data;\n\tsize_t len;\n\tsize_t cap;\n}} {name};\n"
+					));
+					self.helpers.push(format!(
+						"static inline void {name}_add({name}* vector, {element_c} value) {{\n\tif (vector->len == vector->cap) {{\n\t\tvector->cap = vector->cap == 0 ? 8 : vector->cap * 2;\n\t\tvector->data = realloc(vector->data, vector->cap * sizeof(*vector->data));\n\t}}\n\tvector->data[vector->len++] = value;\n}}\n\nstatic inline {element_c}* {name}_at({name} vector, int64_t index) {{\n\treturn &vector.data[apollo_check_index(index, vector.len)];\n}}\n"
+					));
+				}
+				Ok(name)
+			}
+			Ty::Named { name, .. }
+				if self.classes.contains_key(name) || self.structs.contains_key(name) =>
+			{
+				self.define_record(name)?;
+				Ok(name.clone())
+			}
+			Ty::Named { name, .. } if self.enums.iter().any(|e| &e.name == name) => Ok(name.clone()),
+			Ty::Named { name, .. } if self.is_builtin_error(name) => Ok("apollo_error".to_string()),
+			other => Err(self.error(&format!(
+				"The C backend doesn't support values of type {other} yet"
+			))),
+		}
+	}
+
+	/// pointers only need records to be declared, the forward typedefs take care of that
+	fn pointee(&mut self, ty: &Ty) -> Result<String, ApolloError> {
+		match ty {
+			Ty::Named { name, .. }
+				if self.classes.contains_key(name) || self.structs.contains_key(name) =>
+			{
+				Ok(name.clone())
+			}
+			_ => self.c_type(ty),
+		}
+	}
+
+	/// emits the struct of a class or struct after the types of its fields
+	fn define_record(&mut self, name: &str) -> Result<(), ApolloError> {
+		if !self.defined.insert(name.to_string()) {
+			return Ok(());
+		}
+		let mut fields = Vec::new();
+		for (field, ty) in self.record_fields(name) {
+			let c_type = self.c_type(&ty)?;
+			fields.push(format!("\t{c_type} {};", c_ident(&field)));
+		}
+		if fields.is_empty() {
+			fields.push("\tchar empty;".to_string());
+		}
+		self
+			.definitions
+			.push(format!("struct {name} {{\n{}\n}};\n", fields.join("\n")));
+		Ok(())
+	}
+
+	/// fields of a struct, or the inherited fields, parameters and variables of a class
+	fn record_fields(&mut self, name: &str) -> Vec<(String, Ty)> {
+		if let Some(s) = self.structs.get(name) {
+			return s
+				.fields
+				.iter()
+				.map(|f| (f.name.clone(), Ty::from_type(&f.ty)))
+				.collect();
+		}
+		let Some(class) = self.classes.get(name).copied() else {
+			return Vec::new();
+		};
+		let mut fields = match self.parent_of(name) {
+			Some(parent) if self.classes.contains_key(&parent) => self.record_fields(&parent),
+			_ => Vec::new(),
+		};
+		for param in &class.params {
+			if !fields.iter().any(|(field, _)| field == &param.name) {
+				fields.push((param.name.clone(), Ty::from_type(&param.ty)));
+			}
+		}
+		for member in &class.members {
+			if let ItemKind::Variable(variable) = &member.kind
+				&& !fields.iter().any(|(field, _)| field == &variable.name)
+			{
+				let ty = self.field_type(variable);
+				fields.push((variable.name.clone(), ty));
+			}
+		}
+		fields
+	}
+
+	fn field_type(&mut self, variable: &Variable) -> Ty {
+		match (&variable.ty, &variable.value) {
+			(Some(ty), _) => Ty::from_type(ty),
+			(None, Some(value)) => self.type_of(value).concrete(),
+			(None, None) => Ty::Unknown,
+		}
+	}
+
+	fn parent_of(&self, class: &str) -> Option<String> {
+		match &self.classes.get(class)?.parent {
+			Some(Type::Named { name, .. }) => Some(name.clone()),
+			_ => None,
+		}
+	}
+
+	fn is_builtin_error(&self, name: &str) -> bool {
+		BUILTIN_ERRORS.iter().any(|(error, _)| *error == name)
+	}
+
+	/// whether a class derives from `Error`, directly or through other classes
+	fn is_error(&self, class: &str) -> bool {
+		let mut current = Some(class.to_string());
+		let mut depth = 0;
+		while let Some(name) = current {
+			if self.is_builtin_error(&name) {
+				return true;
+			}
+			depth += 1;
+			if depth > self.classes.len() {
+				return false;
+			}
+			current = self.parent_of(&name);
+		}
+		false
+	}
+
+	/// looks up a method in a class, its extensions and then its parents
+	fn find_method(&self, owner: &str, key: &str) -> Option<(String, &'a Function)> {
+		let mut current = Some(owner.to_string());
+		let mut depth = 0;
+		while let Some(name) = current {
+			if let Some(class) = self.classes.get(&name) {
+				for member in &class.members {
+					if let ItemKind::Function(function) = &member.kind
+						&& function.key() == key
+					{
+						return Some((name, function));
+					}
+				}
+			}
+			if let Some(function) = self
+				.extensions
+				.get(&name)
+				.and_then(|methods| methods.iter().find(|f| f.key() == key))
+			{
+				return Some((name, function));
+			}
+			depth += 1;
+			if depth > self.classes.len() {
+				return None;
+			}
+			current = self.parent_of(&name);
+		}
+		None
+	}
+
+	// output
+
+	fn assemble(&self) -> String {
+		let mut out = format!(
+			"/* generated by the Apollo compiler from {} */\n",
+			self.program.filepath
+		);
+		let extra = self
+			.includes
+			.iter()
+			.filter(|h| !HEADERS.contains(&h.as_str()));
+		for header in HEADERS.iter().copied().chain(extra.map(String::as_str)) {
+			out.push_str(&format!("#include <{header}>\n"));
+		}
+		out.push('\n');
+
+		// error ids, `apollo_error_is` walks the parents
+		let ids: Vec<String> = self
+			.errors
+			.iter()
+			.map(|(name, _)| format!("\tAPOLLO_{name},"))
+			.collect();
+		out.push_str(&format!("enum {{\n{}\n}};\n\n", ids.join("\n")));
+		let parents: Vec<String> = self
+			.errors
+			.iter()
+			.map(|(_, parent)| {
+				parent
+					.as_ref()
+					.and_then(|p| self.errors.iter().position(|(name, _)| name == p))
+					.map(|i| i.to_string())
+					.unwrap_or_else(|| "-1".to_string())
+			})
+			.collect();
+		out.push_str(&format!(
+			"static inline int apollo_error_parent(int type) {{\n\tstatic const int parents[] = {{ {} }};\n\treturn parents[type];\n}}\n\n",
+			parents.join(", ")
+		));
+		let names: Vec<String> = self
+			.errors
+			.iter()
+			.map(|(name, _)| format!("\"{name}\""))
+			.collect();
+		out.push_str(&format!(
+			"static inline const char* apollo_error_name(int type) {{\n\tstatic const char* const names[] = {{ {} }};\n\treturn names[type];\n}}\n\n",
+			names.join(", ")
+		));
+		out.push_str(RUNTIME);
+
+		for e in &self.enums {
+			out.push('\n');
+			out.push_str(&enum_definition(e));
+		}
+		out.push('\n');
+		for item in &self.program.items {
+			match &item.kind {
+				ItemKind::Struct(Struct { name, .. }) => {
+					out.push_str(&format!("typedef struct {name} {name};\n"));
+				}
+				ItemKind::Class(Class { name, .. }) if self.classes.contains_key(name) => {
+					out.push_str(&format!("typedef struct {name} {name};\n"));
+				}
+				_ => {}
+			}
+		}
+		for section in [&self.definitions, &self.helpers] {
+			for definition in section {
+				out.push('\n');
+				out.push_str(definition);
+			}
+		}
+		out.push('\n');
+		for prototype in &self.prototypes {
+			out.push_str(prototype);
+			out.push('\n');
+		}
+		if !self.variables.is_empty() {
+			out.push('\n');
+			for variable in &self.variables {
+				out.push_str(variable);
+				out.push('\n');
+			}
+		}
+		if !self.initializers.is_empty() {
+			out.push_str(&format!(
+				"\nstatic void apollo_init_globals(void) {{\n{}\n}}\n",
+				self.initializers.join("\n")
+			));
+		}
+		for function in &self.output {
+			out.push('\n');
+			out.push_str(function);
+		}
+		out
+	}
+
+	// helpers
+
+	fn line(&mut self, text: &str) {
+		for _ in 0..self.indent {
+			self.body.push('\t');
+		}
+		self.body.push_str(text);
+		self.body.push('\n');
+	}
+
+	fn temp(&mut self, prefix: &str) -> String {
+		self.counter += 1;
+		format!("{prefix}_{}", self.counter)
+	}
+
+	fn push_scope(&mut self) {
+		self.locals.push(HashMap::new());
+		self.types.enter_scope();
+	}
+
+	fn pop_scope(&mut self) {
+		self.locals.pop();
+		self.types.exit_scope();
+	}
+
+	fn define_local(&mut self, name: &str, c_name: String, ty: Ty, constant: bool) {
+		if let Some(scope) = self.locals.last_mut() {
+			scope.insert(name.to_string(), c_name);
+		}
+		self.types.define(name, ty, constant);
+	}
+
+	fn local(&self, name: &str) -> Option<String> {
+		self
+			.locals
+			.iter()
+			.rev()
+			.find_map(|scope| scope.get(name).cloned())
+	}
+
+	/// C name for a new local, the same name can only be declared once per C block
+	fn local_name(&self, name: &str) -> String {
+		c_ident(name)
+	}
+
+	fn error(&self, message: &str) -> ApolloError {
+		ApolloError::at(message.to_string(), self.position.0, self.position.1)
+	}
+}
+
+fn unsupported(what: &str, at: &Expression) -> ApolloError {
+	ApolloError::at(
+		format!("The C backend doesn't support {what} yet"),
+		at.line,
+		at.column,
+	)
+}
+
+fn primitive_c(primitive: Primitive) -> &'static str {
+	match primitive {
+		Primitive::U8 => "uint8_t",
+		Primitive::U16 => "uint16_t",
+		Primitive::U32 => "uint32_t",
+		Primitive::U64 => "uint64_t",
+		Primitive::Usize => "size_t",
+		Primitive::I8 => "int8_t",
+		Primitive::I16 => "int16_t",
+		Primitive::I32 => "int32_t",
+		Primitive::I64 => "int64_t",
+		Primitive::Isize => "ptrdiff_t",
+		Primitive::F32 => "float",
+		Primitive::F64 => "double",
+		Primitive::Bool => "bool",
+		Primitive::Char => "char",
+		Primitive::String => "char*",
+	}
+}
+
+/// a name for a type that can be part of a C identifier
+fn mangle(ty: &Ty) -> String {
+	match ty {
+		Ty::Primitive(primitive) => primitive.name().to_string(),
+		Ty::Named { name, generics } => std::iter::once(name.clone())
+			.chain(generics.iter().map(mangle))
+			.collect::<Vec<_>>()
+			.join("_"),
+		Ty::Array { element, length } => format!("arr_{}_{}", mangle(element), length.unwrap_or(0)),
+		Ty::Slice(element) => format!("slice_{}", mangle(element)),
+		Ty::Reference(inner) => format!("ref_{}", mangle(inner)),
+		Ty::IntLiteral => "i32".to_string(),
+		Ty::FloatLiteral => "f64".to_string(),
+		_ => "any".to_string(),
+	}
+}
+
+fn c_ident(name: &str) -> String {
+	if RESERVED.contains(&name) {
+		format!("{name}_")
+	} else {
+		name.to_string()
+	}
+}
+
+/// `Player_attack`, operators are spelled out like `Player_op_decrement`
+fn method_name(owner: &str, function: &Function) -> String {
+	if function.operator.is_none() {
+		return format!("{owner}_{}", function.name);
+	}
+	let word = match function.name.as_str() {
+		"++" => "increment",
+		"--" => "decrement",
+		"+" => "plus",
+		"-" => "minus",
+		"*" => "star",
+		"/" => "slash",
+		"%" => "percent",
+		"~" => "tilde",
+		"!" => "bang",
+		"?" => "question",
+		_ => "operator",
+	};
+	format!("{owner}_op_{word}")
+}
+
+fn param_types(params: &[Param]) -> Vec<Ty> {
+	params.iter().map(|p| Ty::from_type(&p.ty)).collect()
+}
+
+fn is_lvalue(expression: &Expression) -> bool {
+	matches!(
+		expression.kind,
+		ExpressionKind::Identifier(_)
+			| ExpressionKind::Member { .. }
+			| ExpressionKind::Index { .. }
+			| ExpressionKind::SelfValue
+	)
+}
+
+fn is_vector(ty: &Ty) -> bool {
+	matches!(ty, Ty::Named { name, .. } if name == "Vector")
+}
+
+/// escapes decoded literal text so it can sit between `quote`s in C
+fn c_escape(text: &str, quote: char) -> String {
+	let mut escaped = String::new();
+	let mut previous = None;
+	for c in text.chars() {
+		match c {
+			'\n' => escaped.push_str("\\n"),
+			'\t' => escaped.push_str("\\t"),
+			'\r' => escaped.push_str("\\r"),
+			'\\' => escaped.push_str("\\\\"),
+			c if c == quote => {
+				escaped.push('\\');
+				escaped.push(c);
+			}
+			// `??` could start a trigraph
+			'?' if previous == Some('?') => escaped.push_str("\\?"),
+			// octal escapes stop after three digits, so a following digit can't be swallowed
+			c if c.is_ascii_control() => escaped.push_str(&format!("\\{:03o}", c as u32)),
+			c => escaped.push(c),
+		}
+		previous = Some(c);
+	}
+	escaped
+}
+
+/// drops one pair of parentheses around a whole expression, `((a + b))` reads badly in C
+fn strip_parens(text: &str) -> &str {
+	let Some(inner) = text.strip_prefix('(').and_then(|t| t.strip_suffix(')')) else {
+		return text;
+	};
+	// `(a) + (b)` starts and ends with parentheses that don't belong together
+	let mut depth = 0;
+	let mut quote: Option<char> = None;
+	let mut escaped = false;
+	for c in inner.chars() {
+		if let Some(q) = quote {
+			if escaped {
+				escaped = false;
+			} else if c == '\\' {
+				escaped = true;
+			} else if c == q {
+				quote = None;
+			}
+			continue;
+		}
+		match c {
+			'"' | '\'' => quote = Some(c),
+			'(' => depth += 1,
+			')' if depth == 0 => return text,
+			')' => depth -= 1,
+			_ => {}
+		}
+	}
+	inner
+}
+
+fn enum_definition(e: &Enum) -> String {
+	let name = &e.name;
+	let mut variants = Vec::new();
+	let mut values = Vec::new();
+	let mut next: i64 = 0;
+	for variant in &e.variants {
+		let value = match &variant.value {
+			Some(Expression {
+				kind:
+					ExpressionKind::Literal(Literal::Number {
+						number: NumberValue::Integer(value),
+						..
+					}),
+				..
+			}) => *value as i64,
+			Some(Expression {
+				kind: ExpressionKind::Unary {
+					operator: TokenType::MINUS,
+					operand,
+				},
+				..
+			}) => match &operand.kind {
+				ExpressionKind::Literal(Literal::Number {
+					number: NumberValue::Integer(value),
+					..
+				}) => -(*value as i64),
+				_ => next,
+			},
+			_ => next,
+		};
+		next = value + 1;
+		variants.push(format!("\t{name}_{},", variant.name));
+		values.push(value.to_string());
+	}
+	let names: Vec<String> = e
+		.variants
+		.iter()
+		.map(|v| format!("\"{}\"", v.name))
+		.collect();
+	let count = e.variants.len().max(1);
+	if variants.is_empty() {
+		variants.push(format!("\t{name}_EMPTY,"));
+		values.push("0".to_string());
+	}
+	format!(
+		"typedef enum {{\n{}\n}} {name};\nenum {{ {name}_COUNT = {count} }};\n\nstatic inline int64_t {name}_value({name} entry) {{\n\tstatic const int64_t values[] = {{ {} }};\n\treturn values[entry];\n}}\n\nstatic inline const char* {name}_name({name} entry) {{\n\tstatic const char* const names[] = {{ {} }};\n\treturn names[entry];\n}}\n",
+		variants.join("\n"),
+		values.join(", "),
+		if names.is_empty() {
+			"\"\"".to_string()
+		} else {
+			names.join(", ")
+		},
+	)
+}
+
+/// whether `retry` appears in a handler outside of nested catch handlers
+fn contains_retry(block: &Block) -> bool {
+	block.statements.iter().any(statement_retries)
+}
+
+fn statement_retries(statement: &Statement) -> bool {
+	match &statement.kind {
+		StatementKind::Retry => true,
+		StatementKind::If {
+			then_block,
+			else_branch,
+			..
+		} => contains_retry(then_block) || else_branch.as_deref().is_some_and(statement_retries),
+		StatementKind::While { body, .. }
+		| StatementKind::For { body, .. }
+		| StatementKind::Block(body) => contains_retry(body),
+		StatementKind::Catch { body, .. } => contains_retry(body),
+		StatementKind::Expression(Expression {
+			kind: ExpressionKind::Match { arms, .. },
+			..
+		}) => arms.iter().any(|arm| statement_retries(&arm.body)),
+		_ => false,
+	}
+}
+
+/// names that are read somewhere in a function, locals that never are get a `(void)` cast so the
+/// C compiler doesn't warn about them
+fn collect_reads(block: &Block, reads: &mut HashSet<String>) {
+	for statement in &block.statements {
+		statement_reads(statement, reads);
+	}
+}
+
+fn statement_reads(statement: &Statement, reads: &mut HashSet<String>) {
+	match &statement.kind {
+		StatementKind::Variable(variable) => {
+			if let Some(value) = &variable.value {
+				expression_reads(value, reads);
+			}
+			if let Some(Type::Sized { length, .. }) = &variable.ty {
+				expression_reads(length, reads);
+			}
+		}
+		StatementKind::Expression(value) | StatementKind::Panic(value) => {
+			expression_reads(value, reads)
+		}
+		StatementKind::Return(value) => {
+			if let Some(value) = value {
+				expression_reads(value, reads);
+			}
+		}
+		StatementKind::If {
+			condition,
+			then_block,
+			else_branch,
+		} => {
+			expression_reads(condition, reads);
+			collect_reads(then_block, reads);
+			if let Some(branch) = else_branch {
+				statement_reads(branch, reads);
+			}
+		}
+		StatementKind::While { condition, body } => {
+			expression_reads(condition, reads);
+			collect_reads(body, reads);
+		}
+		StatementKind::For { arguments, body } => {
+			for argument in arguments {
+				// the counter is read by the increment
+				match &argument.kind {
+					ExpressionKind::Reference(inner) => expression_reads(inner, reads),
+					_ => expression_reads(argument, reads),
+				}
+			}
+			collect_reads(body, reads);
+		}
+		StatementKind::Block(body) => collect_reads(body, reads),
+		StatementKind::Catch { body, handler, .. } => {
+			collect_reads(body, reads);
+			collect_reads(handler, reads);
+		}
+		StatementKind::Retry | StatementKind::Error => {}
+	}
+}
+
+fn expression_reads(expression: &Expression, reads: &mut HashSet<String>) {
+	match &expression.kind {
+		ExpressionKind::Identifier(name) => {
+			reads.insert(name.clone());
+		}
+		ExpressionKind::Literal(_) | ExpressionKind::SelfValue => {}
+		// writing to a variable doesn't count as reading it
+		ExpressionKind::Unary {
+			operator: TokenType::INCREMENT | TokenType::DECREMENT,
+			operand,
+		}
+		| ExpressionKind::Postfix { operand, .. } => {
+			if !matches!(operand.kind, ExpressionKind::Identifier(_)) {
+				expression_reads(operand, reads);
+			}
+		}
+		ExpressionKind::Assign { target, value, .. } => {
+			if !matches!(target.kind, ExpressionKind::Identifier(_)) {
+				expression_reads(target, reads);
+			}
+			expression_reads(value, reads);
+		}
+		ExpressionKind::Unary { operand, .. }
+		| ExpressionKind::Reference(operand)
+		| ExpressionKind::Member {
+			object: operand, ..
+		}
+		| ExpressionKind::Cast { value: operand, .. } => expression_reads(operand, reads),
+		ExpressionKind::Binary { left, right, .. }
+		| ExpressionKind::Index {
+			object: left,
+			index: right,
+		} => {
+			expression_reads(left, reads);
+			expression_reads(right, reads);
+		}
+		ExpressionKind::Call { callee, arguments } => {
+			expression_reads(callee, reads);
+			for argument in arguments {
+				expression_reads(argument, reads);
+			}
+		}
+		ExpressionKind::New { arguments, .. } | ExpressionKind::ArrayLiteral(arguments) => {
+			for argument in arguments {
+				expression_reads(argument, reads);
+			}
+		}
+		ExpressionKind::StructLiteral { fields, .. } => {
+			for (_, value) in fields {
+				expression_reads(value, reads);
+			}
+		}
+		ExpressionKind::Closure { body, .. } => collect_reads(body, reads),
+		ExpressionKind::Lambda { body, .. } => expression_reads(body, reads),
+		ExpressionKind::Match { subject, arms } => {
+			expression_reads(subject, reads);
+			for arm in arms {
+				statement_reads(&arm.body, reads);
+			}
+		}
+		ExpressionKind::Format(parts) => {
+			for part in parts {
+				if let FormatPart::Expression(part) = part {
+					expression_reads(part, reads);
+				}
+			}
+		}
+	}
+}
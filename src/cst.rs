@@ -0,0 +1,233 @@
+//! Lossless syntax tree over the lexer output, every byte of the source ends up in a token or its
+//! trivia so formatters and refactoring tools can change a file without losing anything
+
+use std::fmt::Display;
+
+use crate::lexer;
+use crate::util;
+
+use lexer::{Lexer, LexerToken, TokenType};
+use util::ApolloError;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TriviaKind {
+	Whitespace,
+	Newline,
+	LineComment,
+	BlockComment,
+	DocComment,
+	/// text the lexer rejected, kept so invalid files still reprint unchanged
+	Skipped,
+}
+
+/// Source text between significant tokens
+#[derive(Clone, Debug)]
+pub struct Trivia {
+	pub kind: TriviaKind,
+	pub text: String,
+}
+
+/// A significant token with the trivia around it
+///
+/// trivia on the same line after a token trails it, everything from the next line break on leads
+/// the following token
+#[derive(Clone)]
+pub struct CstToken {
+	pub token: LexerToken,
+	/// the token exactly as written, `value` on `token` is the decoded form
+	pub text: String,
+	pub leading: Vec<Trivia>,
+	pub trailing: Vec<Trivia>,
+}
+
+#[derive(Clone)]
+pub enum CstNode {
+	Token(Box<CstToken>),
+	/// `(...)`, `[...]` or `{...}`, `close` is missing when the file ends first
+	Group {
+		open: Box<CstToken>,
+		children: Vec<CstNode>,
+		close: Option<Box<CstToken>>,
+	},
+}
+
+pub struct SyntaxTree {
+	pub filepath: String,
+	pub nodes: Vec<CstNode>,
+	/// holds the trivia at the end of the file
+	pub eof: CstToken,
+	/// invalid tokens, their text is kept as `Skipped` trivia
+	pub errors: Vec<ApolloError>,
+}
+
+impl SyntaxTree {
+	pub fn new(lexer: Lexer) -> Self {
+		let filepath = lexer.filepath().to_string();
+		let source = lexer.source().to_string();
+		let mut tokens: Vec<CstToken> = Vec::new();
+		let mut pending: Vec<Trivia> = Vec::new(); // trivia since the last significant token
+		let mut errors = Vec::new();
+		let mut offset = 0;
+
+		for token in lexer {
+			let token = match token {
+				Ok(token) => token,
+				Err(error) => {
+					errors.push(error);
+					continue;
+				}
+			};
+			// the lexer skips spaces and tabs without a token, invalid text is skipped the same way
+			if token.span.start > offset {
+				pending.push(Trivia::gap(&source[offset..token.span.start]));
+			}
+			offset = offset.max(token.span.end);
+			let text = source[token.span.start..token.span.end].to_string();
+			let kind = match token.token_type {
+				TokenType::NEWLINE => TriviaKind::Newline,
+				TokenType::LINECOMMENT => TriviaKind::LineComment,
+				TokenType::BLOCKCOMMENT => TriviaKind::BlockComment,
+				TokenType::DOCCOMMENT => TriviaKind::DocComment,
+				_ => {
+					if let Some(previous) = tokens.last_mut() {
+						let end = pending
+							.iter()
+							.position(|t| t.kind == TriviaKind::Newline)
+							.unwrap_or(pending.len());
+						previous.trailing = pending.drain(..end).collect();
+					}
+					tokens.push(CstToken {
+						token,
+						text,
+						leading: std::mem::take(&mut pending),
+						trailing: Vec::new(),
+					});
+					continue;
+				}
+			};
+			pending.push(Trivia { kind, text });
+		}
+
+		// the lexer always ends with `EOF`
+		let mut eof = match tokens.pop() {
+			Some(token) if token.token_type() == TokenType::EOF => token,
+			token => {
+				tokens.extend(token);
+				CstToken {
+					token: LexerToken {
+						token_type: TokenType::EOF,
+						..LexerToken::default()
+					},
+					text: String::new(),
+					leading: Vec::new(),
+					trailing: Vec::new(),
+				}
+			}
+		};
+		if offset < source.len() {
+			eof.leading.push(Trivia::gap(&source[offset..]));
+		}
+
+		let (nodes, _) = group(&mut tokens.into_iter(), None);
+		SyntaxTree {
+			filepath,
+			nodes,
+			eof,
+			errors,
+		}
+	}
+}
+
+/// reads nodes until `close`, bracket pairs become groups
+fn group(
+	tokens: &mut std::vec::IntoIter<CstToken>,
+	close: Option<TokenType>,
+) -> (Vec<CstNode>, Option<Box<CstToken>>) {
+	let mut nodes = Vec::new();
+	while let Some(token) = tokens.next() {
+		if Some(token.token_type()) == close {
+			return (nodes, Some(Box::new(token)));
+		}
+		let matching = match token.token_type() {
+			TokenType::LEFTPAREN => TokenType::RIGHTPAREN,
+			TokenType::LEFTBRACKET => TokenType::RIGHTBRACKET,
+			TokenType::LEFTBRACE => TokenType::RIGHTBRACE,
+			_ => {
+				nodes.push(CstNode::Token(Box::new(token)));
+				continue;
+			}
+		};
+		let (children, close) = group(tokens, Some(matching));
+		nodes.push(CstNode::Group {
+			open: Box::new(token),
+			children,
+			close,
+		});
+	}
+	(nodes, None)
+}
+
+impl Trivia {
+	fn gap(text: &str) -> Self {
+		let kind = if text.chars().all(Lexer::is_whitespace) {
+			TriviaKind::Whitespace
+		} else {
+			TriviaKind::Skipped
+		};
+		Trivia {
+			kind,
+			text: text.to_string(),
+		}
+	}
+}
+
+impl CstToken {
+	pub fn token_type(&self) -> TokenType {
+		self.token.token_type
+	}
+}
+
+impl Display for CstToken {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		for trivia in &self.leading {
+			write!(f, "{}", trivia.text)?;
+		}
+		write!(f, "{}", self.text)?;
+		for trivia in &self.trailing {
+			write!(f, "{}", trivia.text)?;
+		}
+		Ok(())
+	}
+}
+
+impl Display for CstNode {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		match self {
+			CstNode::Token(token) => write!(f, "{token}"),
+			CstNode::Group {
+				open,
+				children,
+				close,
+			} => {
+				write!(f, "{open}")?;
+				for child in children {
+					write!(f, "{child}")?;
+				}
+				match close {
+					Some(close) => write!(f, "{close}"),
+					None => Ok(()),
+				}
+			}
+		}
+	}
+}
+
+/// prints the source back exactly as it was read
+impl Display for SyntaxTree {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		for node in &self.nodes {
+			write!(f, "{node}")?;
+		}
+		write!(f, "{}", self.eof)
+	}
+}
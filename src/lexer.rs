@@ -1,15 +1,13 @@
+use std::collections::VecDeque;
 use std::fmt::{Debug, Display};
 
-use crate::tui;
 use crate::util;
 
-use tui::LoadingBar;
-use util::print_debug;
 use util::ApolloError;
-use util::{DEBUG, ERR, INFO, MSG, RESET, SUCCESS};
-use util::{DOWN, LEFT, RIGHT, UP};
+use util::print_debug;
+use util::{ERR, INFO};
 
-#[derive(Clone)]
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
 pub enum TokenType {
 	NUMBER,
 	HEXADECIMAL,
@@ -17,6 +15,34 @@ pub enum TokenType {
 	BINARY,
 	FLOAT,
 	IDENTIFIER,
+	// keywords
+	FN,
+	CLASS,
+	STRUCT,
+	ENUM,
+	EXTEND,
+	USE,
+	VAR,
+	CONST,
+	PUBLIC,
+	RETURN,
+	MATCH,
+	IF,
+	ELSE,
+	WHILE,
+	FOR,
+	NEW,
+	PANIC,
+	CATCH,
+	RETRY,
+	OPERATOR,
+	UNARY,
+	AS,
+	FROM,
+	SELF,
+	TRUE,
+	FALSE,
+	UNIT,
 	STRING,
 	CHARACTER,
 	LESSEQL,
@@ -39,6 +65,7 @@ pub enum TokenType {
 	ASSIGN,
 	LINECOMMENT,
 	BLOCKCOMMENT,
+	DOCCOMMENT,
 	DIVASSIGN,
 	DIVIDE,
 	STARASSIGN,
@@ -86,6 +113,33 @@ impl Display for TokenType {
 			TokenType::BINARY => write!(f, "BINARY"),
 			TokenType::FLOAT => write!(f, "FLOAT"),
 			TokenType::IDENTIFIER => write!(f, "IDENTIFIER"),
+			TokenType::FN => write!(f, "FN"),
+			TokenType::CLASS => write!(f, "CLASS"),
+			TokenType::STRUCT => write!(f, "STRUCT"),
+			TokenType::ENUM => write!(f, "ENUM"),
+			TokenType::EXTEND => write!(f, "EXTEND"),
+			TokenType::USE => write!(f, "USE"),
+			TokenType::VAR => write!(f, "VAR"),
+			TokenType::CONST => write!(f, "CONST"),
+			TokenType::PUBLIC => write!(f, "PUBLIC"),
+			TokenType::RETURN => write!(f, "RETURN"),
+			TokenType::MATCH => write!(f, "MATCH"),
+			TokenType::IF => write!(f, "IF"),
+			TokenType::ELSE => write!(f, "ELSE"),
+			TokenType::WHILE => write!(f, "WHILE"),
+			TokenType::FOR => write!(f, "FOR"),
+			TokenType::NEW => write!(f, "NEW"),
+			TokenType::PANIC => write!(f, "PANIC"),
+			TokenType::CATCH => write!(f, "CATCH"),
+			TokenType::RETRY => write!(f, "RETRY"),
+			TokenType::OPERATOR => write!(f, "OPERATOR"),
+			TokenType::UNARY => write!(f, "UNARY"),
+			TokenType::AS => write!(f, "AS"),
+			TokenType::FROM => write!(f, "FROM"),
+			TokenType::SELF => write!(f, "SELF"),
+			TokenType::TRUE => write!(f, "TRUE"),
+			TokenType::FALSE => write!(f, "FALSE"),
+			TokenType::UNIT => write!(f, "UNIT"),
 			TokenType::STRING => write!(f, "STRING"),
 			TokenType::CHARACTER => write!(f, "CHARACTER"),
 			TokenType::LESSEQL => write!(f, "LESS-EQUAL"),
@@ -108,6 +162,7 @@ impl Display for TokenType {
 			TokenType::ASSIGN => write!(f, "ASSIGN"),
 			TokenType::LINECOMMENT => write!(f, "LINE-COMMENT"),
 			TokenType::BLOCKCOMMENT => write!(f, "BLOCK-COMMENT"),
+			TokenType::DOCCOMMENT => write!(f, "DOC-COMMENT"),
 			TokenType::DIVASSIGN => write!(f, "DIVIDE-ASSIGN"),
 			TokenType::DIVIDE => write!(f, "DIVIDE"),
 			TokenType::STARASSIGN => write!(f, "STAR-ASSIGN"),
@@ -147,13 +202,96 @@ impl Display for TokenType {
 	}
 }
 
+/// reserved words and the token type each one is lexed as
+pub const KEYWORDS: &[(&str, TokenType)] = &[
+	("fn", TokenType::FN),
+	("class", TokenType::CLASS),
+	("struct", TokenType::STRUCT),
+	("enum", TokenType::ENUM),
+	("extend", TokenType::EXTEND),
+	("use", TokenType::USE),
+	("var", TokenType::VAR),
+	("const", TokenType::CONST),
+	("public", TokenType::PUBLIC),
+	("return", TokenType::RETURN),
+	("match", TokenType::MATCH),
+	("if", TokenType::IF),
+	("else", TokenType::ELSE),
+	("while", TokenType::WHILE),
+	("for", TokenType::FOR),
+	("new", TokenType::NEW),
+	("panic", TokenType::PANIC),
+	("catch", TokenType::CATCH),
+	("retry", TokenType::RETRY),
+	("operator", TokenType::OPERATOR),
+	("unary", TokenType::UNARY),
+	("as", TokenType::AS),
+	("from", TokenType::FROM),
+	("self", TokenType::SELF),
+	("true", TokenType::TRUE),
+	("false", TokenType::FALSE),
+	("Unit", TokenType::UNIT),
+];
+
+impl TokenType {
+	/// the keyword token for `word`, if it is reserved
+	pub fn keyword(word: &str) -> Option<TokenType> {
+		KEYWORDS.iter().find(|(k, _)| *k == word).map(|(_, t)| *t)
+	}
+
+	pub fn is_keyword(self) -> bool {
+		KEYWORDS.iter().any(|(_, t)| *t == self)
+	}
+}
+
+/// Extra data carried by annotation and interpolated string tokens
+#[derive(Clone, Debug)]
+pub enum Metadata {
+	/// the tokens between `#[` and `]`, ending with the closing `]`
+	Annotation(Vec<LexerToken>),
+	/// a run of literal text in an interpolated string
+	Text(String),
+	/// the tokens of an embedded `{expression}`, ending with its closing `}`
+	Expression(Vec<LexerToken>),
+	/// the parsed value of a numeric literal and its width suffix, `u8` in `255u8`
+	Number {
+		value: NumberValue,
+		suffix: Option<String>,
+	},
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum NumberValue {
+	Integer(u64),
+	Float(f64),
+}
+
+/// width suffixes a numeric literal may end with
+pub const NUMBER_SUFFIXES: &[&str] = &[
+	"u8", "u16", "u32", "u64", "usize", "i8", "i16", "i32", "i64", "isize", "f32", "f64",
+];
+
 #[derive(Clone)]
 pub struct LexerToken {
 	pub token_type: TokenType,
 	pub value: String,
-	pub metadata: Vec<Vec<char>>, // string interpolation and annotations
-	pub line: usize,
-	pub column: usize,
+	pub metadata: Vec<Metadata>, // string interpolation and annotations
+	pub line: usize,             // start of the token, errors point at the offending part instead
+	pub column: usize,           // same as line
+	pub span: Span,
+}
+
+/// Where a token sits in the source, filled in by `next_token` while scanning
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Span {
+	pub start_line: usize,
+	pub start_column: usize,
+	pub end_line: usize,
+	/// one past the last character, so an empty span has equal start and end
+	pub end_column: usize,
+	/// byte range in the source, `&source[span.start..span.end]` is the token's text
+	pub start: usize,
+	pub end: usize,
 }
 
 impl Display for LexerToken {
@@ -186,6 +324,7 @@ impl Default for LexerToken {
 			metadata: Vec::new(),
 			line: 0,
 			column: 0,
+			span: Span::default(),
 		}
 	}
 }
@@ -208,52 +347,90 @@ impl Debug for LexerToken {
 	}
 }
 
+/// saved lexer position, lets `backtrack` undo the last `read_char`
+#[derive(Clone, Copy, Default)]
+struct Cursor {
+	position: usize,
+	read_position: usize,
+	current_char: Option<char>,
+	line: usize,
+	column: usize,
+}
+
 pub struct Lexer {
 	filepath: String,
-	mode: u8,                // 0: quiet, 1: debug, 2: verbose
-	logging: bool,           // Whether to log debug messages
-	output_dir: String,      // Directory for output files and logs
-	loading_bar: LoadingBar, // Loading bar for visual feedback
+	mode: u8,           // 0: quiet, 1: debug, 2: verbose
+	logging: bool,      // Whether to log debug messages
+	output_dir: String, // Directory for output files and logs
 
 	//Additional private fields for lexer state
 	content: String,            // The content of the file being lexed
-	position: usize,            // Current position in the content
-	read_position: usize,       // Position of the character being read
+	position: usize,            // Byte offset of the current character
+	read_position: usize,       // Byte offset of the next character to read
 	current_char: Option<char>, // Current character being processed
+	previous: Cursor,           // State before the last read, restored by backtrack
 
 	current_line: usize, // Current line number // increment when a newline is encountered
 	current_column: usize, // Current column number // increment when a character is read
+
+	lookahead: VecDeque<Result<LexerToken, ApolloError>>, // tokens scanned by `peek_nth` but not yet returned
+	finished: bool,                                       // set once `EOF` has been scanned
 }
 
 impl Lexer {
-	pub fn new(filepath: String, mode: u8, logging: bool, output_dir: String) -> Self {
-		let content = std::fs::read_to_string(&filepath).map_err(|_e| {
+	/// reads `filepath` and lexes its content
+	pub fn new(
+		filepath: String,
+		mode: u8,
+		logging: bool,
+		output_dir: String,
+	) -> Result<Self, ApolloError> {
+		let content = std::fs::read_to_string(&filepath).map_err(|e| {
 			ApolloError::new(
 				format!("Failed to read file: {filepath}"),
 				Some(0),
-				None,
+				Some(e.to_string()),
 				None,
 			)
-		});
-		let content = match content {
-			Ok(c) => c,
-			Err(e) => {
-				e.print();
-				std::process::exit(1);
-			}
-		};
+		})?;
+		Ok(Self::with_content(
+			filepath, content, mode, logging, output_dir,
+		))
+	}
+
+	/// lexes an editor buffer, REPL line or test snippet, `name` stands in for the file path and
+	/// nothing is printed or logged
+	pub fn from_source(source: &str, name: &str) -> Self {
+		Self::with_content(
+			name.to_string(),
+			source.to_string(),
+			0,
+			false,
+			String::new(),
+		)
+	}
+
+	fn with_content(
+		filepath: String,
+		content: String,
+		mode: u8,
+		logging: bool,
+		output_dir: String,
+	) -> Self {
 		let mut l = Lexer {
 			filepath,
 			mode,
 			logging,
 			output_dir,
-			loading_bar: LoadingBar::new(),
 			content,
 			position: 0,
 			read_position: 0,
 			current_char: None,
 			current_line: 1,
 			current_column: 0,
+			previous: Cursor::default(),
+			lookahead: VecDeque::new(),
+			finished: false,
 		};
 		l.read_char(); // Initialize the first character
 		l
@@ -264,6 +441,13 @@ impl Lexer {
 	}
 
 	fn read_char(&mut self) {
+		self.previous = Cursor {
+			position: self.position,
+			read_position: self.read_position,
+			current_char: self.current_char,
+			line: self.current_line,
+			column: self.current_column,
+		};
 		if self.read_position >= self.content.len() {
 			if self.mode > 0 {
 				print_debug(
@@ -274,8 +458,9 @@ impl Lexer {
 				);
 			}
 			self.current_char = None; // End of file
+			self.position = self.content.len();
 		} else {
-			self.current_char = Some(self.content.chars().nth(self.read_position).unwrap());
+			self.current_char = self.content[self.read_position..].chars().next();
 			if self.mode > 1 {
 				print_debug(
 					"Reading char: ",
@@ -286,13 +471,17 @@ impl Lexer {
 			}
 		}
 
-		self.position = self.read_position;
-		self.read_position += 1;
+		if let Some(c) = self.current_char {
+			self.position = self.read_position;
+			self.read_position += c.len_utf8();
+		}
 
-		self.current_column += 1;
-		if self.current_char == Some('\n') {
-			self.current_line += 1; // Increment line number on newline
-			self.current_column = 0; // Reset column number
+		// a newline belongs to the line it ends, the character after it starts the next one
+		if self.previous.current_char == Some('\n') {
+			self.current_line += 1;
+			self.current_column = 1;
+		} else {
+			self.current_column += 1;
 		}
 
 		if self.mode > 1 {
@@ -306,27 +495,22 @@ impl Lexer {
 	}
 
 	fn peek_char(&self) -> Option<char> {
-		if self.read_position >= self.content.len() {
-			None // No more characters to read
-		} else {
-			if self.mode > 1 {
-				print_debug(
-					"Peeking char: ",
-					&self
-						.content
-						.chars()
-						.nth(self.read_position)
-						.unwrap()
-						.to_string(),
-					self.logging,
-					&self.output_dir,
-				);
-			}
-			Some(self.content.chars().nth(self.read_position).unwrap())
+		let c = self.content[self.read_position..].chars().next(); // None when there are no more characters
+		if self.mode > 1
+			&& let Some(c) = c
+		{
+			print_debug(
+				"Peeking char: ",
+				&c.to_string(),
+				self.logging,
+				&self.output_dir,
+			);
 		}
+		c
 	}
 
-	pub fn begin(&mut self) -> Result<Vec<LexerToken>, ApolloError> {
+	/// lexes the whole file, every invalid token is reported instead of stopping at the first one
+	pub fn begin(&mut self) -> Result<Vec<LexerToken>, Vec<ApolloError>> {
 		if self.mode > 0 {
 			print_debug(
 				"Lexing file: ",
@@ -336,36 +520,105 @@ impl Lexer {
 			);
 		} // debug msg
 		let mut tokens: Vec<LexerToken> = Vec::new();
-		while self.current_char.is_some() {
-			let tok = self.next_token();
-			let tok = tok.unwrap_or_else(|| LexerToken {
-				token_type: TokenType::ERROR,
-				value: "".to_string(),
-				metadata: Vec::new(),
-				line: self.current_line,
-				column: self.current_column,
-			});
-			if self.mode > 1 {
-				print_debug(
-					"Token generated: ",
-					&tok.to_string(),
-					self.logging,
-					&self.output_dir,
-				);
+		let mut errors: Vec<ApolloError> = Vec::new();
+		for token in self.by_ref() {
+			match token {
+				Ok(token) => tokens.push(token),
+				Err(error) => errors.push(error),
 			}
-			tokens.push(tok);
-			self.read_char();
-			if self.mode == 0 {
-				self.loading_bar.lerp(
-					((self.position as f32 / self.content.len() as f32) * 100.0).round() as i32,
-					false,
-				);
+		}
+		if errors.is_empty() {
+			Ok(tokens)
+		} else {
+			Err(errors)
+		}
+	}
+
+	pub fn filepath(&self) -> &str {
+		&self.filepath
+	}
+
+	/// the full text being lexed
+	pub fn source(&self) -> &str {
+		&self.content
+	}
+
+	/// the token `next` will return, without consuming it
+	pub fn peek(&mut self) -> Option<&Result<LexerToken, ApolloError>> {
+		self.peek_nth(0)
+	}
+
+	/// looks `n` tokens past the next one, the tokens in between are kept for `next`
+	pub fn peek_nth(&mut self, n: usize) -> Option<&Result<LexerToken, ApolloError>> {
+		while self.lookahead.len() <= n {
+			match self.scan() {
+				Some(token) => self.lookahead.push_back(token),
+				None => break,
+			}
+		}
+		self.lookahead.get(n)
+	}
+
+	/// scans the next token and moves past it, invalid tokens become errors
+	fn scan(&mut self) -> Option<Result<LexerToken, ApolloError>> {
+		if self.finished {
+			return None;
+		}
+		let token = self.next_token().unwrap_or_default();
+		if self.mode > 1 {
+			print_debug(
+				"Token generated: ",
+				&token.to_string(),
+				self.logging,
+				&self.output_dir,
+			);
+		}
+		self.read_char(); // `next_token` stops on the last character of the token
+		Some(match token.token_type {
+			TokenType::EOF => {
+				self.finished = true;
+				Ok(token)
 			}
+			TokenType::ERROR => Err(ApolloError::at(token.value, token.line, token.column)),
+			TokenType::UNKNOWN => Err(ApolloError::at(
+				format!("Unknown character '{}'", token.value.escape_default()),
+				token.line,
+				token.column,
+			)),
+			_ => Ok(token),
+		})
+	}
+
+	/// skips whitespace, then scans one token and records its span, the cursor stops on the token's last character
+	fn next_token(&mut self) -> Option<LexerToken> {
+		while self.current_char.is_some_and(Self::is_whitespace) {
+			self.read_char();
+		}
+		let start = self.position;
+		let start_line = self.current_line;
+		let start_column = self.current_column;
+		let mut token = self.scan_token()?;
+		// at the end of the file there is no last character to stop on
+		let (end, end_column) = match self.current_char {
+			Some(_) => (self.read_position, self.current_column + 1),
+			None => (self.position, self.current_column),
+		};
+		token.span = Span {
+			start_line,
+			start_column,
+			end_line: self.current_line,
+			end_column,
+			start,
+			end,
+		};
+		if token.token_type != TokenType::ERROR {
+			token.line = start_line;
+			token.column = start_column;
 		}
-		Result::Ok(tokens)
+		Some(token)
 	}
 
-	pub fn next_token(&mut self) -> Option<LexerToken> {
+	fn scan_token(&mut self) -> Option<LexerToken> {
 		if self.mode > 1 {
 			print_debug(
 				"Generating next token...",
@@ -390,12 +643,12 @@ impl Lexer {
 				metadata: Vec::new(),
 				line: self.current_line,
 				column: self.current_column,
+				span: Span::default(),
 			});
 		}
 
 		match self.current_char.unwrap() {
 			'0'..='9' => {
-				//TODO: handle hexadecimal, octal, binary, and float numbers -> 0x is hex, 0o is octal, 0b is binary, and float numbers can be handled with a decimal point and an f suffix
 				if self.mode > 1 {
 					print_debug(
 						"Found digit, parsing number...",
@@ -455,6 +708,7 @@ impl Lexer {
 								metadata: Vec::new(),
 								line: self.current_line,
 								column: self.current_column,
+								span: Span::default(),
 							})
 						} else if c == '>' {
 							Some(LexerToken {
@@ -463,6 +717,7 @@ impl Lexer {
 								metadata: Vec::new(),
 								line: self.current_line,
 								column: self.current_column,
+								span: Span::default(),
 							})
 						} else {
 							Some(LexerToken {
@@ -471,6 +726,7 @@ impl Lexer {
 								metadata: Vec::new(),
 								line: self.current_line,
 								column: self.current_column,
+								span: Span::default(),
 							})
 						} // This should not happen
 					}
@@ -486,6 +742,7 @@ impl Lexer {
 									metadata: Vec::new(),
 									line: self.current_line,
 									column: self.current_column,
+									span: Span::default(),
 								});
 							}
 							Some(LexerToken {
@@ -494,6 +751,7 @@ impl Lexer {
 								metadata: Vec::new(),
 								line: self.current_line,
 								column: self.current_column,
+								span: Span::default(),
 							})
 						} else {
 							Some(LexerToken {
@@ -502,6 +760,7 @@ impl Lexer {
 								metadata: Vec::new(),
 								line: self.current_line,
 								column: self.current_column,
+								span: Span::default(),
 							})
 						} // This should not happen
 					}
@@ -517,6 +776,7 @@ impl Lexer {
 									metadata: Vec::new(),
 									line: self.current_line,
 									column: self.current_column,
+									span: Span::default(),
 								});
 							}
 							Some(LexerToken {
@@ -525,6 +785,7 @@ impl Lexer {
 								metadata: Vec::new(),
 								line: self.current_line,
 								column: self.current_column,
+								span: Span::default(),
 							})
 						} else {
 							Some(LexerToken {
@@ -533,6 +794,7 @@ impl Lexer {
 								metadata: Vec::new(),
 								line: self.current_line,
 								column: self.current_column,
+								span: Span::default(),
 							})
 						} // This should not happen
 					}
@@ -545,6 +807,7 @@ impl Lexer {
 								metadata: Vec::new(),
 								line: self.current_line,
 								column: self.current_column,
+								span: Span::default(),
 							})
 						} else if c == '>' {
 							Some(LexerToken {
@@ -553,6 +816,7 @@ impl Lexer {
 								metadata: Vec::new(),
 								line: self.current_line,
 								column: self.current_column,
+								span: Span::default(),
 							})
 						} else {
 							Some(LexerToken {
@@ -561,6 +825,7 @@ impl Lexer {
 								metadata: Vec::new(),
 								line: self.current_line,
 								column: self.current_column,
+								span: Span::default(),
 							})
 						}
 					}
@@ -579,6 +844,7 @@ impl Lexer {
 							metadata: Vec::new(),
 							line: self.current_line,
 							column: self.current_column,
+							span: Span::default(),
 						})
 					}
 					Some('=') => {
@@ -590,6 +856,7 @@ impl Lexer {
 							metadata: Vec::new(),
 							line: self.current_line,
 							column: self.current_column,
+							span: Span::default(),
 						})
 					}
 					_ => {
@@ -600,6 +867,7 @@ impl Lexer {
 							metadata: Vec::new(),
 							line: self.current_line,
 							column: self.current_column,
+							span: Span::default(),
 						})
 					}
 				}
@@ -617,6 +885,7 @@ impl Lexer {
 							metadata: Vec::new(),
 							line: self.current_line,
 							column: self.current_column,
+							span: Span::default(),
 						})
 					}
 					Some('>') => {
@@ -628,6 +897,7 @@ impl Lexer {
 							metadata: Vec::new(),
 							line: self.current_line,
 							column: self.current_column,
+							span: Span::default(),
 						})
 					}
 					Some('=') => {
@@ -639,6 +909,7 @@ impl Lexer {
 							metadata: Vec::new(),
 							line: self.current_line,
 							column: self.current_column,
+							span: Span::default(),
 						})
 					}
 					_ => {
@@ -649,6 +920,7 @@ impl Lexer {
 							metadata: Vec::new(),
 							line: self.current_line,
 							column: self.current_column,
+							span: Span::default(),
 						})
 					}
 				}
@@ -666,6 +938,7 @@ impl Lexer {
 							metadata: Vec::new(),
 							line: self.current_line,
 							column: self.current_column,
+							span: Span::default(),
 						})
 					}
 					Some('>') => {
@@ -677,6 +950,7 @@ impl Lexer {
 							metadata: Vec::new(),
 							line: self.current_line,
 							column: self.current_column,
+							span: Span::default(),
 						})
 					}
 					_ => Some(LexerToken {
@@ -685,6 +959,7 @@ impl Lexer {
 						metadata: Vec::new(),
 						line: self.current_line,
 						column: self.current_column,
+						span: Span::default(),
 					}),
 				}
 			} // handle a = b, ==, =>
@@ -693,29 +968,43 @@ impl Lexer {
 				let next_char = self.peek_char();
 				match next_char {
 					Some('/') => {
-						// //
+						// // and //:
 						self.read_char(); // skip second /
-						self.read_char(); // make sure to skip that second /
+						let doc = self.peek_char() == Some(':');
+						if doc {
+							self.read_char();
+						}
 						let mut content: String = "".to_string();
-						while let Some(c) = self.current_char {
+						// stop on the last character of the comment so the newline is still tokenized
+						while let Some(c) = self.peek_char() {
+							if c == '\n' {
+								break;
+							}
+							self.read_char();
 							if c == '\r' {
 								// skip carriage return
-								self.read_char();
 								continue;
 							}
-							if c == '\n' {
-								// self.read_char();
-								break;
-							} else {
+							if self.mode > 1 {
 								print_debug(
 									"Adding new character to comment content: ",
 									&c.to_string(),
 									self.logging,
 									&self.output_dir,
 								);
-								content.push(c);
-								self.read_char();
 							}
+							content.push(c);
+						}
+						if doc {
+							// `//: text` documents the declaration below it, the space after the marker isn't part of the text
+							return Some(LexerToken {
+								token_type: TokenType::DOCCOMMENT,
+								value: content.strip_prefix(' ').unwrap_or(&content).to_string(),
+								metadata: Vec::new(),
+								line: self.current_line,
+								column: self.current_column,
+								span: Span::default(),
+							});
 						}
 						Some(LexerToken {
 							token_type: TokenType::LINECOMMENT,
@@ -723,22 +1012,46 @@ impl Lexer {
 							metadata: Vec::new(),
 							line: self.current_line,
 							column: self.current_column,
+							span: Span::default(),
 						})
 					}
 					Some('*') => {
-						// /* */
+						// /* */, nested comments have to be closed before the outer one ends
+						let start_line = self.current_line;
+						let start_column = self.current_column;
 						self.read_char();
 						self.read_char();
 						let mut content: String = "".to_string();
-						while let Some(c) = self.current_char {
-							if c == '*' && self.peek_char() == Some('/') {
-								self.read_char();
-								self.read_char();
-								break;
-							} else {
-								self.read_char();
-								content.push(c);
+						let mut depth: usize = 0;
+						loop {
+							match (self.current_char, self.peek_char()) {
+								(None, _) => {
+									return Some(LexerToken {
+										token_type: TokenType::ERROR,
+										value: "Unterminated block comment".to_string(),
+										metadata: Vec::new(),
+										line: start_line,
+										column: start_column,
+										span: Span::default(),
+									});
+								}
+								(Some('*'), Some('/')) if depth == 0 => {
+									self.read_char(); // stop on the closing /
+									break;
+								}
+								(Some('*'), Some('/')) => {
+									depth -= 1;
+									self.read_char();
+									content.push_str("*/");
+								}
+								(Some('/'), Some('*')) => {
+									depth += 1;
+									self.read_char();
+									content.push_str("/*");
+								}
+								(Some(c), _) => content.push(c),
 							}
+							self.read_char();
 						}
 						Some(LexerToken {
 							token_type: TokenType::BLOCKCOMMENT,
@@ -746,6 +1059,7 @@ impl Lexer {
 							metadata: Vec::new(),
 							line: self.current_line,
 							column: self.current_column,
+							span: Span::default(),
 						})
 					}
 					Some('=') => {
@@ -757,6 +1071,7 @@ impl Lexer {
 							metadata: Vec::new(),
 							line: self.current_line,
 							column: self.current_column,
+							span: Span::default(),
 						})
 					}
 					_ => {
@@ -767,6 +1082,7 @@ impl Lexer {
 							metadata: Vec::new(),
 							line: self.current_line,
 							column: self.current_column,
+							span: Span::default(),
 						})
 					}
 				}
@@ -782,6 +1098,7 @@ impl Lexer {
 							metadata: Vec::new(),
 							line: self.current_line,
 							column: self.current_column,
+							span: Span::default(),
 						})
 					}
 					_ => Some(LexerToken {
@@ -790,6 +1107,7 @@ impl Lexer {
 						metadata: Vec::new(),
 						line: self.current_line,
 						column: self.current_column,
+						span: Span::default(),
 					}),
 				}
 			} // handle a * b, a *= b, etc.
@@ -804,6 +1122,7 @@ impl Lexer {
 							metadata: Vec::new(),
 							line: self.current_line,
 							column: self.current_column,
+							span: Span::default(),
 						})
 					}
 					_ => Some(LexerToken {
@@ -812,6 +1131,7 @@ impl Lexer {
 						metadata: Vec::new(),
 						line: self.current_line,
 						column: self.current_column,
+						span: Span::default(),
 					}),
 				}
 			} // handle !a, a != b, etc.
@@ -826,6 +1146,7 @@ impl Lexer {
 							metadata: Vec::new(),
 							line: self.current_line,
 							column: self.current_column,
+							span: Span::default(),
 						})
 					}
 					_ => Some(LexerToken {
@@ -834,6 +1155,7 @@ impl Lexer {
 						metadata: Vec::new(),
 						line: self.current_line,
 						column: self.current_column,
+						span: Span::default(),
 					}),
 				}
 			} // handle a ^ b, a ^= b, etc.
@@ -849,6 +1171,7 @@ impl Lexer {
 							metadata: Vec::new(),
 							line: self.current_line,
 							column: self.current_column,
+							span: Span::default(),
 						})
 					}
 					Some('|') => {
@@ -860,6 +1183,7 @@ impl Lexer {
 							metadata: Vec::new(),
 							line: self.current_line,
 							column: self.current_column,
+							span: Span::default(),
 						})
 					}
 					_ => {
@@ -870,6 +1194,7 @@ impl Lexer {
 							metadata: Vec::new(),
 							line: self.current_line,
 							column: self.current_column,
+							span: Span::default(),
 						})
 					}
 				}
@@ -885,6 +1210,7 @@ impl Lexer {
 							metadata: Vec::new(),
 							line: self.current_line,
 							column: self.current_column,
+							span: Span::default(),
 						})
 					}
 					Some('&') => {
@@ -895,6 +1221,7 @@ impl Lexer {
 							metadata: Vec::new(),
 							line: self.current_line,
 							column: self.current_column,
+							span: Span::default(),
 						})
 					}
 					_ => Some(LexerToken {
@@ -903,6 +1230,7 @@ impl Lexer {
 						metadata: Vec::new(),
 						line: self.current_line,
 						column: self.current_column,
+						span: Span::default(),
 					}),
 				}
 			} // handle a & b, a &= b, a && b, etc.
@@ -912,35 +1240,34 @@ impl Lexer {
 				metadata: Vec::new(),
 				line: self.current_line,
 				column: self.current_column,
+				span: Span::default(),
 			}), // handle pass by reference (@a)
 			'#' => {
 				let next_char = self.peek_char();
 				match next_char {
 					Some('[') => {
-						// annotations
+						// annotations, the parser turns the tokens inside into a name and arguments
+						let start = self.position;
+						let start_line = self.current_line;
+						let start_column = self.current_column;
 						self.read_char(); // skip hash
-						self.read_char(); // skip opening bracket
-						let line: usize = self.current_line;
-						let column: usize = self.current_column;
-						let value: String = "#[{}]".to_string();
-						let mut mdata: Vec<char> = Vec::new();
-						while let Some(c) = self.current_char {
-							if c == ']' {
-								self.read_char(); // Skip the closing bracket
-								break;
-							} else {
-								mdata.push(c); // add all the inner characters to the metadata field for processing later
-								self.read_char();
-							}
+						// an unterminated annotation points at the `#` rather than the bracket
+						match self.parse_delimited(
+							TokenType::LEFTBRACKET,
+							TokenType::RIGHTBRACKET,
+							"Unterminated annotation, expected ']'",
+							(start_line, start_column),
+						) {
+							Ok(tokens) => Some(LexerToken {
+								token_type: TokenType::ANNOTATION,
+								value: self.content[start..self.read_position].to_string(),
+								metadata: vec![Metadata::Annotation(tokens)],
+								line: start_line,
+								column: start_column,
+								span: Span::default(),
+							}),
+							Err(error) => Some(error),
 						}
-
-						Some(LexerToken {
-							token_type: TokenType::ANNOTATION,
-							value,
-							metadata: vec![mdata],
-							line,
-							column,
-						})
 					}
 					_ => {
 						// could be used for custom operators?
@@ -950,6 +1277,7 @@ impl Lexer {
 							metadata: Vec::new(),
 							line: self.current_line,
 							column: self.current_column,
+							span: Span::default(),
 						})
 					}
 				}
@@ -966,6 +1294,7 @@ impl Lexer {
 							metadata: Vec::new(),
 							line: self.current_line,
 							column: self.current_column,
+							span: Span::default(),
 						})
 					}
 					_ => Some(LexerToken {
@@ -974,6 +1303,7 @@ impl Lexer {
 						metadata: Vec::new(),
 						line: self.current_line,
 						column: self.current_column,
+						span: Span::default(),
 					}),
 				}
 			} // handle a % b, a %= b, etc.
@@ -983,6 +1313,7 @@ impl Lexer {
 				metadata: Vec::new(),
 				line: self.current_line,
 				column: self.current_column,
+				span: Span::default(),
 			}), // handle ~a
 			'?' => Some(LexerToken {
 				token_type: TokenType::QUESTION,
@@ -990,6 +1321,7 @@ impl Lexer {
 				metadata: Vec::new(),
 				line: self.current_line,
 				column: self.current_column,
+				span: Span::default(),
 			}), // idk what to use this for, but handle it anyways
 			';' => Some(LexerToken {
 				token_type: TokenType::SEMICOLON,
@@ -997,6 +1329,7 @@ impl Lexer {
 				metadata: Vec::new(),
 				line: self.current_line,
 				column: self.current_column,
+				span: Span::default(),
 			}), // end statements
 			',' => Some(LexerToken {
 				token_type: TokenType::COMMA,
@@ -1004,6 +1337,7 @@ impl Lexer {
 				metadata: Vec::new(),
 				line: self.current_line,
 				column: self.current_column,
+				span: Span::default(),
 			}), // used for separating items in lists, function arguments, etc.
 			':' => Some(LexerToken {
 				token_type: TokenType::COLON,
@@ -1011,6 +1345,7 @@ impl Lexer {
 				metadata: Vec::new(),
 				line: self.current_line,
 				column: self.current_column,
+				span: Span::default(),
 			}), // used for type declarataions (a: u32)
 			'.' => Some(LexerToken {
 				token_type: TokenType::DOT,
@@ -1018,6 +1353,7 @@ impl Lexer {
 				metadata: Vec::new(),
 				line: self.current_line,
 				column: self.current_column,
+				span: Span::default(),
 			}), // used for method calls (a.b()), field access (a.b), etc.
 			'(' => Some(LexerToken {
 				token_type: TokenType::LEFTPAREN,
@@ -1025,6 +1361,7 @@ impl Lexer {
 				metadata: Vec::new(),
 				line: self.current_line,
 				column: self.current_column,
+				span: Span::default(),
 			}), // used for function calls (a()), grouping expressions ((a + b)), etc.
 			')' => Some(LexerToken {
 				token_type: TokenType::RIGHTPAREN,
@@ -1032,6 +1369,7 @@ impl Lexer {
 				metadata: Vec::new(),
 				line: self.current_line,
 				column: self.current_column,
+				span: Span::default(),
 			}), // used for closing function calls, grouping expressions, etc.
 			'{' => Some(LexerToken {
 				token_type: TokenType::LEFTBRACE,
@@ -1039,6 +1377,7 @@ impl Lexer {
 				metadata: Vec::new(),
 				line: self.current_line,
 				column: self.current_column,
+				span: Span::default(),
 			}), // used for starting blocks of code (if, for, while, etc.) and string interpolation "{a + b}"
 			'}' => Some(LexerToken {
 				token_type: TokenType::RIGHTBRACE,
@@ -1046,6 +1385,7 @@ impl Lexer {
 				metadata: Vec::new(),
 				line: self.current_line,
 				column: self.current_column,
+				span: Span::default(),
 			}), // used for closing blocks of code and string interpolation
 			'[' => Some(LexerToken {
 				token_type: TokenType::LEFTBRACKET,
@@ -1053,6 +1393,7 @@ impl Lexer {
 				metadata: Vec::new(),
 				line: self.current_line,
 				column: self.current_column,
+				span: Span::default(),
 			}), // used for starting arrays and indexing
 			']' => Some(LexerToken {
 				token_type: TokenType::RIGHTBRACKET,
@@ -1060,6 +1401,7 @@ impl Lexer {
 				metadata: Vec::new(),
 				line: self.current_line,
 				column: self.current_column,
+				span: Span::default(),
 			}), // used for closing arrays and indexing
 			'\n' => {
 				if self.mode > 1 {
@@ -1071,20 +1413,9 @@ impl Lexer {
 					metadata: Vec::new(),
 					line: self.current_line,
 					column: self.current_column,
+					span: Span::default(),
 				})
 			}
-			' ' | '\t' | '\r' => {
-				if self.mode > 1 {
-					print_debug(
-						"Found whitespace, skipping...",
-						"",
-						self.logging,
-						&self.output_dir,
-					);
-				}
-				self.read_char(); // Skip whitespace character
-				self.next_token() // Continue to the next character
-			}
 			_ => {
 				if self.mode > 1 {
 					print_debug(
@@ -1100,6 +1431,7 @@ impl Lexer {
 					metadata: Vec::new(),
 					line: self.current_line,
 					column: self.current_column,
+					span: Span::default(),
 				}) // Return error token for unknown characters
 			}
 		}
@@ -1109,209 +1441,169 @@ impl Lexer {
 		if self.mode > 1 {
 			print_debug("Backtracking...", "", self.logging, &self.output_dir);
 		}
-		if self.position == 0 {
-			self.current_char = None;
-		} else {
-			self.current_char = self.content.chars().nth(self.position);
-		}
-		self.position -= 1;
-		self.read_position -= 1;
-		if self.current_column == 0 {
-			self.current_line -= 1; // Decrement line number if at the start of a line
-			self.current_column = self.content[..self.position]
-				.chars()
-				.rev()
-				.take_while(|&c| c != '\n')
-				.count(); // Count characters until the last newline
-		} else {
-			self.current_column -= 1; // Decrement column number
-		}
+		// only ever undoes a single read, so restoring the saved cursor is enough
+		self.position = self.previous.position;
+		self.read_position = self.previous.read_position;
+		self.current_char = self.previous.current_char;
+		self.current_line = self.previous.line;
+		self.current_column = self.previous.column;
 	}
 
-	fn is_float_digit(c: char) -> bool {
-		c == '.' || c == 'f'
+	fn is_radix_digit(c: char, radix: u32) -> bool {
+		c == '_' || c.is_digit(radix)
 	}
-	fn is_octal_digit(c: char) -> bool {
-		('0'..='7').contains(&c)
+
+	/// consumes the characters following the current one while `accept` holds, leaving the cursor on the last one taken
+	fn take_while(&mut self, value: &mut String, accept: impl Fn(char) -> bool) {
+		while let Some(c) = self.peek_char() {
+			if !accept(c) {
+				break;
+			}
+			self.read_char();
+			value.push(c);
+		}
 	}
-	fn is_binary_digit(c: char) -> bool {
-		c == '0' || c == '1'
+
+	/// looks `n` characters past the next one without consuming anything
+	fn peek_char_at(&self, n: usize) -> Option<char> {
+		self.content[self.read_position..].chars().nth(n)
 	}
 
 	fn parse_number(&mut self) -> Option<LexerToken> {
 		if self.mode > 1 {
 			print_debug("Parsing number...", "", self.logging, &self.output_dir);
 		}
-		let start_position = self.position;
-		let mut value = String::new();
+		let start_line = self.current_line;
+		let start_column = self.current_column;
+		let error = |value: String| {
+			Some(LexerToken {
+				token_type: TokenType::ERROR,
+				value,
+				metadata: Vec::new(),
+				line: start_line,
+				column: start_column,
+				span: Span::default(),
+			})
+		};
+		let mut value = self.current_char.unwrap().to_string(); // the literal as written
 
-		while let Some(c) = self.current_char {
-			if c.is_ascii_hexdigit() || Self::is_float_digit(c) || c == 'o' || c == 'x' || c == 'b' {
-				// if number, add to number buffer and go to next char
-				value.push(c);
+		let (token_type, radix, name) = match (value.as_str(), self.peek_char()) {
+			("0", Some('x')) => (TokenType::HEXADECIMAL, 16, "hexadecimal"),
+			("0", Some('o')) => (TokenType::OCTAL, 8, "octal"),
+			("0", Some('b')) => (TokenType::BINARY, 2, "binary"),
+			_ => (TokenType::NUMBER, 10, "decimal"),
+		};
+		let mut token_type = token_type;
+
+		let digits_start;
+		if radix == 10 {
+			digits_start = 0;
+			self.take_while(&mut value, |c| Self::is_radix_digit(c, 10));
+			// a point only starts a fraction when a digit follows, so `1.max(2)` still calls a method
+			if self.peek_char() == Some('.') && self.peek_char_at(1).is_some_and(|c| c.is_ascii_digit()) {
 				self.read_char();
-			} else {
-				self.backtrack(); // move to previous character and exit loop
-				break;
+				value.push('.');
+				self.take_while(&mut value, |c| Self::is_radix_digit(c, 10));
+				token_type = TokenType::FLOAT;
+			}
+			let exponent = match (self.peek_char_at(1), self.peek_char_at(2)) {
+				(Some('+' | '-'), Some(c)) | (Some(c), _) => c.is_ascii_digit(),
+				_ => false,
+			};
+			if matches!(self.peek_char(), Some('e' | 'E')) && exponent {
+				self.read_char();
+				value.push(self.current_char.unwrap());
+				if let Some(sign @ ('+' | '-')) = self.peek_char() {
+					self.read_char();
+					value.push(sign);
+				}
+				self.take_while(&mut value, |c| Self::is_radix_digit(c, 10));
+				token_type = TokenType::FLOAT;
 			}
+		} else {
+			self.read_char();
+			value.push(self.current_char.unwrap());
+			digits_start = 2;
+			self.take_while(&mut value, |c| Self::is_radix_digit(c, radix));
 		}
+		let digits: String = value[digits_start..]
+			.chars()
+			.filter(|c| *c != '_')
+			.collect();
+
+		// anything glued to the literal is a suffix, so `12fxboa5` is reported instead of splitting into `12` and `fxboa5`
+		let mut suffix = String::new();
+		self.take_while(&mut suffix, |c| c.is_alphanumeric() || c == '_');
+		value.push_str(&suffix);
 
 		if self.mode > 1 {
 			print_debug("Value: ", &value, self.logging, &self.output_dir);
 		}
 
-		if value.starts_with('0') && !value.contains('.') && value.len() > 2 {
-			match value.chars().nth(1) {
-				Some('x') => {
-					if self.mode > 1 {
-						print_debug(
-							"Found hexadecimal number ",
-							&value,
-							self.logging,
-							&self.output_dir,
-						);
-					}
-					// check if all digits are within hexadecimal range then return hexadecimal number
-					for c in value[2..].chars() {
-						if !c.is_ascii_hexdigit() {
-							return Some(LexerToken {
-								token_type: TokenType::ERROR,
-								value,
-								metadata: Vec::new(),
-								line: self.current_line,
-								column: self.current_column - (self.position - start_position),
-							});
-						}
-					}
-					// reach here when everything succeeds
-					return Some(LexerToken {
-						token_type: TokenType::HEXADECIMAL,
-						value,
-						metadata: Vec::new(),
-						line: self.current_line,
-						column: self.current_column - (self.position - start_position),
-					});
-				}
-				Some('o') => {
-					// check if all digits are under 8 then return octal number
-					if self.mode > 1 {
-						print_debug(
-							"Found octal number ",
-							&value,
-							self.logging,
-							&self.output_dir,
-						);
-					}
-					for c in value[2..].chars() {
-						if !Self::is_octal_digit(c) {
-							return Some(LexerToken {
-								token_type: TokenType::ERROR,
-								value,
-								metadata: Vec::new(),
-								line: self.current_line,
-								column: self.current_column - (self.position - start_position),
-							});
-						}
-					}
-					return Some(LexerToken {
-						token_type: TokenType::OCTAL,
-						value,
-						metadata: Vec::new(),
-						line: self.current_line,
-						column: self.current_column - (self.position - start_position),
-					});
-				}
-				Some('b') => {
-					// check if all digits are 0 or 1 then return binary number
-					if self.mode > 1 {
-						print_debug(
-							"Found binary number ",
-							&value,
-							self.logging,
-							&self.output_dir,
-						);
-					}
-					for c in value[2..].chars() {
-						if !Self::is_binary_digit(c) {
-							return Some(LexerToken {
-								token_type: TokenType::ERROR,
-								value,
-								metadata: Vec::new(),
-								line: self.current_line,
-								column: self.current_column - (self.position - start_position),
-							});
-						}
-					}
-					return Some(LexerToken {
-						token_type: TokenType::BINARY,
-						value,
-						metadata: Vec::new(),
-						line: self.current_line,
-						column: self.current_column - (self.position - start_position),
-					});
-				}
-				_ => {
-					// do nothing
-				}
-			}
+		if radix != 10
+			&& suffix
+				.chars()
+				.next()
+				.is_some_and(|c| c.is_ascii_alphanumeric())
+			&& !suffix.starts_with(['u', 'i'])
+		{
+			return error(format!("Invalid {name} number '{value}'"));
 		}
-		if value.ends_with('f') {
-			if self.mode > 1 {
-				print_debug("Found float ", &value, self.logging, &self.output_dir);
-			}
-			let mut point_found = false;
-			for c in value[..value.len() - 1].chars() {
-				if !c.is_ascii_digit() && c != '.' {
-					return Some(LexerToken {
-						token_type: TokenType::ERROR,
-						value,
-						metadata: Vec::new(),
-						line: self.current_line,
-						column: self.current_column - (self.position - start_position),
-					});
-				}
-				if c == '.' && point_found {
-					return Some(LexerToken {
-						token_type: TokenType::ERROR,
-						value,
-						metadata: Vec::new(),
-						line: self.current_line,
-						column: self.current_column - (self.position - start_position),
-					});
-				} else if c == '.' {
-					point_found = true;
-				}
-			}
-			return Some(LexerToken {
-				token_type: TokenType::FLOAT,
-				value,
-				metadata: Vec::new(),
-				line: self.current_line,
-				column: self.current_column - (self.position - start_position),
-			});
+		if digits.is_empty() {
+			return error(format!(
+				"Invalid {name} number '{value}', expected digits after '{}'",
+				&value[..2]
+			));
+		}
+		let float_suffix = suffix == "f" || suffix == "f32" || suffix == "f64";
+		if !suffix.is_empty() && suffix != "f" && !NUMBER_SUFFIXES.contains(&suffix.as_str()) {
+			return error(format!(
+				"Invalid number '{value}', '{suffix}' is not a numeric suffix"
+			));
+		}
+		if float_suffix && radix != 10 {
+			return error(format!(
+				"Invalid {name} number '{value}', only decimal numbers can be floats"
+			));
+		}
+		if token_type == TokenType::FLOAT && !suffix.is_empty() && !float_suffix {
+			return error(format!(
+				"Invalid float '{value}', '{suffix}' is an integer suffix"
+			));
 		}
 
-		for c in value.chars() {
-			if !c.is_ascii_digit() {
-				return Some(LexerToken {
-					token_type: TokenType::ERROR,
-					value,
-					metadata: Vec::new(),
-					line: self.current_line,
-					column: self.current_column - (self.position - start_position),
-				});
+		let number = if token_type == TokenType::FLOAT || float_suffix {
+			token_type = TokenType::FLOAT;
+			match digits.parse::<f64>() {
+				Ok(float) if float.is_finite() => NumberValue::Float(float),
+				_ => return error(format!("Float '{value}' is too large")),
 			}
-		}
+		} else {
+			match u64::from_str_radix(&digits, radix) {
+				Ok(integer) => NumberValue::Integer(integer),
+				Err(_) => return error(format!("Number '{value}' does not fit in 64 bits")),
+			}
+		};
 
 		if self.mode > 1 {
-			print_debug("Parsed integer: ", &value, self.logging, &self.output_dir);
+			print_debug(
+				"Parsed number: ",
+				&format!("{number:?}"),
+				self.logging,
+				&self.output_dir,
+			);
 		}
 		Some(LexerToken {
-			token_type: TokenType::NUMBER,
+			token_type,
 			value,
-			metadata: Vec::new(),
-			line: self.current_line,
-			column: self.current_column - (self.position - start_position),
+			metadata: vec![Metadata::Number {
+				value: number,
+				// the legacy `f` only marks a float, it doesn't pick a width
+				suffix: Some(suffix).filter(|suffix| !suffix.is_empty() && suffix != "f"),
+			}],
+			line: start_line,
+			column: start_column,
+			span: Span::default(),
 		})
 	}
 
@@ -1319,7 +1611,8 @@ impl Lexer {
 		if self.mode > 1 {
 			print_debug("Parsing identifier...", "", self.logging, &self.output_dir);
 		}
-		let start_position = self.position;
+		let start_line = self.current_line;
+		let start_column = self.current_column;
 		let mut value = String::new();
 
 		while let Some(c) = self.current_char {
@@ -1341,48 +1634,81 @@ impl Lexer {
 			);
 		}
 		Some(LexerToken {
-			token_type: TokenType::IDENTIFIER,
+			token_type: TokenType::keyword(&value).unwrap_or(TokenType::IDENTIFIER),
 			value,
 			metadata: Vec::new(),
-			line: self.current_line,
-			column: self.current_column - (self.position - start_position), // Adjust column based on the length of the identifier
+			line: start_line,
+			column: start_column,
+			span: Span::default(),
 		})
 	}
 
 	fn parse_string(&mut self) -> Option<LexerToken> {
-		//TODO: need to update to be able to handle escaping \{, \}, and string interpolation
 		if self.mode > 1 {
 			print_debug("Parsing string...", "", self.logging, &self.output_dir);
 		}
-		let start_position = self.position;
-		let mut value = String::new();
-		self.read_char(); // Skip the opening quote
+		let start_line = self.current_line;
+		let start_column = self.current_column;
+		let mut value = String::new(); // every embedded expression shows up as `{}`
+		let mut text = String::new(); // literal text since the last embedded expression
 		let mut metadata = Vec::new();
-		// split up string into multiple sections and rejoin at the { and }
-		// so it will either break at " or {, and start again with }
+		let mut error = None; // first invalid escape, the rest of the string is still skipped
+		self.read_char(); // Skip the opening quote
 
 		while let Some(c) = self.current_char {
-			if c == '"' {
-				self.read_char(); // Skip the closing quote
-				break;
-			} else if c == '{' {
-				value.push(c); // push '{' to string before splitting
-				self.read_char(); // skip '{'
-				let mut inner_content = Vec::new();
-				while let Some(inner_c) = self.current_char {
-					if inner_c == '}' {
-						value.push(inner_c); // push closing character
-						self.read_char();
-						break;
+			match c {
+				'"' => break, // stop on the closing quote
+				'\\' => match self.parse_escape() {
+					Ok(c) => {
+						value.push(c);
+						text.push(c);
+					}
+					Err(e) => {
+						error.get_or_insert(e);
+					}
+				},
+				'{' => {
+					value.push_str("{}");
+					if !text.is_empty() {
+						metadata.push(Metadata::Text(std::mem::take(&mut text)));
+					}
+					let brace = (self.current_line, self.current_column);
+					match self.parse_delimited(
+						TokenType::LEFTBRACE,
+						TokenType::RIGHTBRACE,
+						"Unterminated string interpolation",
+						brace,
+					) {
+						Ok(tokens) => metadata.push(Metadata::Expression(tokens)),
+						Err(e) if self.current_char.is_none() => return Some(e),
+						Err(e) => {
+							error.get_or_insert(e);
+						}
 					}
-					inner_content.push(inner_c); // change metadata from Vec<Vec<LexerToken>> to Vec<Vec<char>>
-					self.read_char();
 				}
-				metadata.push(inner_content); // push the contents of the {} to metadata
-			} else {
-				value.push(c);
-				self.read_char();
+				_ => {
+					value.push(c);
+					text.push(c);
+				}
 			}
+			self.read_char();
+		}
+		if self.current_char.is_none() {
+			return Some(LexerToken {
+				token_type: TokenType::ERROR,
+				value: "Unterminated string".to_string(),
+				metadata: Vec::new(),
+				line: start_line,
+				column: start_column,
+				span: Span::default(),
+			});
+		}
+		if let Some(error) = error {
+			return Some(error);
+		}
+		// plain strings carry no metadata, only interpolated ones are split into parts
+		if !metadata.is_empty() && !text.is_empty() {
+			metadata.push(Metadata::Text(text));
 		}
 
 		if self.mode > 1 {
@@ -1392,34 +1718,209 @@ impl Lexer {
 			token_type: TokenType::STRING,
 			value,
 			metadata,
-			line: self.current_line,
-			column: self.current_column - (self.position - start_position), // Adjust column based on the length of the string
+			line: start_line,
+			column: start_column,
+			span: Span::default(),
+		})
+	}
+
+	/// lexes the inside of `{...}` in a string or `#[...]`, starting on the opening bracket and
+	/// stopping on the matching `close`, nested brackets and strings are lexed like anywhere else.
+	/// The first invalid token inside is returned with its own position once `close` is reached,
+	/// running out of input reports `unterminated` at `start` instead
+	fn parse_delimited(
+		&mut self,
+		open: TokenType,
+		close: TokenType,
+		unterminated: &str,
+		start: (usize, usize),
+	) -> Result<Vec<LexerToken>, LexerToken> {
+		let mut tokens = Vec::new();
+		let mut depth: usize = 0;
+		let mut error = None;
+		self.read_char(); // skip the opening bracket
+
+		while let Some(token) = self.next_token() {
+			match token.token_type {
+				TokenType::EOF => break,
+				TokenType::ERROR => {
+					error.get_or_insert(token);
+					self.read_char();
+					continue;
+				}
+				TokenType::UNKNOWN => {
+					error.get_or_insert(LexerToken {
+						token_type: TokenType::ERROR,
+						value: format!("Unknown character '{}'", token.value.escape_default()),
+						..token
+					});
+					self.read_char();
+					continue;
+				}
+				t if t == open => depth += 1,
+				t if t == close && depth == 0 => {
+					tokens.push(token);
+					return match error {
+						Some(error) => Err(error),
+						None => Ok(tokens),
+					};
+				}
+				t if t == close => depth -= 1,
+				_ => {}
+			}
+			if token.token_type != TokenType::NEWLINE {
+				tokens.push(token);
+			}
+			self.read_char();
+		}
+		Err(LexerToken {
+			token_type: TokenType::ERROR,
+			value: unterminated.to_string(),
+			metadata: Vec::new(),
+			line: start.0,
+			column: start.1,
+			span: Span::default(),
 		})
 	}
 
+	/// decodes the escape sequence starting on the current `\`, stops on its last character
+	fn parse_escape(&mut self) -> Result<char, LexerToken> {
+		let line = self.current_line;
+		let column = self.current_column;
+		let invalid = |message: String| LexerToken {
+			token_type: TokenType::ERROR,
+			value: message,
+			metadata: Vec::new(),
+			line,
+			column,
+			span: Span::default(),
+		};
+		self.read_char(); // skip '\'
+		let Some(c) = self.current_char else {
+			return Err(invalid("Unterminated escape sequence".to_string()));
+		};
+		match c {
+			'n' => Ok('\n'),
+			't' => Ok('\t'),
+			'r' => Ok('\r'),
+			'0' => Ok('\0'),
+			'\\' | '\'' | '"' | '{' | '}' => Ok(c),
+			'x' => {
+				// exactly two hex digits, limited to ASCII so a char always fits in a byte
+				let mut digits = String::new();
+				while digits.len() < 2
+					&& let Some(d) = self.peek_char().filter(char::is_ascii_hexdigit)
+				{
+					self.read_char();
+					digits.push(d);
+				}
+				match u8::from_str_radix(&digits, 16) {
+					Ok(byte) if digits.len() == 2 && byte.is_ascii() => Ok(byte as char),
+					Ok(_) if digits.len() == 2 => Err(invalid(format!(
+						"Escape sequence '\\x{digits}' is out of range, '\\x' only goes up to 7F"
+					))),
+					_ => Err(invalid(format!(
+						"Expected two hexadecimal digits after '\\x', found '\\x{digits}'"
+					))),
+				}
+			}
+			'u' => {
+				if self.peek_char() != Some('{') {
+					return Err(invalid("Expected '{' after '\\u'".to_string()));
+				}
+				self.read_char();
+				let mut digits = String::new();
+				while let Some(d) = self.peek_char().filter(char::is_ascii_hexdigit) {
+					self.read_char();
+					digits.push(d);
+				}
+				if self.peek_char() != Some('}') {
+					return Err(invalid(format!(
+						"Expected '}}' to close the unicode escape '\\u{{{digits}'"
+					)));
+				}
+				self.read_char(); // stop on the closing brace
+				if digits.is_empty() || digits.len() > 6 {
+					return Err(invalid(format!(
+						"Unicode escape '\\u{{{digits}}}' needs 1 to 6 hexadecimal digits"
+					)));
+				}
+				u32::from_str_radix(&digits, 16)
+					.ok()
+					.and_then(char::from_u32)
+					.ok_or_else(|| {
+						invalid(format!(
+							"'\\u{{{digits}}}' is not a valid unicode character"
+						))
+					})
+			}
+			_ => Err(invalid(format!(
+				"Unknown escape sequence '\\{}'",
+				c.escape_default()
+			))),
+		}
+	}
+
 	fn parse_character(&mut self) -> Option<LexerToken> {
 		if self.mode > 1 {
 			print_debug("Parsing character...", "", self.logging, &self.output_dir);
 		}
-		let start_position = self.position;
+		let start_line = self.current_line;
+		let start_column = self.current_column;
 		let mut value = String::new();
+		let mut error = None;
 		self.read_char(); // Skip the opening quote
 
-		let no_escape = self.current_char != Some('\\');
-
 		while let Some(c) = self.current_char {
-			if c == '\'' {
-				self.read_char(); // Skip the closing quote
-				break;
-			} else {
-				value.push(c);
-				self.read_char();
+			match c {
+				'\'' => break, // stop on the closing quote
+				'\\' => match self.parse_escape() {
+					Ok(c) => value.push(c),
+					Err(e) => {
+						error.get_or_insert(e);
+					}
+				},
+				_ => value.push(c),
 			}
+			self.read_char();
 		}
 
-		if value.len() != 1 && no_escape {
-			eprintln!("{ERR}Error: {MSG}Invalid character literal: {INFO}'{}'{MSG}. Expected a single character.{ERR} Located @ Line: {INFO}{}{ERR}, Column: {INFO}{}{ERR}.{RESET}", value, self.current_line, self.current_column);
-			std::process::exit(1);
+		if self.current_char.is_none() {
+			return Some(LexerToken {
+				token_type: TokenType::ERROR,
+				value: "Unterminated character literal".to_string(),
+				metadata: Vec::new(),
+				line: start_line,
+				column: start_column,
+				span: Span::default(),
+			});
+		}
+		if let Some(error) = error {
+			return Some(error);
+		}
+		if value.chars().count() != 1 {
+			return Some(LexerToken {
+				token_type: TokenType::ERROR,
+				value: format!(
+					"Invalid character literal '{}', expected a single character",
+					value.escape_default()
+				),
+				metadata: Vec::new(),
+				line: start_line,
+				column: start_column,
+				span: Span::default(),
+			});
+		}
+		// a char is a single byte all the way down to the generated C
+		if !value.is_ascii() {
+			return Some(LexerToken {
+				token_type: TokenType::ERROR,
+				value: format!("'{value}' does not fit in a char, only ASCII characters are supported"),
+				metadata: Vec::new(),
+				line: start_line,
+				column: start_column,
+				span: Span::default(),
+			});
 		}
 
 		if self.mode > 1 {
@@ -1429,8 +1930,18 @@ impl Lexer {
 			token_type: TokenType::CHARACTER,
 			value,
 			metadata: Vec::new(),
-			line: self.current_line,
-			column: self.current_column - (self.position - start_position), // Adjust column based on the length of the character
+			line: start_line,
+			column: start_column,
+			span: Span::default(),
 		})
 	}
 }
+
+/// yields every token of the file up to and including `EOF`, so a consumer can stop partway through
+impl Iterator for Lexer {
+	type Item = Result<LexerToken, ApolloError>;
+
+	fn next(&mut self) -> Option<Self::Item> {
+		self.lookahead.pop_front().or_else(|| self.scan())
+	}
+}
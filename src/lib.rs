@@ -0,0 +1,11 @@
+pub mod builtins;
+pub mod compiler;
+pub mod cst;
+pub mod lexer;
+pub mod parser;
+pub mod resolver;
+pub mod semantic;
+pub mod toolchain;
+pub mod tui;
+pub mod typechecker;
+pub mod util;
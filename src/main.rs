@@ -1,17 +1,22 @@
-pub mod lexer;
-pub mod parser;
-pub mod tui;
-pub mod util;
-
 use std::io::{self, Write};
 
-use lexer::Lexer;
-use parser::Parser;
-use std::path::Path;
+use apollo::compiler::Compiler;
+use apollo::lexer::Lexer;
+use apollo::parser::Parser;
+use apollo::resolver::Resolver;
+use apollo::semantic::Analyzer;
+use apollo::toolchain::Toolchain;
+use apollo::tui::LoadingBar;
+use apollo::typechecker::TypeChecker;
+use apollo::util::{ApolloError, CLEAR, DEBUG, ERR, INFO, MSG, RESET, SUCCESS};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
 use std::process::Command;
-use util::{CLEAR, DEBUG, ERR, INFO, MSG, RESET, SUCCESS};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::mpsc;
 
 const VERSION: &str = "0.0.0-A";
+const MAX_TASKS: usize = 5; // default size of the --dir worker pool
 
 fn main() {
 	// take in cmdline args
@@ -67,13 +72,17 @@ fn main() {
 		return;
 	}
 
+	// `build` also compiles the generated C into a native executable, `run` then executes it
+	let run = args[1] == "run";
+	let build = run || args[1] == "build";
+
 	let file = args.iter().position(|x| x == "-f" || x == "--file");
 	let dir = args.iter().position(|x| x == "--dir");
 
 	let help_flag = args.contains(&"-h".to_string()) || args.contains(&"--help".to_string());
 	let version_flag = args.contains(&"--version".to_string());
 	if help_flag {
-		println!("{MSG}Usage: apollo [options]\nOptions:\n  clean                Delete the output directory and it's contents.\n  -f, --file <file>    Specify a file to compile\n  --dir <directory>    Specify a directory to compile\n  -d, --debug          Enable debug mode\n  -v, --verbose        Enable verbose mode\n  -q, --quiet          Disable all output except for errors\n  -o, --output <dir>   Specify the output directory (default: ./out)\n  -l, --lib <libs>     Specify libraries to pull and compile\n  -h, --help           Show this help message\n  --log                Enable logging, placed in <output dir>/logs/\n  --version            Show version number\nVersions are in the format <major>.<minor>.<patch>-<Alpha/Beta/Release>\n{RESET}");
+		println!("{MSG}Usage: apollo [command] [options]\nCommands:\n  clean                Delete the output directory and it's contents.\n  build                Compile the generated C into <output dir>/bin/<name>\n  run                  Build and then execute the program\nOptions:\n  -f, --file <file>    Specify a file to compile\n  --dir <directory>    Specify a directory to compile\n  -j, --jobs <n>       Number of files compiled at once with --dir (default: 5)\n  -d, --debug          Enable debug mode\n  -v, --verbose        Enable verbose mode\n  -q, --quiet          Disable all output except for errors\n  -o, --output <dir>   Specify the output directory (default: ./out)\n  -l, --lib <libs>     Specify libraries to pull and compile\n  --cc <compiler>      C compiler used by build and run (default: $CC, cc, gcc or clang)\n  -h, --help           Show this help message\n  --log                Enable logging, placed in <output dir>/logs/\n  --version            Show version number\nVersions are in the format <major>.<minor>.<patch>-<Alpha/Beta/Release>\n{RESET}");
 		return;
 	}
 	if version_flag {
@@ -150,7 +159,16 @@ fn main() {
 
 	// -l and --lib flags would be processed similarly, rn they are unsupported
 
-	// iterate through every file and send them to a compile task (thread pool, max 5 threads/tasks)
+	let cc = args.iter().position(|x| x == "--cc").map(|index| {
+		if index + 1 < args.len() {
+			unsanatized_args[index + 1].clone()
+		} else {
+			eprintln!("{ERR}Error: {MSG}--cc flag requires a compiler argument.{RESET}");
+			std::process::exit(1);
+		}
+	});
+
+	// iterate through every file and send them to a compile task (thread pool, max 5 threads/tasks by default)
 	// lexer -> parser -> compiler
 
 	let mut mode: u8 = 0; // 0: quiet, 1: debug, 2: verbose
@@ -163,6 +181,25 @@ fn main() {
 		mode = 1;
 	}
 
+	let jobs = match args.iter().position(|x| x == "-j" || x == "--jobs") {
+		Some(index) => match args.get(index + 1).and_then(|n| n.parse::<usize>().ok()) {
+			Some(jobs) if jobs > 0 => jobs,
+			_ => {
+				eprintln!("{ERR}Error: {MSG}-j/--jobs flag requires a number of tasks above 0.{RESET}");
+				std::process::exit(1);
+			}
+		},
+		None => MAX_TASKS,
+	};
+
+	let options = Options {
+		mode,
+		logging,
+		output_dir: output_dir.clone(),
+		build,
+		cc,
+	};
+
 	print!("{CLEAR}");
 
 	if let Some(dir) = dir {
@@ -173,12 +210,105 @@ fn main() {
 			eprintln!("{ERR}Error: {MSG}{dir} does not exist in the current working directory.{RESET}");
 			std::process::exit(1);
 		}
+		if run {
+			eprintln!("{ERR}Error: {MSG}run needs a single file, use -f/--file instead of --dir.{RESET}");
+			std::process::exit(1);
+		}
 
 		if mode > 0 {
 			println!("{DEBUG}Compiling directory: {INFO}{dir}{RESET}");
 		}
 
 		// iterate over every valid file in the directory and compile to target
+		let mut files = Vec::new();
+		collect_files(p, &mut files);
+		files.sort();
+		if files.is_empty() {
+			eprintln!("{ERR}Error: {MSG}{dir} does not contain any .sun files.{RESET}");
+			std::process::exit(1);
+		}
+
+		// outputs are named after the file alone, so two files with the same name would overwrite
+		// each other's C source and executable
+		let mut names: HashMap<String, &PathBuf> = HashMap::new();
+		let mut clashes = 0;
+		for file in &files {
+			let name = file.file_stem().unwrap_or_default().to_string_lossy().to_string();
+			if let Some(other) = names.insert(name.clone(), file) {
+				eprintln!(
+					"{ERR}Error: {MSG}{} and {} would both be compiled to {INFO}{name}.c{MSG}, rename one of them.{RESET}",
+					other.display(),
+					file.display()
+				);
+				clashes += 1;
+			}
+		}
+		if clashes > 0 {
+			std::process::exit(1);
+		}
+
+		// a pool of workers pulls files off a shared counter and reports back as each one finishes
+		let next = AtomicUsize::new(0);
+		let (sender, receiver) = mpsc::channel();
+		let mut results: Vec<Option<(Option<String>, Vec<ApolloError>)>> =
+			(0..files.len()).map(|_| None).collect();
+		std::thread::scope(|scope| {
+			for _ in 0..jobs.min(files.len()) {
+				let sender = sender.clone();
+				let (next, files, options) = (&next, &files, &options);
+				scope.spawn(move || {
+					loop {
+						let index = next.fetch_add(1, Ordering::SeqCst);
+						let Some(file) = files.get(index) else {
+							break;
+						};
+						let result = compile(&file.to_string_lossy(), options);
+						if sender.send((index, result)).is_err() {
+							break;
+						}
+					}
+				});
+			}
+			drop(sender);
+
+			let mut bar = (mode == 0).then(LoadingBar::new);
+			let mut done = 0;
+			for (index, result) in receiver {
+				results[index] = Some(result);
+				done += 1;
+				if let Some(bar) = &mut bar {
+					bar.lerp(((done * 100) / files.len()) as i32, false);
+				}
+			}
+		});
+
+		let mut failed = 0;
+		for (file, result) in files.iter().zip(results) {
+			let (output, diagnostics) = result.unwrap_or((None, Vec::new()));
+			if !diagnostics.is_empty() {
+				eprintln!("{MSG}{}:{RESET}", file.display());
+				for diagnostic in &diagnostics {
+					diagnostic.print();
+				}
+			}
+			match output {
+				Some(output) if mode > 0 => {
+					println!("{SUCCESS}Compiled {INFO}{}{SUCCESS} to {INFO}{output}{RESET}", file.display())
+				}
+				Some(_) => {}
+				None => failed += 1,
+			}
+		}
+		if failed > 0 {
+			eprintln!(
+				"{ERR}Error: {MSG}{failed} of {} files failed to compile.{RESET}",
+				files.len()
+			);
+			std::process::exit(1);
+		}
+		if mode > 0 {
+			println!("{SUCCESS}Compiled {} files successfully.{RESET}", files.len());
+		}
 	} else if let Some(file) = file {
 		let file = unsanatized_args[file + 1].clone(); // filepath
 
@@ -192,33 +322,166 @@ fn main() {
 			println!("{DEBUG}Compiling file: {INFO}{file}{RESET}");
 		}
 
-		let result = Lexer::new(file, mode, logging, output_dir.clone()).begin();
-		match result {
-			Ok(tokens) => {
-				if mode > 0 {
-					println!("{SUCCESS}Lexing completed successfully.{RESET}");
-				}
-				if logging {
-					let mut log_file = std::fs::OpenOptions::new()
-						.write(true)
-						.create(true)
-						.open(format!("{}/logs/lexer_tokens.log", output_dir.clone()))
-						.unwrap_or_else(|e| {
-							panic!("{ERR}Failed to open {INFO}lexer_tokens.log{ERR} file: {e}{RESET}")
-						});
-					writeln!(log_file, "{tokens:#?}").unwrap_or_else(|e| {
-						panic!("{ERR}Failed to write to {INFO}lexer_tokens.log{ERR} file:\n\t{e}{RESET}")
-					});
-					println!("{SUCCESS}Logged lexer tokens to {INFO}lexer_tokens.log{SUCCESS} file{RESET}");
+		let mut bar = (mode == 0).then(LoadingBar::new);
+		let (output, diagnostics) = compile(&file, &options);
+		if let Some(bar) = &mut bar {
+			bar.lerp(100, false);
+		}
+		for diagnostic in &diagnostics {
+			diagnostic.print();
+		}
+		let Some(output) = output else {
+			std::process::exit(1);
+		};
+
+		if run {
+			// everything after `--` is passed on to the program
+			let program_args = unsanatized_args.iter().skip_while(|x| *x != "--").skip(1);
+			match Command::new(&output).args(program_args).status() {
+				Ok(status) => std::process::exit(status.code().unwrap_or(1)),
+				Err(e) => {
+					eprintln!("{ERR}Error: {MSG}Failed to run {output}.{RESET} {e}");
+					std::process::exit(1);
 				}
-				//TODO: if logging flag, write parser_tree to logs/parser_tree.log file
-			}
-			Err(e) => {
-				e.print();
-				std::process::exit(1);
 			}
 		}
 	} else {
 		eprintln!("{ERR}Error: {MSG}Encountered an unexpected compiler state, quitting...{RESET}");
 	}
 }
+
+/// settings shared by every compile task
+struct Options {
+	mode: u8,
+	logging: bool,
+	output_dir: String,
+	build: bool,
+	cc: Option<String>,
+}
+
+/// every `.sun` file in `dir` and its subdirectories
+fn collect_files(dir: &Path, files: &mut Vec<PathBuf>) {
+	let Ok(entries) = std::fs::read_dir(dir) else {
+		return;
+	};
+	for entry in entries.flatten() {
+		let path = entry.path();
+		if path.is_dir() {
+			collect_files(&path, files);
+		} else if path.extension().is_some_and(|e| e == "sun") {
+			files.push(path);
+		}
+	}
+}
+
+/// runs every stage on one file, returns the generated C file (or the executable when building)
+/// together with every diagnostic, the path is `None` if any stage failed
+fn compile(file: &str, options: &Options) -> (Option<String>, Vec<ApolloError>) {
+	let Options {
+		mode,
+		logging,
+		output_dir,
+		build,
+		cc,
+	} = options;
+	let (mode, logging) = (*mode, *logging);
+
+	let lexer = match Lexer::new(file.to_string(), mode, logging, output_dir.clone()) {
+		Ok(lexer) => lexer,
+		Err(e) => return (None, vec![e]),
+	};
+	// the parser pulls tokens as it goes, lexer errors come back together with syntax errors
+	let mut parser = Parser::from_lexer(lexer, mode, logging, output_dir.clone());
+	let parsed = parser.begin();
+	// named after the source file so --dir workers don't write over each other's logs
+	let stem = Path::new(file)
+		.file_stem()
+		.map(|s| s.to_string_lossy().to_string())
+		.unwrap_or_default();
+	if logging {
+		let log_name = format!("{stem}.tokens.log");
+		let mut log_file = std::fs::OpenOptions::new()
+			.write(true)
+			.create(true)
+			.truncate(true)
+			.open(format!("{}/logs/{log_name}", output_dir.clone()))
+			.unwrap_or_else(|e| {
+				panic!("{ERR}Failed to open {INFO}{log_name}{ERR} file: {e}{RESET}")
+			});
+		writeln!(log_file, "{:#?}", parser.tokens()).unwrap_or_else(|e| {
+			panic!("{ERR}Failed to write to {INFO}{log_name}{ERR} file:\n\t{e}{RESET}")
+		});
+		println!("{SUCCESS}Logged lexer tokens to {INFO}{log_name}{SUCCESS} file{RESET}");
+	}
+	let program = match parsed {
+		Ok(program) => program,
+		Err(errors) => return (None, errors),
+	};
+	if mode > 0 {
+		println!("{SUCCESS}Parsing completed successfully.{RESET}");
+	}
+	if logging {
+		let log_name = format!("{stem}.tree.log");
+		let mut log_file =
+			std::fs::File::create(format!("{}/logs/{log_name}", output_dir.clone()))
+				.unwrap_or_else(|e| {
+					panic!("{ERR}Failed to open {INFO}{log_name}{ERR} file: {e}{RESET}")
+				});
+		writeln!(log_file, "{program:#?}").unwrap_or_else(|e| {
+			panic!("{ERR}Failed to write to {INFO}{log_name}{ERR} file:\n\t{e}{RESET}")
+		});
+		println!("{SUCCESS}Logged parser tree to {INFO}{log_name}{SUCCESS} file{RESET}");
+	}
+
+	let mut diagnostics = Resolver::new(&program, mode, logging, output_dir.clone()).begin();
+	diagnostics.extend(Analyzer::new(&program, mode, logging, output_dir.clone()).begin());
+	if diagnostics.iter().any(|d| !d.is_warning()) {
+		return (None, diagnostics);
+	}
+	if mode > 0 {
+		println!("{SUCCESS}Semantic analysis completed successfully.{RESET}");
+	}
+
+	diagnostics.extend(TypeChecker::new(&program, mode, logging, output_dir.clone()).begin());
+	if diagnostics.iter().any(|d| !d.is_warning()) {
+		return (None, diagnostics);
+	}
+	if mode > 0 {
+		println!("{SUCCESS}Type checking completed successfully.{RESET}");
+	}
+
+	let source = match Compiler::new(&program, mode, logging, output_dir.clone()).begin() {
+		Ok(path) => path,
+		Err(e) => {
+			diagnostics.push(e);
+			return (None, diagnostics);
+		}
+	};
+	if mode > 0 {
+		println!("{SUCCESS}Generated C source: {INFO}{source}{RESET}");
+	}
+	if !build {
+		return (Some(source), diagnostics);
+	}
+
+	let toolchain = match Toolchain::find(cc.clone(), mode, logging, output_dir.clone()) {
+		Ok(toolchain) => toolchain,
+		Err(e) => {
+			diagnostics.push(e);
+			return (None, diagnostics);
+		}
+	};
+	match toolchain.build(&source) {
+		Ok((executable, warnings)) => {
+			diagnostics.extend(warnings);
+			if mode > 0 {
+				println!("{SUCCESS}Built executable: {INFO}{executable}{RESET}");
+			}
+			(Some(executable), diagnostics)
+		}
+		Err(errors) => {
+			diagnostics.extend(errors);
+			(None, diagnostics)
+		}
+	}
+}
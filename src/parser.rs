@@ -1,39 +1,494 @@
-use std::fmt::{Debug, Display};
-
 use crate::lexer;
-use crate::tui;
 use crate::util;
 
-use lexer::LexerToken;
-use tui::LoadingBar;
-use util::print_debug;
+use lexer::{Lexer, LexerToken, Metadata, NumberValue, Span, TokenType};
 use util::ApolloError;
-use util::{DEBUG, ERR, INFO, MSG, RESET, SUCCESS};
-use util::{DOWN, LEFT, RIGHT, UP};
+use util::print_debug;
+
+/// A fully parsed `.sun` file
+#[derive(Debug, Clone)]
+pub struct Program {
+	pub filepath: String,
+	pub items: Vec<Item>,
+}
+
+/// `#[name]` or `#[name: arguments]` placed above a declaration
+#[derive(Debug, Clone)]
+pub struct Annotation {
+	pub name: String,
+	/// comma separated values after the `:`, empty for annotations without arguments
+	pub arguments: Vec<AnnotationArgument>,
+	pub line: usize,
+	pub column: usize,
+}
+
+#[derive(Debug, Clone)]
+pub enum AnnotationArgument {
+	/// `"stdio.printf"`
+	String(String),
+	/// `string` in `#[link: string]`, a name or type
+	Name(String),
+	Number(NumberValue),
+	Boolean(bool),
+}
+
+impl AnnotationArgument {
+	/// the kind of argument as written in `builtins::ANNOTATIONS`
+	pub fn kind(&self) -> &'static str {
+		match self {
+			AnnotationArgument::String(_) => "string",
+			AnnotationArgument::Name(_) => "name",
+			AnnotationArgument::Number(_) => "number",
+			AnnotationArgument::Boolean(_) => "bool",
+		}
+	}
+}
+
+/// A declaration, either at the top level of a file or inside a `class`/`extend` body
+#[derive(Debug, Clone)]
+pub struct Item {
+	pub kind: ItemKind,
+	pub annotations: Vec<Annotation>,
+	/// `//:` lines above the declaration, one entry per line
+	pub docs: Vec<String>,
+	pub public: bool,
+	pub line: usize,
+	pub column: usize,
+}
+
+#[derive(Debug, Clone)]
+pub enum ItemKind {
+	Use(Vec<String>), // use stdlib.random; -> ["stdlib", "random"]
+	Function(Function),
+	Class(Class),
+	Struct(Struct),
+	Enum(Enum),
+	Extend(Extend),
+	Variable(Variable), // top-level constants and class fields
+	/// a declaration that failed to parse, the error is reported by the parser
+	Error,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Fixity {
+	Unary,
+	Binary,
+}
+
+#[derive(Debug, Clone)]
+pub struct Function {
+	/// operator functions are named after their operator, e.g. `--`
+	pub name: String,
+	/// set for `unary operator --()` style declarations
+	pub operator: Option<Fixity>,
+	pub params: Vec<Param>,
+	/// `None` when no `-> Type` was written
+	pub return_type: Option<Type>,
+	/// `None` for bodyless declarations like `#[extern]` functions
+	pub body: Option<Block>,
+}
+
+impl Function {
+	/// operator functions are keyed by fixity so `unary operator -` and `operator -` can coexist
+	pub fn key(&self) -> String {
+		match self.operator {
+			Some(fixity) => format!("{}{fixity:?}", self.name),
+			None => self.name.clone(),
+		}
+	}
+}
+
+#[derive(Debug, Clone)]
+pub struct Param {
+	pub name: String,
+	pub ty: Type,
+	/// `const str: string` or `str: const string`
+	pub constant: bool,
+	/// `...data: Any`
+	pub variadic: bool,
+	pub line: usize,
+	pub column: usize,
+}
+
+#[derive(Debug, Clone)]
+pub struct Class {
+	pub name: String,
+	/// parameters of the implicit constructor, `class Player(health: u8)`
+	pub params: Vec<Param>,
+	/// `from Error`
+	pub parent: Option<Type>,
+	pub members: Vec<Item>,
+}
+
+#[derive(Debug, Clone)]
+pub struct Struct {
+	pub name: String,
+	pub fields: Vec<Field>,
+}
+
+#[derive(Debug, Clone)]
+pub struct Field {
+	pub name: String,
+	pub ty: Type,
+	pub line: usize,
+	pub column: usize,
+}
+
+#[derive(Debug, Clone)]
+pub struct Enum {
+	pub name: String,
+	pub variants: Vec<Variant>,
+}
+
+#[derive(Debug, Clone)]
+pub struct Variant {
+	pub name: String,
+	/// explicit discriminant, `ACE = 1`
+	pub value: Option<Expression>,
+	pub line: usize,
+	pub column: usize,
+}
 
-pub enum ParserTokenType {
-	// primitives
+#[derive(Debug, Clone)]
+pub struct Extend {
+	pub target: Type,
+	pub members: Vec<Item>,
 }
 
-pub struct ParserToken {
-	pub token_type: ParserTokenType,
-	pub value: Vec<LexerToken>,
+#[derive(Debug, Clone)]
+pub struct Variable {
+	pub constant: bool,
+	pub name: String,
+	pub ty: Option<Type>,
+	pub value: Option<Expression>,
+}
+
+#[derive(Debug, Clone)]
+pub enum Type {
+	/// `u8`, `Player`, `Vector<Card>`
+	Named { name: String, generics: Vec<Type> },
+	/// `[u8; 256]`
+	Array {
+		element: Box<Type>,
+		length: Box<Expression>,
+	},
+	/// `[char]`
+	Slice(Box<Type>),
+	/// `string[progLen]`
+	Sized {
+		base: Box<Type>,
+		length: Box<Expression>,
+	},
+	/// `@Player`
+	Reference(Box<Type>),
+}
+
+#[derive(Debug, Clone)]
+pub struct Block {
+	pub statements: Vec<Statement>,
+	pub line: usize,
+	pub column: usize,
+}
+
+#[derive(Debug, Clone)]
+pub struct Statement {
+	pub kind: StatementKind,
+	pub line: usize,
+	pub column: usize,
+}
+
+#[derive(Debug, Clone)]
+pub enum StatementKind {
+	Variable(Variable),
+	Expression(Expression),
+	Return(Option<Expression>),
+	If {
+		condition: Expression,
+		then_block: Block,
+		/// either another `If` statement or a `Block` statement
+		else_branch: Option<Box<Statement>>,
+	},
+	While {
+		condition: Expression,
+		body: Block,
+	},
+	/// `for(@i, n => n < times) { ... }`
+	For {
+		arguments: Vec<Expression>,
+		body: Block,
+	},
+	Panic(Expression),
+	Block(Block),
+	/// `{ ... } catch(IllegalArgument e) { ... }`, re-runs `body` whenever the handler hits `retry`
+	Catch {
+		body: Block,
+		error_type: Type,
+		binding: String,
+		handler: Block,
+	},
+	Retry,
+	/// a statement that failed to parse, the error is reported by the parser
+	Error,
+}
+
+#[derive(Debug, Clone)]
+pub struct Expression {
+	pub kind: ExpressionKind,
+	pub line: usize,
+	pub column: usize,
+}
+
+#[derive(Debug, Clone)]
+pub enum ExpressionKind {
+	Literal(Literal),
+	Identifier(String),
+	SelfValue,
+	Unary {
+		operator: TokenType,
+		operand: Box<Expression>,
+	},
+	Postfix {
+		operator: TokenType,
+		operand: Box<Expression>,
+	},
+	Binary {
+		left: Box<Expression>,
+		operator: TokenType,
+		right: Box<Expression>,
+	},
+	/// `=` as well as every compound assignment like `+=`
+	Assign {
+		target: Box<Expression>,
+		operator: TokenType,
+		value: Box<Expression>,
+	},
+	Call {
+		callee: Box<Expression>,
+		arguments: Vec<Expression>,
+	},
+	Member {
+		object: Box<Expression>,
+		name: String,
+	},
+	Index {
+		object: Box<Expression>,
+		index: Box<Expression>,
+	},
+	/// `new`, `new (1, 2)`, `new 2`, `new IllegalArgument("...")` and `Dealer new`
+	New {
+		ty: Option<Type>,
+		arguments: Vec<Expression>,
+	},
+	/// `Card { face = FACES[i], suit = SUITS[j] }`
+	StructLiteral {
+		name: String,
+		fields: Vec<(String, Expression)>,
+	},
+	/// `{sum, altsum}`
+	ArrayLiteral(Vec<Expression>),
+	/// `temp as u8`
+	Cast {
+		value: Box<Expression>,
+		ty: Type,
+	},
+	/// `@player`, passes `player` by reference
+	Reference(Box<Expression>),
+	/// `{ |const c: char| ... }`, usually passed as a trailing lambda
+	Closure {
+		params: Vec<Param>,
+		body: Block,
+	},
+	/// `n => n < times`
+	Lambda {
+		param: String,
+		body: Box<Expression>,
+	},
+	/// `match cmd { '>' -> ptr++; _ -> panic ...; }`
+	Match {
+		subject: Box<Expression>,
+		arms: Vec<MatchArm>,
+	},
+	/// `"{player.name} has won!"`, a string with embedded expressions
+	Format(Vec<FormatPart>),
+}
+
+#[derive(Debug, Clone)]
+pub enum FormatPart {
+	Text(String),
+	Expression(Expression),
+}
+
+#[derive(Debug, Clone)]
+pub struct MatchArm {
+	pub pattern: Pattern,
+	/// a single statement like `ptr++;` or a block statement
+	pub body: Statement,
 	pub line: usize,
 	pub column: usize,
 }
 
+#[derive(Debug, Clone)]
+pub enum Pattern {
+	/// `_`
+	Wildcard,
+	/// `'a'`, `1`, `"h"`, `true`
+	Literal(Literal),
+	/// `ACE` or `FACES.ACE`
+	Variant(Vec<String>),
+}
+
+#[derive(Debug, Clone)]
+pub enum Literal {
+	/// keeps the lexer token kind so the type checker can tell `NUMBER` from `FLOAT`
+	Number {
+		/// the literal as written, `1_000u32`
+		value: String,
+		kind: TokenType,
+		number: NumberValue,
+		/// `u8` in `255u8`
+		suffix: Option<String>,
+	},
+	String(String),
+	Character(String),
+	Boolean(bool),
+}
+
+/// How tightly an operator binds, from loosest to tightest
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum Precedence {
+	Lowest,
+	Assignment,
+	Lambda,
+	BoolOr,
+	BoolAnd,
+	BitOr,
+	BitXor,
+	BitAnd,
+	Equality,
+	Comparison,
+	Shift,
+	Additive,
+	Multiplicative,
+	Cast,
+	Prefix,
+	Postfix,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Associativity {
+	Left,
+	Right,
+}
+
+/// where an operator sits relative to its operand(s)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Position {
+	Prefix,
+	Infix,
+	Postfix,
+}
+
+/// Every operator token the lexer emits and how it binds, loosest first.
+///
+/// A few tokens show up more than once because their meaning depends on where they sit:
+/// `-a` is a prefix negation, `a - b` is a subtraction and `self.dealer-` calls a custom
+/// `unary operator -`. `+` and `-` are only treated as postfix operators when nothing that
+/// could start an operand follows them.
+///
+/// `as` is the only keyword operator and binds at `Precedence::Cast`, just above `*`, so
+/// `(c as u16) - 48` needs no parentheses around the cast. `->` never binds as an operator,
+/// it only separates return types and match arms.
+#[rustfmt::skip]
+const OPERATOR_TABLE: [(TokenType, Position, Precedence, Associativity); 46] = [
+	// a = b, a += b, ... (right associative, `a = b = c` assigns `c` to both)
+	(TokenType::ASSIGN, Position::Infix, Precedence::Assignment, Associativity::Right),
+	(TokenType::ADDASSIGN, Position::Infix, Precedence::Assignment, Associativity::Right),
+	(TokenType::SUBASSIGN, Position::Infix, Precedence::Assignment, Associativity::Right),
+	(TokenType::STARASSIGN, Position::Infix, Precedence::Assignment, Associativity::Right),
+	(TokenType::DIVASSIGN, Position::Infix, Precedence::Assignment, Associativity::Right),
+	(TokenType::MODASSIGN, Position::Infix, Precedence::Assignment, Associativity::Right),
+	(TokenType::LEFTSHIFTASSIGN, Position::Infix, Precedence::Assignment, Associativity::Right),
+	(TokenType::RIGHTSHIFTASSIGN, Position::Infix, Precedence::Assignment, Associativity::Right),
+	(TokenType::BITANDASSIGN, Position::Infix, Precedence::Assignment, Associativity::Right),
+	(TokenType::BITORASSIGN, Position::Infix, Precedence::Assignment, Associativity::Right),
+	(TokenType::CARROTASSIGN, Position::Infix, Precedence::Assignment, Associativity::Right),
+	// n => n < times
+	(TokenType::LAMBDA, Position::Infix, Precedence::Lambda, Associativity::Right),
+	// a || b
+	(TokenType::BOOLOR, Position::Infix, Precedence::BoolOr, Associativity::Left),
+	// a && b
+	(TokenType::BOOLAND, Position::Infix, Precedence::BoolAnd, Associativity::Left),
+	// a | b
+	(TokenType::BAR, Position::Infix, Precedence::BitOr, Associativity::Left),
+	// a ^ b
+	(TokenType::CARROT, Position::Infix, Precedence::BitXor, Associativity::Left),
+	// a & b
+	(TokenType::AMPERSAND, Position::Infix, Precedence::BitAnd, Associativity::Left),
+	// a == b, a != b
+	(TokenType::EQL, Position::Infix, Precedence::Equality, Associativity::Left),
+	(TokenType::BANGASSIGN, Position::Infix, Precedence::Equality, Associativity::Left),
+	// a < b, a <= b, a > b, a >= b
+	(TokenType::LESS, Position::Infix, Precedence::Comparison, Associativity::Left),
+	(TokenType::LESSEQL, Position::Infix, Precedence::Comparison, Associativity::Left),
+	(TokenType::GREATER, Position::Infix, Precedence::Comparison, Associativity::Left),
+	(TokenType::GREATEREQL, Position::Infix, Precedence::Comparison, Associativity::Left),
+	// a << b, a >> b
+	(TokenType::LEFTSHIFT, Position::Infix, Precedence::Shift, Associativity::Left),
+	(TokenType::RIGHTSHIFT, Position::Infix, Precedence::Shift, Associativity::Left),
+	// a + b, a - b
+	(TokenType::PLUS, Position::Infix, Precedence::Additive, Associativity::Left),
+	(TokenType::MINUS, Position::Infix, Precedence::Additive, Associativity::Left),
+	// a * b, a / b, a % b
+	(TokenType::STAR, Position::Infix, Precedence::Multiplicative, Associativity::Left),
+	(TokenType::DIVIDE, Position::Infix, Precedence::Multiplicative, Associativity::Left),
+	(TokenType::PERCENT, Position::Infix, Precedence::Multiplicative, Associativity::Left),
+	// a as T
+	(TokenType::AS, Position::Infix, Precedence::Cast, Associativity::Left),
+	// -a, !a, ~a, ++a, --a, @a
+	(TokenType::MINUS, Position::Prefix, Precedence::Prefix, Associativity::Right),
+	(TokenType::BANG, Position::Prefix, Precedence::Prefix, Associativity::Right),
+	(TokenType::SQUIGGLE, Position::Prefix, Precedence::Prefix, Associativity::Right),
+	(TokenType::INCREMENT, Position::Prefix, Precedence::Prefix, Associativity::Right),
+	(TokenType::DECREMENT, Position::Prefix, Precedence::Prefix, Associativity::Right),
+	(TokenType::ATSIGN, Position::Prefix, Precedence::Prefix, Associativity::Right),
+	// a++, a--, a(), a[i], a.b and the custom operators a+, a-, a~, a?
+	(TokenType::INCREMENT, Position::Postfix, Precedence::Postfix, Associativity::Left),
+	(TokenType::DECREMENT, Position::Postfix, Precedence::Postfix, Associativity::Left),
+	(TokenType::LEFTPAREN, Position::Postfix, Precedence::Postfix, Associativity::Left),
+	(TokenType::LEFTBRACKET, Position::Postfix, Precedence::Postfix, Associativity::Left),
+	(TokenType::DOT, Position::Postfix, Precedence::Postfix, Associativity::Left),
+	(TokenType::PLUS, Position::Postfix, Precedence::Postfix, Associativity::Left),
+	(TokenType::MINUS, Position::Postfix, Precedence::Postfix, Associativity::Left),
+	(TokenType::SQUIGGLE, Position::Postfix, Precedence::Postfix, Associativity::Left),
+	(TokenType::QUESTION, Position::Postfix, Precedence::Postfix, Associativity::Left),
+];
+
+fn lookup_operator(
+	token_type: TokenType,
+	position: Position,
+) -> Option<(Precedence, Associativity)> {
+	OPERATOR_TABLE
+		.iter()
+		.find(|(t, p, _, _)| *t == token_type && *p == position)
+		.map(|(_, _, precedence, associativity)| (*precedence, *associativity))
+}
+
 pub struct Parser {
 	filepath: String,
 	debug_mode: u8,
 	logging: bool,
 	output_dir: String,
-	loading_bar: LoadingBar,
 
 	// additional private fields for parser state
-	content: Vec<LexerToken>,
-	position: usize,
-	read_position: usize,
-	current_token: Option<LexerToken>,
+	content: Vec<LexerToken>,                    // every token pulled so far
+	lexer: Option<Lexer>,                        // source of further tokens when parsing while lexing
+	errors: Vec<ApolloError>, // syntax errors recovered from and invalid tokens pulled from `lexer`
+	position: usize,          // index of the current (non-trivia) token
+	previous: usize,          // index of the last consumed token
+	struct_literals: bool,    // false while parsing `if`/`while` conditions
+	arm_body: bool,           // true while parsing a match arm, where `,` also ends the statement
+	outer: Vec<(Vec<LexerToken>, usize, usize)>, // saved token cursors while parsing string interpolations
+	docs: Vec<String>, // `//:` comments in front of the current token, taken by the next declaration
 
 	// parser file position
 	current_line: usize,
@@ -47,45 +502,1568 @@ impl Parser {
 		debug_mode: u8,
 		logging: bool,
 		output_dir: String,
+	) -> Self {
+		let mut content = lexer_tokens;
+		if !matches!(content.last(), Some(t) if t.token_type == TokenType::EOF) {
+			content.push(Self::eof_after(content.last()));
+		}
+		Self::with_source(filepath, content, None, debug_mode, logging, output_dir)
+	}
+
+	/// parses while lexing, tokens are only pulled from `lexer` as far as the parser looks ahead
+	pub fn from_lexer(lexer: Lexer, debug_mode: u8, logging: bool, output_dir: String) -> Self {
+		let filepath = lexer.filepath().to_string();
+		Self::with_source(
+			filepath,
+			Vec::new(),
+			Some(lexer),
+			debug_mode,
+			logging,
+			output_dir,
+		)
+	}
+
+	fn with_source(
+		filepath: String,
+		content: Vec<LexerToken>,
+		lexer: Option<Lexer>,
+		debug_mode: u8,
+		logging: bool,
+		output_dir: String,
 	) -> Self {
 		let mut p = Parser {
 			filepath,
 			debug_mode,
 			logging,
 			output_dir,
-			loading_bar: LoadingBar::new(),
-			content: lexer_tokens,
+			content,
+			lexer,
+			errors: Vec::new(),
 			position: 0,
-			read_position: 0,
-			current_token: None,
+			previous: 0,
+			struct_literals: true,
+			arm_body: false,
+			outer: Vec::new(),
+			docs: Vec::new(),
 			current_line: 1,
 			current_column: 0,
 		};
-		p.read_token();
+		p.skip_trivia();
 		p
 	}
 
-	fn read_token(&mut self) {
-		if self.read_position >= self.content.len() {
-			if self.debug_mode > 0 {
-				print_debug(
-					"Reached end of token list: ",
-					&self.filepath,
-					self.logging,
-					&self.output_dir,
-				);
+	/// the tokens read from the lexer so far, trivia included, every token of the file once parsed
+	pub fn tokens(&self) -> &[LexerToken] {
+		&self.content
+	}
+
+	/// an `EOF` token placed right after `last`, for token lists that don't end in one
+	fn eof_after(last: Option<&LexerToken>) -> LexerToken {
+		let span = last.map_or(
+			Span {
+				start_line: 1,
+				start_column: 1,
+				end_line: 1,
+				end_column: 1,
+				..Span::default()
+			},
+			|t| Span {
+				start_line: t.span.end_line,
+				start_column: t.span.end_column,
+				start: t.span.end,
+				..t.span
+			},
+		);
+		LexerToken {
+			token_type: TokenType::EOF,
+			value: "".to_string(),
+			metadata: Vec::new(),
+			line: span.start_line,
+			column: span.start_column,
+			span,
+		}
+	}
+
+	/// parses the whole file, every syntax error in it is reported instead of only the first
+	pub fn begin(&mut self) -> Result<Program, Vec<ApolloError>> {
+		let (program, errors) = self.parse();
+		if errors.is_empty() {
+			Ok(program)
+		} else {
+			Err(errors)
+		}
+	}
+
+	/// the program with `Error` nodes in place of declarations and statements that failed to parse,
+	/// together with the syntax errors in source order
+	pub fn parse(&mut self) -> (Program, Vec<ApolloError>) {
+		if self.debug_mode > 0 {
+			print_debug(
+				"Parsing file: ",
+				&self.filepath,
+				self.logging,
+				&self.output_dir,
+			);
+		}
+		let mut items = Vec::new();
+		while !self.check(TokenType::EOF) {
+			let start = self.position;
+			match self.parse_item() {
+				Ok(item) => items.push(item),
+				Err(error) => items.push(self.recover_item(error, start, false)),
+			}
+		}
+		if self.debug_mode > 0 {
+			print_debug(
+				"Reached end of token list: ",
+				&self.filepath,
+				self.logging,
+				&self.output_dir,
+			);
+		}
+		// tokens pulled ahead by lookahead can report lexer errors before earlier syntax errors
+		let mut errors = std::mem::take(&mut self.errors);
+		errors.sort_by_key(|e| e.position());
+		let program = Program {
+			filepath: self.filepath.clone(),
+			items,
+		};
+		(program, errors)
+	}
+
+	// ---------------------------------------------------------------------------
+	// token cursor
+	// ---------------------------------------------------------------------------
+
+	fn is_trivia(token_type: TokenType) -> bool {
+		matches!(
+			token_type,
+			TokenType::NEWLINE | TokenType::LINECOMMENT | TokenType::BLOCKCOMMENT | TokenType::DOCCOMMENT
+		)
+	}
+
+	/// pulls tokens from the lexer until `index` is buffered, token lists from metadata are always complete
+	fn fill(&mut self, index: usize) {
+		if !self.outer.is_empty() {
+			return;
+		}
+		while self.content.len() <= index {
+			let token = match self.lexer.as_mut().and_then(Iterator::next) {
+				Some(Ok(token)) => token,
+				// syntax errors at it are only a symptom, `report` leaves them out for the lexer's message
+				Some(Err(error)) => {
+					let (line, column) = error.position().unwrap_or((0, 0));
+					let token = LexerToken {
+						token_type: TokenType::ERROR,
+						value: error.message().to_string(),
+						line,
+						column,
+						..LexerToken::default()
+					};
+					self.errors.push(error);
+					token
+				}
+				None => Self::eof_after(self.content.last()),
+			};
+			self.content.push(token);
+		}
+	}
+
+	fn skip_trivia(&mut self) {
+		self.fill(self.position);
+		while Self::is_trivia(self.content[self.position].token_type) {
+			let token = &self.content[self.position];
+			if token.token_type == TokenType::DOCCOMMENT {
+				self.docs.push(token.value.clone());
+			}
+			self.position += 1;
+			self.fill(self.position);
+		}
+	}
+
+	fn peek(&self) -> &LexerToken {
+		&self.content[self.position]
+	}
+
+	/// look `n` significant tokens ahead, `peek_nth(0)` is the current token
+	fn peek_nth(&mut self, n: usize) -> &LexerToken {
+		let mut index = self.position;
+		let mut remaining = n;
+		while remaining > 0 && self.content[index].token_type != TokenType::EOF {
+			index += 1;
+			self.fill(index);
+			while Self::is_trivia(self.content[index].token_type) {
+				index += 1;
+				self.fill(index);
+			}
+			remaining -= 1;
+		}
+		&self.content[index]
+	}
+
+	fn read_token(&mut self) -> LexerToken {
+		let token = self.content[self.position].clone();
+		if self.debug_mode > 1 {
+			print_debug(
+				"Reading token: ",
+				&token.to_string(),
+				self.logging,
+				&self.output_dir,
+			);
+		}
+		self.current_line = token.line;
+		self.current_column = token.column;
+		// doc comments carry over annotations, anything else means they didn't belong to a declaration
+		if token.token_type != TokenType::ANNOTATION {
+			self.docs.clear();
+		}
+		if token.token_type != TokenType::EOF {
+			self.previous = self.position;
+			self.position += 1;
+			self.skip_trivia();
+		}
+		token
+	}
+
+	/// true when a line break separates the current token from the last consumed one
+	fn newline_before(&self) -> bool {
+		// line comments always run to the end of their line
+		self.content[self.previous + 1..self.position]
+			.iter()
+			.any(|t| {
+				matches!(
+					t.token_type,
+					TokenType::NEWLINE | TokenType::LINECOMMENT | TokenType::DOCCOMMENT
+				)
+			})
+	}
+
+	fn check(&self, token_type: TokenType) -> bool {
+		self.peek().token_type == token_type
+	}
+
+	fn eat(&mut self, token_type: TokenType) -> bool {
+		if self.check(token_type) {
+			self.read_token();
+			true
+		} else {
+			false
+		}
+	}
+
+	fn expect(&mut self, token_type: TokenType, what: &str) -> Result<LexerToken, ApolloError> {
+		if self.check(token_type) {
+			Ok(self.read_token())
+		} else {
+			Err(self.error(&format!("Expected {what}")))
+		}
+	}
+
+	fn expect_identifier(&mut self, what: &str) -> Result<LexerToken, ApolloError> {
+		let token = self.peek();
+		if token.token_type == TokenType::IDENTIFIER {
+			Ok(self.read_token())
+		} else {
+			Err(self.error(&format!("Expected {what}")))
+		}
+	}
+
+	/// `>` closing a generic list, splits `>>` so `Vector<Vector<u8>>` works
+	fn expect_closing_angle(&mut self) -> Result<(), ApolloError> {
+		if self.check(TokenType::RIGHTSHIFT) {
+			let token = &mut self.content[self.position];
+			token.token_type = TokenType::GREATER;
+			token.value = ">".to_string();
+			token.column += 1;
+			token.span.start_column += 1;
+			token.span.start += 1;
+			return Ok(());
+		}
+		self.expect(TokenType::GREATER, "'>'")?;
+		Ok(())
+	}
+
+	/// statements end in `;`, but a line break or a closing brace is also accepted
+	fn expect_terminator(&mut self) -> Result<(), ApolloError> {
+		if self.eat(TokenType::SEMICOLON)
+			|| self.check(TokenType::RIGHTBRACE)
+			|| self.check(TokenType::EOF)
+			|| self.content[self.previous].token_type == TokenType::RIGHTBRACE
+			|| self.newline_before()
+			|| (self.arm_body && self.check(TokenType::COMMA))
+		{
+			Ok(())
+		} else {
+			Err(self.error("Expected ';'"))
+		}
+	}
+
+	fn error(&self, message: &str) -> ApolloError {
+		let token = self.peek();
+		let found = match token.token_type {
+			TokenType::EOF if !self.outer.is_empty() => format!("'{}'", token.value),
+			TokenType::EOF => "end of file".to_string(),
+			TokenType::ERROR | TokenType::UNKNOWN => format!("invalid token '{}'", token.value),
+			_ => format!("'{}'", token.value),
+		};
+		ApolloError::spanning(format!("{message}, found {found}"), token.span)
+	}
+
+	/// the source range from the token at index `start` up to the last consumed token
+	fn span_from(&self, start: usize) -> Span {
+		let first = self.content[start].span;
+		if self.previous < start {
+			return first;
+		}
+		let last = self.content[self.previous].span;
+		Span {
+			end_line: last.end_line,
+			end_column: last.end_column,
+			end: last.end,
+			..first
+		}
+	}
+
+	// ---------------------------------------------------------------------------
+	// error recovery
+	// ---------------------------------------------------------------------------
+
+	/// keeps `error` for `begin` unless it only follows from an invalid token the lexer already reported
+	fn report(&mut self, error: ApolloError) {
+		// an unterminated string or comment runs to the end of the file, so errors there are symptoms too
+		let symptom = match self.peek().token_type {
+			TokenType::ERROR => true,
+			TokenType::EOF => self
+				.content
+				.iter()
+				.any(|t| t.token_type == TokenType::ERROR),
+			_ => false,
+		};
+		if self.lexer.is_none() || !symptom {
+			self.errors.push(error);
+		}
+	}
+
+	fn starts_declaration(token_type: TokenType) -> bool {
+		matches!(
+			token_type,
+			TokenType::USE
+				| TokenType::FN
+				| TokenType::UNARY
+				| TokenType::CLASS
+				| TokenType::STRUCT
+				| TokenType::ENUM
+				| TokenType::EXTEND
+				| TokenType::VAR
+				| TokenType::CONST
+				| TokenType::PUBLIC
+				| TokenType::ANNOTATION
+		)
+	}
+
+	/// skips ahead after an error to where parsing can resume: past a `;` or the `}` closing a
+	/// skipped body, or up to a keyword that starts a declaration, `nested` leaves the `}` of the
+	/// enclosing block for its owner
+	fn synchronize(&mut self, start: usize, nested: bool) {
+		// at least one token goes, otherwise the same token would fail again
+		let mut skipped = self.position > start;
+		let mut depth = 0;
+		loop {
+			let token_type = self.peek().token_type;
+			match token_type {
+				TokenType::EOF => return,
+				TokenType::SEMICOLON if depth == 0 => {
+					self.read_token();
+					return;
+				}
+				TokenType::LEFTBRACE => depth += 1,
+				TokenType::RIGHTBRACE if depth > 1 => depth -= 1,
+				TokenType::RIGHTBRACE if depth == 1 || !nested => {
+					self.read_token();
+					return;
+				}
+				TokenType::RIGHTBRACE => return,
+				_ if depth == 0 && skipped && Self::starts_declaration(token_type) => return,
+				_ => {}
+			}
+			self.read_token();
+			skipped = true;
+		}
+	}
+
+	/// skips the rest of a match arm that began at index `start`: past its `,` or `;` or the `}` of
+	/// its block, or up to the `}` closing the match or the next arm's pattern on a new line
+	fn synchronize_arm(&mut self, start: usize) {
+		let mut skipped = self.position > start;
+		let mut depth = 0;
+		loop {
+			let token_type = self.peek().token_type;
+			match token_type {
+				TokenType::EOF => return,
+				TokenType::COMMA | TokenType::SEMICOLON if depth == 0 => {
+					self.read_token();
+					return;
+				}
+				TokenType::LEFTBRACE => depth += 1,
+				TokenType::RIGHTBRACE if depth == 0 => return,
+				TokenType::RIGHTBRACE if depth == 1 => {
+					self.read_token();
+					return;
+				}
+				TokenType::RIGHTBRACE => depth -= 1,
+				_ if depth == 0
+					&& skipped
+					&& self.newline_before()
+					&& self.peek_nth(1).token_type == TokenType::RIGHTARROW =>
+				{
+					return;
+				}
+				_ => {}
+			}
+			self.read_token();
+			skipped = true;
+		}
+	}
+
+	/// reports `error` and skips the rest of the declaration that began at index `start`
+	fn recover_item(&mut self, error: ApolloError, start: usize, nested: bool) -> Item {
+		self.report(error);
+		let token = self.content[start].clone();
+		self.synchronize(start, nested);
+		self.docs.clear();
+		Item {
+			kind: ItemKind::Error,
+			annotations: Vec::new(),
+			docs: Vec::new(),
+			public: false,
+			line: token.line,
+			column: token.column,
+		}
+	}
+
+	/// runs `f` with struct literals enabled or disabled, restoring the previous state afterwards
+	fn with_struct_literals<T>(
+		&mut self,
+		allowed: bool,
+		f: impl FnOnce(&mut Self) -> Result<T, ApolloError>,
+	) -> Result<T, ApolloError> {
+		let saved = self.struct_literals;
+		self.struct_literals = allowed;
+		let result = f(self);
+		self.struct_literals = saved;
+		result
+	}
+
+	// ---------------------------------------------------------------------------
+	// items
+	// ---------------------------------------------------------------------------
+
+	fn parse_annotations(&mut self) -> Result<Vec<Annotation>, ApolloError> {
+		let mut annotations = Vec::new();
+		while self.check(TokenType::ANNOTATION) {
+			let token = self.read_token();
+			let tokens = match token.metadata.into_iter().next() {
+				Some(Metadata::Annotation(tokens)) => tokens,
+				_ => Vec::new(),
+			};
+			let (name, arguments) = self.parse_nested(tokens, |p| p.parse_annotation())?;
+			annotations.push(Annotation {
+				name,
+				arguments,
+				line: token.line,
+				column: token.column,
+			});
+		}
+		Ok(annotations)
+	}
+
+	/// `name` or `name: argument, ...` between the brackets of an annotation
+	fn parse_annotation(&mut self) -> Result<(String, Vec<AnnotationArgument>), ApolloError> {
+		let name = self.expect_identifier("an annotation name")?.value;
+		let mut arguments = Vec::new();
+		if self.eat(TokenType::COLON) {
+			loop {
+				let token = self.peek().clone();
+				let argument = match token.token_type {
+					TokenType::STRING if token.metadata.is_empty() => AnnotationArgument::String(token.value),
+					TokenType::TRUE => AnnotationArgument::Boolean(true),
+					TokenType::FALSE => AnnotationArgument::Boolean(false),
+					TokenType::IDENTIFIER => AnnotationArgument::Name(token.value),
+					// `Unit` and the other keywords can name types too
+					t if t.is_keyword() => AnnotationArgument::Name(token.value),
+					TokenType::NUMBER
+					| TokenType::HEXADECIMAL
+					| TokenType::OCTAL
+					| TokenType::BINARY
+					| TokenType::FLOAT => match token.metadata.first() {
+						Some(Metadata::Number { value, .. }) => AnnotationArgument::Number(*value),
+						_ => return Err(self.error("Expected an annotation argument")),
+					},
+					_ => return Err(self.error("Expected an annotation argument")),
+				};
+				self.read_token();
+				arguments.push(argument);
+				if !self.eat(TokenType::COMMA) {
+					break;
+				}
+			}
+		}
+		if !self.check(TokenType::EOF) {
+			return Err(self.error("Expected ']'"));
+		}
+		Ok((name, arguments))
+	}
+
+	fn parse_item(&mut self) -> Result<Item, ApolloError> {
+		let annotations = self.parse_annotations()?;
+		let docs = std::mem::take(&mut self.docs);
+		let start = self.peek().clone();
+		let public = self.eat(TokenType::PUBLIC);
+
+		let kind = if self.check(TokenType::USE) {
+			self.parse_use()?
+		} else if self.check(TokenType::FN) || self.check(TokenType::UNARY) {
+			ItemKind::Function(self.parse_function()?)
+		} else if self.check(TokenType::CLASS) {
+			ItemKind::Class(self.parse_class()?)
+		} else if self.check(TokenType::STRUCT) {
+			ItemKind::Struct(self.parse_struct()?)
+		} else if self.check(TokenType::ENUM) {
+			ItemKind::Enum(self.parse_enum()?)
+		} else if self.check(TokenType::EXTEND) {
+			ItemKind::Extend(self.parse_extend()?)
+		} else if self.check(TokenType::VAR) || self.check(TokenType::CONST) {
+			let variable = self.parse_variable()?;
+			self.expect_terminator()?;
+			ItemKind::Variable(variable)
+		} else {
+			return Err(self.error("Expected a declaration"));
+		};
+
+		if self.debug_mode > 1 {
+			print_debug(
+				"Parsed declaration at line: ",
+				&start.line.to_string(),
+				self.logging,
+				&self.output_dir,
+			);
+		}
+		Ok(Item {
+			kind,
+			annotations,
+			docs,
+			public,
+			line: start.line,
+			column: start.column,
+		})
+	}
+
+	fn parse_use(&mut self) -> Result<ItemKind, ApolloError> {
+		self.expect(TokenType::USE, "'use'")?;
+		let mut path = vec![self.expect_identifier("a module name")?.value];
+		while self.eat(TokenType::DOT) {
+			path.push(self.expect_identifier("a module name")?.value);
+		}
+		self.expect_terminator()?;
+		Ok(ItemKind::Use(path))
+	}
+
+	fn parse_function(&mut self) -> Result<Function, ApolloError> {
+		let (name, operator) = if self.eat(TokenType::UNARY) {
+			self.expect(TokenType::OPERATOR, "'operator'")?;
+			let token = self.read_token();
+			if token.token_type.is_keyword()
+				|| matches!(
+					token.token_type,
+					TokenType::IDENTIFIER | TokenType::LEFTPAREN | TokenType::EOF
+				) {
+				return Err(ApolloError::spanning(
+					format!("Expected an operator, found '{}'", token.value),
+					token.span,
+				));
+			}
+			(token.value, Some(Fixity::Unary))
+		} else {
+			self.expect(TokenType::FN, "'fn'")?;
+			// `new` and `panic` are allowed as function names so classes can declare custom
+			// constructors and error messages
+			let name = if self.check(TokenType::NEW) || self.check(TokenType::PANIC) {
+				self.read_token().value
+			} else {
+				self.expect_identifier("a function name")?.value
+			};
+			(name, None)
+		};
+
+		self.expect(TokenType::LEFTPAREN, "'('")?;
+		let params = self.parse_params(TokenType::RIGHTPAREN)?;
+		self.expect(TokenType::RIGHTPAREN, "')'")?;
+
+		let return_type = if self.eat(TokenType::RIGHTARROW) {
+			Some(self.parse_type()?)
+		} else {
+			None
+		};
+
+		let body = if self.check(TokenType::LEFTBRACE) {
+			Some(self.parse_block()?)
+		} else {
+			self.expect_terminator()?;
+			None
+		};
+
+		Ok(Function {
+			name,
+			operator,
+			params,
+			return_type,
+			body,
+		})
+	}
+
+	/// comma separated parameters up to (but not including) `close`
+	fn parse_params(&mut self, close: TokenType) -> Result<Vec<Param>, ApolloError> {
+		let mut params = Vec::new();
+		while !self.check(close) {
+			let start = self.peek().clone();
+			let mut constant = self.eat(TokenType::CONST);
+			let mut variadic = false;
+			if self.check(TokenType::DOT) {
+				for _ in 0..3 {
+					self.expect(TokenType::DOT, "'...'")?;
+				}
+				variadic = true;
+			}
+			let name = self.expect_identifier("a parameter name")?.value;
+			self.expect(TokenType::COLON, "':'")?;
+			constant |= self.eat(TokenType::CONST);
+			let ty = self.parse_type()?;
+			params.push(Param {
+				name,
+				ty,
+				constant,
+				variadic,
+				line: start.line,
+				column: start.column,
+			});
+			if !self.eat(TokenType::COMMA) {
+				break;
+			}
+		}
+		Ok(params)
+	}
+
+	fn parse_class(&mut self) -> Result<Class, ApolloError> {
+		self.expect(TokenType::CLASS, "'class'")?;
+		let name = self.expect_identifier("a class name")?.value;
+		let params = if self.eat(TokenType::LEFTPAREN) {
+			let params = self.parse_params(TokenType::RIGHTPAREN)?;
+			self.expect(TokenType::RIGHTPAREN, "')'")?;
+			params
+		} else {
+			Vec::new()
+		};
+		let parent = if self.eat(TokenType::FROM) {
+			Some(self.parse_type()?)
+		} else {
+			None
+		};
+		let members = self.parse_members("class")?;
+		Ok(Class {
+			name,
+			params,
+			parent,
+			members,
+		})
+	}
+
+	fn parse_extend(&mut self) -> Result<Extend, ApolloError> {
+		self.expect(TokenType::EXTEND, "'extend'")?;
+		let target = self.parse_type()?;
+		let members = self.parse_members("extend")?;
+		Ok(Extend { target, members })
+	}
+
+	/// `{ ... }` body of a class or extend block, only fields and functions are allowed
+	fn parse_members(&mut self, owner: &str) -> Result<Vec<Item>, ApolloError> {
+		self.expect(TokenType::LEFTBRACE, "'{'")?;
+		let mut members = Vec::new();
+		while !self.check(TokenType::RIGHTBRACE) && !self.check(TokenType::EOF) {
+			let start = self.position;
+			let member = match self.parse_item() {
+				Ok(member) => member,
+				Err(error) => {
+					members.push(self.recover_item(error, start, true));
+					continue;
+				}
+			};
+			let allowed = match member.kind {
+				ItemKind::Function(_) => true,
+				ItemKind::Variable(_) => owner == "class",
+				_ => false,
+			};
+			if !allowed {
+				let article = if owner == "extend" { "an" } else { "a" };
+				// parsed fine, so there is nothing to skip
+				self.errors.push(ApolloError::spanning(
+					format!("This declaration is not allowed inside {article} {owner} block"),
+					self.span_from(start),
+				));
+				continue;
+			}
+			members.push(member);
+		}
+		self.expect(TokenType::RIGHTBRACE, "'}'")?;
+		Ok(members)
+	}
+
+	fn parse_struct(&mut self) -> Result<Struct, ApolloError> {
+		self.expect(TokenType::STRUCT, "'struct'")?;
+		let name = self.expect_identifier("a struct name")?.value;
+		self.expect(TokenType::LEFTBRACE, "'{'")?;
+		let mut fields = Vec::new();
+		while !self.check(TokenType::RIGHTBRACE) {
+			let token = self.expect_identifier("a field name")?;
+			self.expect(TokenType::COLON, "':'")?;
+			let ty = self.parse_type()?;
+			fields.push(Field {
+				name: token.value,
+				ty,
+				line: token.line,
+				column: token.column,
+			});
+			// fields may be separated by either `,` or `;`
+			if !self.eat(TokenType::COMMA) && !self.eat(TokenType::SEMICOLON) {
+				break;
+			}
+		}
+		self.expect(TokenType::RIGHTBRACE, "'}'")?;
+		Ok(Struct { name, fields })
+	}
+
+	fn parse_enum(&mut self) -> Result<Enum, ApolloError> {
+		self.expect(TokenType::ENUM, "'enum'")?;
+		let name = self.expect_identifier("an enum name")?.value;
+		self.expect(TokenType::LEFTBRACE, "'{'")?;
+		let mut variants = Vec::new();
+		while !self.check(TokenType::RIGHTBRACE) {
+			let token = self.expect_identifier("an enum variant")?;
+			let value = if self.eat(TokenType::ASSIGN) {
+				Some(self.parse_expression()?)
+			} else {
+				None
+			};
+			variants.push(Variant {
+				name: token.value,
+				value,
+				line: token.line,
+				column: token.column,
+			});
+			if !self.eat(TokenType::COMMA) {
+				break;
 			}
-			self.current_token = None; // end of file
+		}
+		self.expect(TokenType::RIGHTBRACE, "'}'")?;
+		Ok(Enum { name, variants })
+	}
+
+	/// `var x: T = value`, `const x: T = value` or the shorthand `x: T = value`, without the terminator
+	fn parse_variable(&mut self) -> Result<Variable, ApolloError> {
+		let constant = if self.eat(TokenType::CONST) {
+			true
+		} else {
+			self.eat(TokenType::VAR);
+			false
+		};
+		let name = self.expect_identifier("a variable name")?.value;
+		let ty = if self.eat(TokenType::COLON) {
+			Some(self.parse_type()?)
+		} else {
+			None
+		};
+		let value = if self.eat(TokenType::ASSIGN) {
+			Some(self.parse_expression()?)
+		} else {
+			None
+		};
+		Ok(Variable {
+			constant,
+			name,
+			ty,
+			value,
+		})
+	}
+
+	fn parse_type(&mut self) -> Result<Type, ApolloError> {
+		if self.eat(TokenType::ATSIGN) {
+			return Ok(Type::Reference(Box::new(self.parse_type()?)));
+		}
+		if self.eat(TokenType::LEFTBRACKET) {
+			let element = Box::new(self.parse_type()?);
+			let ty = if self.eat(TokenType::SEMICOLON) {
+				let length = Box::new(self.parse_expression()?);
+				Type::Array { element, length }
+			} else {
+				Type::Slice(element)
+			};
+			self.expect(TokenType::RIGHTBRACKET, "']'")?;
+			return Ok(ty);
+		}
+
+		// `Unit` is a keyword but names a type like any other
+		let name = if self.check(TokenType::UNIT) {
+			self.read_token().value
 		} else {
-			self.current_token = Some(self.content[self.read_position].clone());
-			if self.debug_mode > 1 {
-				print_debug(
-					"Reading token: ",
-					&self.current_token.clone().unwrap().to_string(),
-					self.logging,
-					&self.output_dir,
-				);
+			self.expect_identifier("a type")?.value
+		};
+		let mut generics = Vec::new();
+		if self.eat(TokenType::LESS) {
+			loop {
+				generics.push(self.parse_type()?);
+				if !self.eat(TokenType::COMMA) {
+					break;
+				}
 			}
+			self.expect_closing_angle()?;
 		}
+		let ty = Type::Named { name, generics };
+
+		if self.eat(TokenType::LEFTBRACKET) {
+			let length = Box::new(self.with_struct_literals(true, |p| p.parse_expression())?);
+			self.expect(TokenType::RIGHTBRACKET, "']'")?;
+			return Ok(Type::Sized {
+				base: Box::new(ty),
+				length,
+			});
+		}
+		Ok(ty)
+	}
+
+	// ---------------------------------------------------------------------------
+	// statements
+	// ---------------------------------------------------------------------------
+
+	fn parse_block(&mut self) -> Result<Block, ApolloError> {
+		let open = self.expect(TokenType::LEFTBRACE, "'{'")?;
+		self.parse_block_rest(open)
+	}
+
+	/// statements up to and including the closing `}` of a block opened by `open`
+	fn parse_block_rest(&mut self, open: LexerToken) -> Result<Block, ApolloError> {
+		let mut statements = Vec::new();
+		// a block inside a match arm has statements of its own, `,` doesn't end those
+		let arm_body = std::mem::replace(&mut self.arm_body, false);
+		loop {
+			while self.eat(TokenType::SEMICOLON) {} // stray semicolons are harmless
+			if self.check(TokenType::RIGHTBRACE) || self.check(TokenType::EOF) {
+				break;
+			}
+			let start = self.position;
+			match self.with_struct_literals(true, |p| p.parse_statement()) {
+				Ok(statement) => statements.push(statement),
+				Err(error) => {
+					self.report(error);
+					let token = self.content[start].clone();
+					self.synchronize(start, true);
+					statements.push(Statement {
+						kind: StatementKind::Error,
+						line: token.line,
+						column: token.column,
+					});
+				}
+			}
+		}
+		self.arm_body = arm_body;
+		self.expect(TokenType::RIGHTBRACE, "'}'")?;
+		Ok(Block {
+			statements,
+			line: open.line,
+			column: open.column,
+		})
+	}
+
+	fn parse_statement(&mut self) -> Result<Statement, ApolloError> {
+		let start = self.peek().clone();
+		let kind = if self.check(TokenType::VAR) || self.check(TokenType::CONST) {
+			let variable = self.parse_variable()?;
+			self.expect_terminator()?;
+			StatementKind::Variable(variable)
+		} else if start.token_type == TokenType::IDENTIFIER
+			&& self.peek_nth(1).token_type == TokenType::COLON
+		{
+			// `temp: char = ...` declares a variable without `var`
+			let variable = self.parse_variable()?;
+			self.expect_terminator()?;
+			StatementKind::Variable(variable)
+		} else if self.eat(TokenType::RETURN) {
+			let value = if self.check(TokenType::SEMICOLON)
+				|| self.check(TokenType::RIGHTBRACE)
+				|| self.newline_before()
+			{
+				None
+			} else {
+				Some(self.parse_expression()?)
+			};
+			self.expect_terminator()?;
+			StatementKind::Return(value)
+		} else if self.check(TokenType::IF) {
+			self.parse_if()?
+		} else if self.eat(TokenType::WHILE) {
+			let condition = self.with_struct_literals(false, |p| p.parse_expression())?;
+			let body = self.parse_block()?;
+			StatementKind::While { condition, body }
+		} else if self.eat(TokenType::FOR) {
+			self.expect(TokenType::LEFTPAREN, "'('")?;
+			let arguments = self.parse_arguments(TokenType::RIGHTPAREN)?;
+			self.expect(TokenType::RIGHTPAREN, "')'")?;
+			let body = self.parse_block()?;
+			StatementKind::For { arguments, body }
+		} else if self.eat(TokenType::PANIC) {
+			let value = self.parse_expression()?;
+			self.expect_terminator()?;
+			StatementKind::Panic(value)
+		} else if self.eat(TokenType::RETRY) {
+			self.expect_terminator()?;
+			StatementKind::Retry
+		} else if self.check(TokenType::LEFTBRACE) && !self.at_closure() {
+			let body = self.parse_block()?;
+			if self.eat(TokenType::CATCH) {
+				self.expect(TokenType::LEFTPAREN, "'('")?;
+				let error_type = self.parse_type()?;
+				let binding = self.expect_identifier("a name for the caught error")?.value;
+				self.expect(TokenType::RIGHTPAREN, "')'")?;
+				let handler = self.parse_block()?;
+				StatementKind::Catch {
+					body,
+					error_type,
+					binding,
+					handler,
+				}
+			} else {
+				StatementKind::Block(body)
+			}
+		} else {
+			let expression = self.parse_expression()?;
+			self.expect_terminator()?;
+			StatementKind::Expression(expression)
+		};
+
+		Ok(Statement {
+			kind,
+			line: start.line,
+			column: start.column,
+		})
+	}
+
+	fn parse_if(&mut self) -> Result<StatementKind, ApolloError> {
+		self.expect(TokenType::IF, "'if'")?;
+		let condition = self.with_struct_literals(false, |p| p.parse_expression())?;
+		let then_block = self.parse_block()?;
+		let else_branch = if self.eat(TokenType::ELSE) {
+			let start = self.peek().clone();
+			let kind = if self.check(TokenType::IF) {
+				self.parse_if()?
+			} else {
+				StatementKind::Block(self.parse_block()?)
+			};
+			Some(Box::new(Statement {
+				kind,
+				line: start.line,
+				column: start.column,
+			}))
+		} else {
+			None
+		};
+		Ok(StatementKind::If {
+			condition,
+			then_block,
+			else_branch,
+		})
+	}
+
+	// ---------------------------------------------------------------------------
+	// expressions
+	// ---------------------------------------------------------------------------
+
+	pub fn parse_expression(&mut self) -> Result<Expression, ApolloError> {
+		self.parse_precedence(Precedence::Lowest)
+	}
+
+	/// comma separated expressions up to (but not including) `close`
+	fn parse_arguments(&mut self, close: TokenType) -> Result<Vec<Expression>, ApolloError> {
+		self.with_struct_literals(true, |p| {
+			let mut arguments = Vec::new();
+			while !p.check(close) {
+				arguments.push(p.parse_expression()?);
+				if !p.eat(TokenType::COMMA) {
+					break;
+				}
+			}
+			Ok(arguments)
+		})
+	}
+
+	/// Pratt loop, keeps folding operators into `left` while they bind tighter than `min`
+	fn parse_precedence(&mut self, min: Precedence) -> Result<Expression, ApolloError> {
+		let start = self.position;
+		let mut left = self.parse_prefix()?;
+		loop {
+			if self.at_postfix_operator(&left) {
+				left = self.parse_postfix_operator(left)?;
+				continue;
+			}
+			let Some((precedence, associativity)) = self.infix_operator() else {
+				break;
+			};
+			if precedence < min || (precedence == min && associativity == Associativity::Left) {
+				break;
+			}
+			left = self.parse_infix_operator(left, precedence, start)?;
+		}
+		Ok(left)
+	}
+
+	fn parse_prefix(&mut self) -> Result<Expression, ApolloError> {
+		let token = self.peek().clone();
+		if lookup_operator(token.token_type, Position::Prefix).is_none() {
+			return self.parse_primary();
+		}
+		self.read_token();
+		let operand = Box::new(self.parse_precedence(Precedence::Prefix)?);
+		let kind = if token.token_type == TokenType::ATSIGN {
+			ExpressionKind::Reference(operand)
+		} else {
+			ExpressionKind::Unary {
+				operator: token.token_type,
+				operand,
+			}
+		};
+		Ok(Expression {
+			kind,
+			line: token.line,
+			column: token.column,
+		})
+	}
+
+	fn infix_operator(&self) -> Option<(Precedence, Associativity)> {
+		lookup_operator(self.peek().token_type, Position::Infix)
+	}
+
+	fn parse_infix_operator(
+		&mut self,
+		left: Expression,
+		precedence: Precedence,
+		start: usize,
+	) -> Result<Expression, ApolloError> {
+		let token = self.read_token();
+		let (line, column) = (left.line, left.column);
+		let kind = match precedence {
+			Precedence::Cast => ExpressionKind::Cast {
+				value: Box::new(left),
+				ty: self.parse_type()?,
+			},
+			Precedence::Assignment => {
+				if !matches!(
+					left.kind,
+					ExpressionKind::Identifier(_)
+						| ExpressionKind::SelfValue
+						| ExpressionKind::Member { .. }
+						| ExpressionKind::Index { .. }
+				) {
+					return Err(ApolloError::spanning(
+						"Invalid assignment target".to_string(),
+						self.span_from(start),
+					));
+				}
+				ExpressionKind::Assign {
+					target: Box::new(left),
+					operator: token.token_type,
+					value: Box::new(self.parse_precedence(precedence)?),
+				}
+			}
+			Precedence::Lambda => {
+				let ExpressionKind::Identifier(param) = left.kind else {
+					return Err(ApolloError::spanning(
+						"Expected a parameter name before '=>'".to_string(),
+						self.span_from(start),
+					));
+				};
+				ExpressionKind::Lambda {
+					param,
+					body: Box::new(self.parse_precedence(precedence)?),
+				}
+			}
+			_ => ExpressionKind::Binary {
+				left: Box::new(left),
+				operator: token.token_type,
+				right: Box::new(self.parse_precedence(precedence)?),
+			},
+		};
+		Ok(Expression { kind, line, column })
+	}
+
+	fn at_postfix_operator(&mut self, left: &Expression) -> bool {
+		let token = self.peek();
+		// only member access may continue on the next line, everything else would swallow the
+		// start of the following statement
+		if token.token_type == TokenType::DOT {
+			return true;
+		}
+		if self.newline_before() {
+			return false;
+		}
+		match token.token_type {
+			// trailing lambda, `cmds.foreach { |const cmd: char| ... }`
+			TokenType::LEFTBRACE => self.struct_literals && self.at_closure(),
+			// `+` and `-` are custom postfix operators only when no operand follows them
+			TokenType::PLUS | TokenType::MINUS => !Self::can_start_expression(self.peek_nth(1)),
+			TokenType::NEW => {
+				matches!(left.kind, ExpressionKind::Identifier(_))
+			}
+			TokenType::IDENTIFIER => matches!(
+				left.kind,
+				ExpressionKind::Identifier(_) | ExpressionKind::SelfValue | ExpressionKind::Member { .. }
+			),
+			t => lookup_operator(t, Position::Postfix).is_some(),
+		}
+	}
+
+	fn parse_postfix_operator(&mut self, left: Expression) -> Result<Expression, ApolloError> {
+		let (line, column) = (left.line, left.column);
+		if self.check(TokenType::LEFTBRACE) {
+			let closure = self.parse_primary()?;
+			let kind = match left.kind {
+				// `xs.map(a) { |x| ... }` passes the closure as the last argument
+				ExpressionKind::Call {
+					callee,
+					mut arguments,
+				} => {
+					arguments.push(closure);
+					ExpressionKind::Call { callee, arguments }
+				}
+				_ => ExpressionKind::Call {
+					callee: Box::new(left),
+					arguments: vec![closure],
+				},
+			};
+			return Ok(Expression { kind, line, column });
+		}
+		let token = self.read_token();
+		let kind = match token.token_type {
+			TokenType::LEFTPAREN => {
+				let arguments = self.parse_arguments(TokenType::RIGHTPAREN)?;
+				self.expect(TokenType::RIGHTPAREN, "')'")?;
+				ExpressionKind::Call {
+					callee: Box::new(left),
+					arguments,
+				}
+			}
+			TokenType::LEFTBRACKET => {
+				let index = self.with_struct_literals(true, |p| p.parse_expression())?;
+				self.expect(TokenType::RIGHTBRACKET, "']'")?;
+				ExpressionKind::Index {
+					object: Box::new(left),
+					index: Box::new(index),
+				}
+			}
+			TokenType::DOT => {
+				let name = self.expect_identifier("a member name")?.value;
+				ExpressionKind::Member {
+					object: Box::new(left),
+					name,
+				}
+			}
+			TokenType::NEW => {
+				// `Dealer new`
+				let ExpressionKind::Identifier(name) = left.kind else {
+					unreachable!()
+				};
+				ExpressionKind::New {
+					ty: Some(Type::Named {
+						name,
+						generics: Vec::new(),
+					}),
+					arguments: Vec::new(),
+				}
+			}
+			TokenType::IDENTIFIER => {
+				// keyword-like method call without parentheses, `g start;`
+				let callee = Expression {
+					kind: ExpressionKind::Member {
+						object: Box::new(left),
+						name: token.value,
+					},
+					line,
+					column,
+				};
+				ExpressionKind::Call {
+					callee: Box::new(callee),
+					arguments: Vec::new(),
+				}
+			}
+			operator => ExpressionKind::Postfix {
+				operator,
+				operand: Box::new(left),
+			},
+		};
+		Ok(Expression { kind, line, column })
+	}
+
+	fn can_start_expression(token: &LexerToken) -> bool {
+		match token.token_type {
+			TokenType::NUMBER
+			| TokenType::HEXADECIMAL
+			| TokenType::OCTAL
+			| TokenType::BINARY
+			| TokenType::FLOAT
+			| TokenType::STRING
+			| TokenType::CHARACTER
+			| TokenType::LEFTPAREN
+			| TokenType::LEFTBRACE
+			| TokenType::IDENTIFIER
+			| TokenType::TRUE
+			| TokenType::FALSE
+			| TokenType::SELF
+			| TokenType::NEW => true,
+			t => lookup_operator(t, Position::Prefix).is_some(),
+		}
+	}
+
+	/// `Name<...> new`, checked by scanning ahead so nothing has to be undone when it's a comparison
+	fn at_generic_new(&mut self) -> bool {
+		if !self.check(TokenType::IDENTIFIER) || self.peek_nth(1).token_type != TokenType::LESS {
+			return false;
+		}
+		let mut depth: i32 = 0;
+		let mut n = 1;
+		loop {
+			let token = self.peek_nth(n);
+			match token.token_type {
+				TokenType::LESS => depth += 1,
+				TokenType::GREATER => depth -= 1,
+				TokenType::RIGHTSHIFT => depth -= 2,
+				TokenType::IDENTIFIER
+				| TokenType::UNIT
+				| TokenType::COMMA
+				| TokenType::ATSIGN
+				| TokenType::LEFTBRACKET
+				| TokenType::RIGHTBRACKET
+				| TokenType::SEMICOLON
+				| TokenType::NUMBER => {}
+				_ => return false,
+			}
+			n += 1;
+			if depth <= 0 {
+				return depth == 0 && self.peek_nth(n).token_type == TokenType::NEW;
+			}
+		}
+	}
+
+	fn parse_primary(&mut self) -> Result<Expression, ApolloError> {
+		let token = self.peek().clone();
+		let kind = match token.token_type {
+			TokenType::NUMBER
+			| TokenType::HEXADECIMAL
+			| TokenType::OCTAL
+			| TokenType::BINARY
+			| TokenType::FLOAT => {
+				self.read_token();
+				let (number, suffix) = match token.metadata.into_iter().next() {
+					Some(Metadata::Number { value, suffix }) => (value, suffix),
+					_ => (NumberValue::Integer(0), None),
+				};
+				ExpressionKind::Literal(Literal::Number {
+					value: token.value,
+					kind: token.token_type,
+					number,
+					suffix,
+				})
+			}
+			TokenType::STRING if !token.metadata.is_empty() => {
+				self.read_token();
+				self.parse_format(token.metadata)?
+			}
+			TokenType::STRING => {
+				self.read_token();
+				ExpressionKind::Literal(Literal::String(token.value))
+			}
+			TokenType::CHARACTER => {
+				self.read_token();
+				ExpressionKind::Literal(Literal::Character(token.value))
+			}
+			TokenType::LEFTPAREN => {
+				self.read_token();
+				let inner = self.with_struct_literals(true, |p| p.parse_expression())?;
+				self.expect(TokenType::RIGHTPAREN, "')'")?;
+				return Ok(inner);
+			}
+			TokenType::LEFTBRACE if self.at_closure() => self.parse_closure()?,
+			TokenType::LEFTBRACE => {
+				self.read_token();
+				let elements = self.parse_arguments(TokenType::RIGHTBRACE)?;
+				self.expect(TokenType::RIGHTBRACE, "'}'")?;
+				ExpressionKind::ArrayLiteral(elements)
+			}
+			TokenType::TRUE | TokenType::FALSE => {
+				self.read_token();
+				ExpressionKind::Literal(Literal::Boolean(token.token_type == TokenType::TRUE))
+			}
+			TokenType::SELF => {
+				self.read_token();
+				ExpressionKind::SelfValue
+			}
+			TokenType::NEW => {
+				self.read_token();
+				self.parse_new()?
+			}
+			TokenType::MATCH => self.parse_match()?,
+			TokenType::IDENTIFIER if self.at_generic_new() => {
+				// `Vector<Card> new`
+				let ty = self.parse_type()?;
+				self.expect(TokenType::NEW, "'new'")?;
+				ExpressionKind::New {
+					ty: Some(ty),
+					arguments: Vec::new(),
+				}
+			}
+			TokenType::IDENTIFIER => {
+				self.read_token();
+				if self.struct_literals && self.at_struct_literal() {
+					self.parse_struct_literal(token.value)?
+				} else {
+					ExpressionKind::Identifier(token.value)
+				}
+			}
+			_ => return Err(self.error("Expected an expression")),
+		};
+		Ok(Expression {
+			kind,
+			line: token.line,
+			column: token.column,
+		})
+	}
+
+	/// the parts of an interpolated string, each embedded expression is parsed from its own tokens
+	fn parse_format(&mut self, metadata: Vec<Metadata>) -> Result<ExpressionKind, ApolloError> {
+		let mut parts = Vec::new();
+		for part in metadata {
+			match part {
+				Metadata::Text(text) => parts.push(FormatPart::Text(text)),
+				Metadata::Expression(tokens) => {
+					parts.push(FormatPart::Expression(self.parse_embedded(tokens)?))
+				}
+				Metadata::Annotation(_) | Metadata::Number { .. } => {}
+			}
+		}
+		Ok(ExpressionKind::Format(parts))
+	}
+
+	/// parses `tokens` as a single expression, then picks the outer token stream back up
+	fn parse_embedded(&mut self, tokens: Vec<LexerToken>) -> Result<Expression, ApolloError> {
+		if tokens.is_empty() {
+			return Err(self.error("Expected an expression"));
+		}
+		self.parse_nested(tokens, |p| {
+			let expression = p.with_struct_literals(true, |p| p.parse_expression())?;
+			if p.check(TokenType::EOF) {
+				Ok(expression)
+			} else {
+				Err(p.error("Expected '}'"))
+			}
+		})
+	}
+
+	/// runs `f` over a token list carried in metadata, then picks the outer token stream back up
+	fn parse_nested<T>(
+		&mut self,
+		mut tokens: Vec<LexerToken>,
+		f: impl FnOnce(&mut Self) -> Result<T, ApolloError>,
+	) -> Result<T, ApolloError> {
+		// the closing `}` or `]` ends the list like the end of a file would
+		match tokens.last_mut() {
+			Some(close) => close.token_type = TokenType::EOF,
+			None => tokens.push(LexerToken {
+				token_type: TokenType::EOF,
+				line: self.peek().line,
+				column: self.peek().column,
+				span: self.peek().span,
+				..LexerToken::default()
+			}),
+		}
+		let content = std::mem::replace(&mut self.content, tokens);
+		self.outer.push((content, self.position, self.previous));
+		let docs = std::mem::take(&mut self.docs);
+		self.position = 0;
+		self.previous = 0;
+		self.skip_trivia();
+
+		let result = f(self);
+
+		let (content, position, previous) = self.outer.pop().unwrap();
+		self.content = content;
+		self.position = position;
+		self.previous = previous;
+		self.docs = docs;
+		result
+	}
+
+	fn parse_match(&mut self) -> Result<ExpressionKind, ApolloError> {
+		self.expect(TokenType::MATCH, "'match'")?;
+		let subject = self.with_struct_literals(false, |p| p.parse_expression())?;
+		self.expect(TokenType::LEFTBRACE, "'{'")?;
+		let mut arms = Vec::new();
+		while !self.check(TokenType::RIGHTBRACE) && !self.check(TokenType::EOF) {
+			let start = self.position;
+			let token = self.peek().clone();
+			// a broken arm is left out, the ones after it are still parsed
+			let arm = self.parse_pattern().and_then(|pattern| {
+				self.expect(TokenType::RIGHTARROW, "'->'")?;
+				let arm_body = std::mem::replace(&mut self.arm_body, true);
+				let body = self.with_struct_literals(true, |p| p.parse_statement());
+				self.arm_body = arm_body;
+				let body = body?;
+				Ok(MatchArm {
+					pattern,
+					body,
+					line: token.line,
+					column: token.column,
+				})
+			});
+			match arm {
+				Ok(arm) => {
+					arms.push(arm);
+					self.eat(TokenType::COMMA);
+				}
+				Err(error) => {
+					self.report(error);
+					self.synchronize_arm(start);
+				}
+			}
+		}
+		self.expect(TokenType::RIGHTBRACE, "'}'")?;
+		Ok(ExpressionKind::Match {
+			subject: Box::new(subject),
+			arms,
+		})
+	}
+
+	fn parse_pattern(&mut self) -> Result<Pattern, ApolloError> {
+		let token = self.peek().clone();
+		match token.token_type {
+			TokenType::IDENTIFIER if token.value == "_" => {
+				self.read_token();
+				Ok(Pattern::Wildcard)
+			}
+			TokenType::TRUE | TokenType::FALSE => {
+				self.read_token();
+				Ok(Pattern::Literal(Literal::Boolean(
+					token.token_type == TokenType::TRUE,
+				)))
+			}
+			TokenType::IDENTIFIER => {
+				let mut path = vec![self.expect_identifier("a pattern")?.value];
+				while self.eat(TokenType::DOT) {
+					path.push(self.expect_identifier("an enum variant")?.value);
+				}
+				Ok(Pattern::Variant(path))
+			}
+			TokenType::NUMBER
+			| TokenType::HEXADECIMAL
+			| TokenType::OCTAL
+			| TokenType::BINARY
+			| TokenType::FLOAT
+			| TokenType::STRING
+			| TokenType::CHARACTER => match self.parse_primary()?.kind {
+				ExpressionKind::Literal(literal) => Ok(Pattern::Literal(literal)),
+				_ => Err(ApolloError::at(
+					"Interpolated strings can't be used as patterns".to_string(),
+					token.line,
+					token.column,
+				)),
+			},
+			_ => Err(self.error("Expected a pattern")),
+		}
+	}
+
+	/// `{` opening a closure rather than a block or array literal, the parameter list
+	/// always starts with a `|` (or `||` when the closure takes no parameters)
+	fn at_closure(&mut self) -> bool {
+		self.check(TokenType::LEFTBRACE)
+			&& matches!(
+				self.peek_nth(1).token_type,
+				TokenType::BAR | TokenType::BOOLOR
+			)
+	}
+
+	/// `{ |const c: char, i: u32| statements }`
+	fn parse_closure(&mut self) -> Result<ExpressionKind, ApolloError> {
+		let open = self.expect(TokenType::LEFTBRACE, "'{'")?;
+		let params = if self.eat(TokenType::BOOLOR) {
+			Vec::new()
+		} else {
+			self.expect(TokenType::BAR, "'|'")?;
+			let params = self.parse_params(TokenType::BAR)?;
+			self.expect(TokenType::BAR, "'|'")?;
+			params
+		};
+		let body = self.parse_block_rest(open)?;
+		Ok(ExpressionKind::Closure { params, body })
+	}
+
+	/// `Name {` followed by `}` or `field =`
+	fn at_struct_literal(&mut self) -> bool {
+		self.check(TokenType::LEFTBRACE)
+			&& (self.peek_nth(1).token_type == TokenType::RIGHTBRACE
+				|| (self.peek_nth(1).token_type == TokenType::IDENTIFIER
+					&& self.peek_nth(2).token_type == TokenType::ASSIGN))
+	}
+
+	fn parse_struct_literal(&mut self, name: String) -> Result<ExpressionKind, ApolloError> {
+		self.expect(TokenType::LEFTBRACE, "'{'")?;
+		let mut fields = Vec::new();
+		while !self.check(TokenType::RIGHTBRACE) {
+			let field = self.expect_identifier("a field name")?.value;
+			self.expect(TokenType::ASSIGN, "'='")?;
+			let value = self.with_struct_literals(true, |p| p.parse_expression())?;
+			fields.push((field, value));
+			if !self.eat(TokenType::COMMA) {
+				break;
+			}
+		}
+		self.expect(TokenType::RIGHTBRACE, "'}'")?;
+		Ok(ExpressionKind::StructLiteral { name, fields })
+	}
+
+	/// everything after the `new` keyword
+	fn parse_new(&mut self) -> Result<ExpressionKind, ApolloError> {
+		let token = self.peek().clone();
+		let (ty, arguments) = match token.token_type {
+			TokenType::LEFTPAREN => {
+				self.read_token();
+				let arguments = self.parse_arguments(TokenType::RIGHTPAREN)?;
+				self.expect(TokenType::RIGHTPAREN, "')'")?;
+				(None, arguments)
+			}
+			TokenType::IDENTIFIER | TokenType::UNIT if !self.newline_before() => {
+				let ty = self.parse_type()?;
+				let arguments = if self.eat(TokenType::LEFTPAREN) {
+					let arguments = self.parse_arguments(TokenType::RIGHTPAREN)?;
+					self.expect(TokenType::RIGHTPAREN, "')'")?;
+					arguments
+				} else {
+					Vec::new()
+				};
+				(Some(ty), arguments)
+			}
+			TokenType::NUMBER
+			| TokenType::HEXADECIMAL
+			| TokenType::OCTAL
+			| TokenType::BINARY
+			| TokenType::FLOAT
+			| TokenType::STRING
+			| TokenType::CHARACTER => (None, vec![self.parse_primary()?]), // `new 2`
+			_ => (None, Vec::new()),
+		};
+		Ok(ExpressionKind::New { ty, arguments })
 	}
 }
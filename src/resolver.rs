@@ -0,0 +1,873 @@
+use std::collections::HashMap;
+
+use crate::builtins;
+use crate::parser;
+use crate::util;
+
+use builtins::{BUILTIN_CLASSES, BUILTIN_ERRORS, PRELUDE_FUNCTIONS, PRIMITIVE_TYPES};
+use parser::{
+	Block, Class, Expression, ExpressionKind, FormatPart, Function, Item, ItemKind, Param, Program,
+	Statement, StatementKind, Type, Variable,
+};
+use util::ApolloError;
+use util::print_debug;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SymbolKind {
+	Module,
+	Type, // primitive types
+	Class,
+	Struct,
+	Enum,
+	Function,
+	Field,
+	Constant,
+	Variable,
+	Parameter,
+}
+
+impl SymbolKind {
+	fn is_type(self) -> bool {
+		matches!(
+			self,
+			SymbolKind::Type | SymbolKind::Class | SymbolKind::Struct | SymbolKind::Enum
+		)
+	}
+}
+
+#[derive(Debug, Clone)]
+pub struct Symbol {
+	pub name: String,
+	pub kind: SymbolKind,
+	pub line: usize, // 0 for builtins
+	pub column: usize,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ScopeKind {
+	Builtin, // prelude and everything pulled in with `use`
+	Module,
+	Class,
+	Extend,
+	Function,
+	Closure,
+	Block,
+}
+
+#[derive(Debug)]
+pub struct Scope {
+	pub kind: ScopeKind,
+	pub symbols: HashMap<String, Symbol>,
+	/// locals declared further down in this block, used to report use-before-declaration
+	pending: HashMap<String, (usize, usize)>,
+}
+
+/// A stack of scopes, innermost last
+#[derive(Debug, Default)]
+pub struct SymbolTable {
+	scopes: Vec<Scope>,
+}
+
+enum Lookup<'a> {
+	Found(&'a Symbol, ScopeKind),
+	NotYetDeclared(usize), // line of the declaration
+	Missing,
+}
+
+impl SymbolTable {
+	pub fn push(&mut self, kind: ScopeKind) {
+		self.scopes.push(Scope {
+			kind,
+			symbols: HashMap::new(),
+			pending: HashMap::new(),
+		});
+	}
+
+	pub fn pop(&mut self) -> Option<Scope> {
+		self.scopes.pop()
+	}
+
+	/// defines a symbol in the innermost scope, returns the symbol it collides with
+	pub fn define(&mut self, symbol: Symbol) -> Option<Symbol> {
+		let scope = self.scopes.last_mut()?;
+		scope.pending.remove(&symbol.name);
+		if let Some(existing) = scope.symbols.get(&symbol.name) {
+			return Some(existing.clone());
+		}
+		scope.symbols.insert(symbol.name.clone(), symbol);
+		None
+	}
+
+	pub fn lookup(&self, name: &str) -> Option<&Symbol> {
+		match self.find(name) {
+			Lookup::Found(symbol, _) => Some(symbol),
+			_ => None,
+		}
+	}
+
+	fn find(&self, name: &str) -> Lookup<'_> {
+		for scope in self.scopes.iter().rev() {
+			if let Some(symbol) = scope.symbols.get(name) {
+				return Lookup::Found(symbol, scope.kind);
+			}
+			if let Some((line, _)) = scope.pending.get(name) {
+				return Lookup::NotYetDeclared(*line);
+			}
+		}
+		Lookup::Missing
+	}
+
+	fn innermost(&self, kinds: &[ScopeKind]) -> Option<&Scope> {
+		self.scopes.iter().rev().find(|s| kinds.contains(&s.kind))
+	}
+}
+
+/// Members of a user defined class, including the ones added with `extend`
+#[derive(Debug, Default)]
+struct ClassInfo {
+	parent: Option<String>,
+	members: Vec<Symbol>,
+}
+
+/// Resolves every name in a file to its declaration
+pub struct Resolver<'a> {
+	program: &'a Program,
+	debug_mode: u8,
+	logging: bool,
+	output_dir: String,
+
+	table: SymbolTable,
+	classes: HashMap<String, ClassInfo>,
+	structs: HashMap<String, Vec<String>>, // struct name -> field names
+	current_class: Option<String>,         // class or extend target `self` refers to
+	global_initializer: Option<usize>,     // line of the top-level constant being initialized
+	diagnostics: Vec<ApolloError>,
+}
+
+impl<'a> Resolver<'a> {
+	pub fn new(program: &'a Program, debug_mode: u8, logging: bool, output_dir: String) -> Self {
+		Resolver {
+			program,
+			debug_mode,
+			logging,
+			output_dir,
+			table: SymbolTable::default(),
+			classes: HashMap::new(),
+			structs: HashMap::new(),
+			current_class: None,
+			global_initializer: None,
+			diagnostics: Vec::new(),
+		}
+	}
+
+	/// returns every name resolution error in the file
+	pub fn begin(&mut self) -> Vec<ApolloError> {
+		if self.debug_mode > 0 {
+			print_debug(
+				"Resolving file: ",
+				&self.program.filepath,
+				self.logging,
+				&self.output_dir,
+			);
+		}
+		let program = self.program;
+
+		self.table.push(ScopeKind::Builtin);
+		self.define_builtins();
+		for item in &program.items {
+			if let ItemKind::Use(path) = &item.kind {
+				self.define_import(path);
+			}
+		}
+
+		self.collect_members(&program.items);
+
+		self.table.push(ScopeKind::Module);
+		for item in &program.items {
+			self.declare_item(item);
+		}
+		for item in &program.items {
+			self.resolve_item(item);
+		}
+		self.pop_scope();
+		self.pop_scope();
+
+		std::mem::take(&mut self.diagnostics)
+	}
+
+	fn define_builtins(&mut self) {
+		let builtins = PRELUDE_FUNCTIONS
+			.iter()
+			.map(|name| (*name, SymbolKind::Function))
+			.chain(PRIMITIVE_TYPES.iter().map(|name| (*name, SymbolKind::Type)))
+			.chain(
+				BUILTIN_CLASSES
+					.iter()
+					.map(|name| (*name, SymbolKind::Class)),
+			)
+			.chain(
+				BUILTIN_ERRORS
+					.iter()
+					.map(|(name, _)| (*name, SymbolKind::Class)),
+			);
+		for (name, kind) in builtins {
+			self.table.define(Symbol {
+				name: name.to_string(),
+				kind,
+				line: 0,
+				column: 0,
+			});
+		}
+	}
+
+	/// `use stdlib.random;` makes `stdlib` and everything `stdlib.random` exports visible
+	fn define_import(&mut self, path: &[String]) {
+		let Some(first) = path.first() else {
+			return;
+		};
+		let exports = builtins::module_exports(&path.join("."));
+		let module = match exports {
+			Some(_) => first,
+			None => path.last().unwrap_or(first), // packages from apollo.lib aren't known yet
+		};
+		let mut names: Vec<(&str, SymbolKind)> = vec![(module, SymbolKind::Module)];
+		for export in exports.unwrap_or_default() {
+			let kind = match export.chars().next() {
+				Some(c) if c.is_uppercase() => SymbolKind::Class,
+				_ => SymbolKind::Function,
+			};
+			names.push((export, kind));
+		}
+		for (name, kind) in names {
+			// importing a name twice, or importing a builtin, isn't an error
+			self.table.define(Symbol {
+				name: name.to_string(),
+				kind,
+				line: 0,
+				column: 0,
+			});
+		}
+	}
+
+	/// records class members and struct fields so `self.x` and `Card { face = ... }` can be checked
+	fn collect_members(&mut self, items: &[Item]) {
+		for item in items {
+			match &item.kind {
+				ItemKind::Class(class) => {
+					let info = self.classes.entry(class.name.clone()).or_default();
+					info.parent = match &class.parent {
+						Some(Type::Named { name, .. }) => Some(name.clone()),
+						_ => None,
+					};
+					for param in &class.params {
+						info.members.push(param_symbol(param));
+					}
+					info
+						.members
+						.extend(class.members.iter().filter_map(member_symbol));
+				}
+				ItemKind::Extend(extend) => {
+					if let Type::Named { name, .. } = &extend.target {
+						let info = self.classes.entry(name.clone()).or_default();
+						info
+							.members
+							.extend(extend.members.iter().filter_map(member_symbol));
+					}
+				}
+				ItemKind::Struct(s) => {
+					self.structs.insert(
+						s.name.clone(),
+						s.fields.iter().map(|f| f.name.clone()).collect(),
+					);
+				}
+				_ => {}
+			}
+		}
+	}
+
+	/// hoists a top-level declaration into the module scope
+	fn declare_item(&mut self, item: &Item) {
+		let (name, kind) = match &item.kind {
+			ItemKind::Function(function) => (function.key(), SymbolKind::Function),
+			ItemKind::Class(class) => (class.name.clone(), SymbolKind::Class),
+			ItemKind::Struct(s) => (s.name.clone(), SymbolKind::Struct),
+			ItemKind::Enum(e) => (e.name.clone(), SymbolKind::Enum),
+			ItemKind::Variable(variable) => (variable.name.clone(), variable_kind(variable)),
+			ItemKind::Use(_) | ItemKind::Extend(_) | ItemKind::Error => return,
+		};
+		self.define(name, kind, item.line, item.column);
+	}
+
+	fn resolve_item(&mut self, item: &Item) {
+		match &item.kind {
+			ItemKind::Function(function) => self.resolve_function(function),
+			ItemKind::Class(class) => self.resolve_class(class),
+			ItemKind::Struct(s) => {
+				let mut seen: HashMap<&str, usize> = HashMap::new();
+				for field in &s.fields {
+					self.resolve_type(&field.ty, field.line, field.column);
+					if let Some(line) = seen.insert(&field.name, field.line) {
+						self.diagnostics.push(ApolloError::at(
+							format!("{} is already defined on line {line}", field.name),
+							field.line,
+							field.column,
+						));
+					}
+				}
+			}
+			ItemKind::Enum(e) => {
+				let mut seen: HashMap<&str, usize> = HashMap::new();
+				for variant in &e.variants {
+					if let Some(value) = &variant.value {
+						self.resolve_expression(value);
+					}
+					if let Some(line) = seen.insert(&variant.name, variant.line) {
+						self.diagnostics.push(ApolloError::at(
+							format!("{} is already defined on line {line}", variant.name),
+							variant.line,
+							variant.column,
+						));
+					}
+				}
+			}
+			ItemKind::Extend(extend) => {
+				let target = match &extend.target {
+					Type::Named { name, .. } => Some(name.clone()),
+					_ => None,
+				};
+				self.resolve_type(&extend.target, item.line, item.column);
+				let saved = std::mem::replace(&mut self.current_class, target.clone());
+				self.table.push(ScopeKind::Extend);
+				if let Some(target) = &target {
+					self.define_class_members(target);
+				}
+				for member in &extend.members {
+					self.resolve_member(member);
+				}
+				self.pop_scope();
+				self.current_class = saved;
+			}
+			ItemKind::Variable(variable) => {
+				// functions may use constants declared further down, other constants may not
+				self.global_initializer = Some(item.line);
+				self.resolve_variable(variable, item.line, item.column);
+				self.global_initializer = None;
+			}
+			ItemKind::Use(_) | ItemKind::Error => {}
+		}
+	}
+
+	fn resolve_class(&mut self, class: &Class) {
+		let saved = self.current_class.replace(class.name.clone());
+		if let Some(parent) = &class.parent {
+			self.resolve_type(parent, 0, 0);
+		}
+		self.table.push(ScopeKind::Class);
+		for param in &class.params {
+			self.resolve_type(&param.ty, param.line, param.column);
+			self.define(
+				param.name.clone(),
+				SymbolKind::Parameter,
+				param.line,
+				param.column,
+			);
+		}
+		// fields may share a name with a constructor parameter, the parameter initializes the field
+		let mut members: HashMap<String, usize> = HashMap::new();
+		for member in &class.members {
+			if let Some(symbol) = member_symbol(member) {
+				if let Some(line) = members.insert(symbol.name.clone(), symbol.line) {
+					self.diagnostics.push(ApolloError::at(
+						format!("{} is already defined on line {line}", symbol.name),
+						symbol.line,
+						symbol.column,
+					));
+				}
+				if let Some(scope) = self.table.scopes.last_mut() {
+					scope.symbols.insert(symbol.name.clone(), symbol);
+				}
+			}
+		}
+		self.define_class_members(&class.name);
+		for member in &class.members {
+			self.resolve_member(member);
+		}
+		self.pop_scope();
+		self.current_class = saved;
+	}
+
+	/// makes the members of a class, its extensions and its parents visible without `self.`
+	fn define_class_members(&mut self, class: &str) {
+		let mut current = Some(class.to_string());
+		let mut visited: Vec<String> = Vec::new();
+		while let Some(name) = current {
+			if visited.contains(&name) {
+				break;
+			}
+			let Some(info) = self.classes.get(&name) else {
+				break;
+			};
+			let members = info.members.clone();
+			current = info.parent.clone();
+			for member in members {
+				if let Some(scope) = self.table.scopes.last_mut() {
+					scope.symbols.entry(member.name.clone()).or_insert(member);
+				}
+			}
+			visited.push(name);
+		}
+	}
+
+	fn resolve_member(&mut self, member: &Item) {
+		match &member.kind {
+			ItemKind::Function(function) => self.resolve_function(function),
+			ItemKind::Variable(variable) => {
+				if let Some(ty) = &variable.ty {
+					self.resolve_type(ty, member.line, member.column);
+				}
+				if let Some(value) = &variable.value {
+					self.resolve_expression(value);
+				}
+			}
+			_ => self.resolve_item(member),
+		}
+	}
+
+	fn resolve_function(&mut self, function: &Function) {
+		self.table.push(ScopeKind::Function);
+		self.define_params(&function.params);
+		if let Some(return_type) = &function.return_type {
+			self.resolve_type(return_type, 0, 0);
+		}
+		if let Some(body) = &function.body {
+			self.resolve_block(body);
+		}
+		self.pop_scope();
+	}
+
+	fn define_params(&mut self, params: &[Param]) {
+		for param in params {
+			self.resolve_type(&param.ty, param.line, param.column);
+			self.define(
+				param.name.clone(),
+				SymbolKind::Parameter,
+				param.line,
+				param.column,
+			);
+		}
+	}
+
+	fn resolve_block(&mut self, block: &Block) {
+		self.table.push(ScopeKind::Block);
+		if let Some(scope) = self.table.scopes.last_mut() {
+			for statement in &block.statements {
+				if let StatementKind::Variable(variable) = &statement.kind {
+					scope
+						.pending
+						.entry(variable.name.clone())
+						.or_insert((statement.line, statement.column));
+				}
+			}
+		}
+		for statement in &block.statements {
+			self.resolve_statement(statement);
+		}
+		self.pop_scope();
+	}
+
+	fn resolve_statement(&mut self, statement: &Statement) {
+		match &statement.kind {
+			StatementKind::Variable(variable) => {
+				self.resolve_variable(variable, statement.line, statement.column);
+				self.define(
+					variable.name.clone(),
+					variable_kind(variable),
+					statement.line,
+					statement.column,
+				);
+			}
+			StatementKind::Expression(expression) | StatementKind::Panic(expression) => {
+				self.resolve_expression(expression)
+			}
+			StatementKind::Return(value) => {
+				if let Some(value) = value {
+					self.resolve_expression(value);
+				}
+			}
+			StatementKind::If {
+				condition,
+				then_block,
+				else_branch,
+			} => {
+				self.resolve_expression(condition);
+				self.resolve_block(then_block);
+				if let Some(else_branch) = else_branch {
+					self.resolve_statement(else_branch);
+				}
+			}
+			StatementKind::While { condition, body } => {
+				self.resolve_expression(condition);
+				self.resolve_block(body);
+			}
+			StatementKind::For { arguments, body } => {
+				for argument in arguments {
+					self.resolve_expression(argument);
+				}
+				self.resolve_block(body);
+			}
+			StatementKind::Block(block) => self.resolve_block(block),
+			StatementKind::Catch {
+				body,
+				error_type,
+				binding,
+				handler,
+			} => {
+				self.resolve_block(body);
+				self.resolve_type(error_type, statement.line, statement.column);
+				self.table.push(ScopeKind::Block);
+				self.define(
+					binding.clone(),
+					SymbolKind::Variable,
+					statement.line,
+					statement.column,
+				);
+				self.resolve_block(handler);
+				self.pop_scope();
+			}
+			StatementKind::Retry | StatementKind::Error => {}
+		}
+	}
+
+	/// the initializer is resolved first, `var x = x;` doesn't see itself
+	fn resolve_variable(&mut self, variable: &Variable, line: usize, column: usize) {
+		if let Some(ty) = &variable.ty {
+			self.resolve_type(ty, line, column);
+		}
+		if let Some(value) = &variable.value {
+			self.resolve_expression(value);
+		}
+	}
+
+	fn resolve_type(&mut self, ty: &Type, line: usize, column: usize) {
+		match ty {
+			Type::Named { name, generics } => {
+				match self.table.lookup(name) {
+					Some(symbol) if symbol.kind.is_type() => {}
+					Some(symbol) => self.diagnostics.push(ApolloError::at(
+						format!("{name} is not a type, it is a {}", kind_name(symbol.kind)),
+						line,
+						column,
+					)),
+					None => self.diagnostics.push(ApolloError::at(
+						format!("Unknown type {name}"),
+						line,
+						column,
+					)),
+				}
+				for generic in generics {
+					self.resolve_type(generic, line, column);
+				}
+			}
+			Type::Array { element, length } => {
+				self.resolve_type(element, line, column);
+				self.resolve_expression(length);
+			}
+			Type::Sized { base, length } => {
+				self.resolve_type(base, line, column);
+				self.resolve_expression(length);
+			}
+			Type::Slice(element) | Type::Reference(element) => self.resolve_type(element, line, column),
+		}
+	}
+
+	fn resolve_expression(&mut self, expression: &Expression) {
+		match &expression.kind {
+			ExpressionKind::Literal(_) => {}
+			ExpressionKind::Identifier(name) => self.resolve_identifier(name, expression),
+			ExpressionKind::SelfValue => {
+				self.check_self(expression);
+			}
+			ExpressionKind::Unary { operand, .. }
+			| ExpressionKind::Postfix { operand, .. }
+			| ExpressionKind::Reference(operand) => self.resolve_expression(operand),
+			ExpressionKind::Binary { left, right, .. } => {
+				self.resolve_expression(left);
+				self.resolve_expression(right);
+			}
+			ExpressionKind::Assign { target, value, .. } => {
+				self.resolve_expression(target);
+				self.resolve_expression(value);
+			}
+			ExpressionKind::Call { callee, arguments } => {
+				self.resolve_expression(callee);
+				for argument in arguments {
+					self.resolve_expression(argument);
+				}
+			}
+			ExpressionKind::Member { object, name } => {
+				if matches!(object.kind, ExpressionKind::SelfValue) {
+					self.resolve_self_member(name, expression);
+				} else {
+					self.resolve_expression(object);
+				}
+			}
+			ExpressionKind::Index { object, index } => {
+				self.resolve_expression(object);
+				self.resolve_expression(index);
+			}
+			ExpressionKind::New { ty, arguments } => {
+				if let Some(ty) = ty {
+					self.resolve_type(ty, expression.line, expression.column);
+				}
+				for argument in arguments {
+					self.resolve_expression(argument);
+				}
+			}
+			ExpressionKind::ArrayLiteral(elements) => {
+				for element in elements {
+					self.resolve_expression(element);
+				}
+			}
+			ExpressionKind::StructLiteral { name, fields } => {
+				self.resolve_struct_literal(name, fields, expression);
+			}
+			ExpressionKind::Cast { value, ty } => {
+				self.resolve_expression(value);
+				self.resolve_type(ty, expression.line, expression.column);
+			}
+			ExpressionKind::Closure { params, body } => {
+				self.table.push(ScopeKind::Closure);
+				self.define_params(params);
+				self.resolve_block(body);
+				self.pop_scope();
+			}
+			ExpressionKind::Lambda { param, body } => {
+				self.table.push(ScopeKind::Closure);
+				self.define(
+					param.clone(),
+					SymbolKind::Parameter,
+					expression.line,
+					expression.column,
+				);
+				self.resolve_expression(body);
+				self.pop_scope();
+			}
+			ExpressionKind::Match { subject, arms } => {
+				// patterns name enum variants, those are checked by the analyzer
+				self.resolve_expression(subject);
+				for arm in arms {
+					self.resolve_statement(&arm.body);
+				}
+			}
+			ExpressionKind::Format(parts) => {
+				for part in parts {
+					if let FormatPart::Expression(part) = part {
+						self.resolve_expression(part);
+					}
+				}
+			}
+		}
+	}
+
+	fn resolve_identifier(&mut self, name: &str, expression: &Expression) {
+		let error = match self.table.find(name) {
+			Lookup::Found(symbol, ScopeKind::Module)
+				if matches!(symbol.kind, SymbolKind::Constant | SymbolKind::Variable)
+					&& self
+						.global_initializer
+						.is_some_and(|line| symbol.line >= line) =>
+			{
+				format!(
+					"{name} is used before its declaration on line {}",
+					symbol.line
+				)
+			}
+			Lookup::Found(..) => return,
+			Lookup::NotYetDeclared(line) => {
+				format!("{name} is used before its declaration on line {line}")
+			}
+			Lookup::Missing => format!("Undefined identifier {name}"),
+		};
+		self
+			.diagnostics
+			.push(ApolloError::at(error, expression.line, expression.column));
+	}
+
+	/// `self` only means something inside a class or an extend block
+	fn check_self(&mut self, expression: &Expression) -> bool {
+		if self
+			.table
+			.innermost(&[ScopeKind::Class, ScopeKind::Extend])
+			.is_some()
+		{
+			return true;
+		}
+		self.diagnostics.push(ApolloError::at(
+			"'self' can only be used inside of a class or extend block".to_string(),
+			expression.line,
+			expression.column,
+		));
+		false
+	}
+
+	fn resolve_self_member(&mut self, name: &str, expression: &Expression) {
+		if !self.check_self(expression) {
+			return;
+		}
+		let Some(class) = self.current_class.clone() else {
+			return;
+		};
+		if !self.class_has_member(&class, name) {
+			self.diagnostics.push(ApolloError::at(
+				format!("{class} has no member named {name}"),
+				expression.line,
+				expression.column,
+			));
+		}
+	}
+
+	/// walks the parent chain, classes that end in a builtin are assumed to have the member
+	fn class_has_member(&self, class: &str, name: &str) -> bool {
+		let mut current = Some(class.to_string());
+		let mut visited: Vec<String> = Vec::new();
+		while let Some(class) = current {
+			if visited.contains(&class) {
+				return false;
+			}
+			let Some(info) = self.classes.get(&class) else {
+				return true; // builtin class, its members aren't known here
+			};
+			if is_builtin_class(&class) || info.members.iter().any(|m| m.name == name) {
+				return true;
+			}
+			current = info.parent.clone();
+			visited.push(class);
+		}
+		false
+	}
+
+	fn resolve_struct_literal(
+		&mut self,
+		name: &str,
+		fields: &[(String, Expression)],
+		expression: &Expression,
+	) {
+		let fields_known: Option<Vec<String>> = match self.table.lookup(name).map(|s| s.kind) {
+			Some(SymbolKind::Struct) => self.structs.get(name).cloned(),
+			Some(SymbolKind::Class) => None,
+			Some(kind) => {
+				self.diagnostics.push(ApolloError::at(
+					format!("{name} is a {}, not a struct", kind_name(kind)),
+					expression.line,
+					expression.column,
+				));
+				None
+			}
+			None => {
+				self.diagnostics.push(ApolloError::at(
+					format!("Unknown type {name}"),
+					expression.line,
+					expression.column,
+				));
+				None
+			}
+		};
+		for (field, value) in fields {
+			let exists = match &fields_known {
+				Some(known) => known.contains(field),
+				None if self.classes.contains_key(name) => self.class_has_member(name, field),
+				None => true,
+			};
+			if !exists {
+				self.diagnostics.push(ApolloError::at(
+					format!("{name} has no field named {field}"),
+					value.line,
+					value.column,
+				));
+			}
+			self.resolve_expression(value);
+		}
+	}
+
+	fn define(&mut self, name: String, kind: SymbolKind, line: usize, column: usize) {
+		if let Some(existing) = self.table.define(Symbol {
+			name: name.clone(),
+			kind,
+			line,
+			column,
+		}) {
+			self.diagnostics.push(ApolloError::at(
+				format!("{name} is already defined on line {}", existing.line),
+				line,
+				column,
+			));
+		}
+	}
+
+	fn pop_scope(&mut self) {
+		let Some(scope) = self.table.pop() else {
+			return;
+		};
+		if self.debug_mode > 1 && scope.kind != ScopeKind::Builtin {
+			let mut names: Vec<&String> = scope.symbols.keys().collect();
+			names.sort();
+			print_debug(
+				&format!("Closed {:?} scope: ", scope.kind),
+				&format!("{names:?}"),
+				self.logging,
+				&self.output_dir,
+			);
+		}
+	}
+}
+
+fn variable_kind(variable: &Variable) -> SymbolKind {
+	if variable.constant {
+		SymbolKind::Constant
+	} else {
+		SymbolKind::Variable
+	}
+}
+
+fn member_symbol(member: &Item) -> Option<Symbol> {
+	let (name, kind) = match &member.kind {
+		ItemKind::Function(function) => (function.key(), SymbolKind::Function),
+		ItemKind::Variable(variable) => (variable.name.clone(), SymbolKind::Field),
+		_ => return None,
+	};
+	Some(Symbol {
+		name,
+		kind,
+		line: member.line,
+		column: member.column,
+	})
+}
+
+/// constructor parameters double as fields
+fn param_symbol(param: &Param) -> Symbol {
+	Symbol {
+		name: param.name.clone(),
+		kind: SymbolKind::Field,
+		line: param.line,
+		column: param.column,
+	}
+}
+
+fn is_builtin_class(name: &str) -> bool {
+	BUILTIN_CLASSES.contains(&name) || BUILTIN_ERRORS.iter().any(|(error, _)| *error == name)
+}
+
+fn kind_name(kind: SymbolKind) -> &'static str {
+	match kind {
+		SymbolKind::Module => "module",
+		SymbolKind::Type => "type",
+		SymbolKind::Class => "class",
+		SymbolKind::Struct => "struct",
+		SymbolKind::Enum => "enum",
+		SymbolKind::Function => "function",
+		SymbolKind::Field => "field",
+		SymbolKind::Constant => "constant",
+		SymbolKind::Variable => "variable",
+		SymbolKind::Parameter => "parameter",
+	}
+}
@@ -0,0 +1,458 @@
+use std::collections::HashMap;
+
+use crate::builtins;
+use crate::parser;
+use crate::util;
+
+use builtins::{ANNOTATIONS, BUILTIN_ERRORS};
+use parser::{
+	AnnotationArgument, Block, Expression, ExpressionKind, FormatPart, Item, ItemKind, Literal,
+	MatchArm, Pattern, Program, Statement, StatementKind, Type,
+};
+use util::ApolloError;
+use util::print_debug;
+
+/// Checks that need the whole parsed file but no type information
+pub struct Analyzer<'a> {
+	program: &'a Program,
+	debug_mode: u8,
+	logging: bool,
+	output_dir: String,
+
+	enums: HashMap<String, Vec<String>>, // enum name -> variant names in declaration order
+	classes: HashMap<String, Option<String>>, // class name -> parent class name
+	handler_depth: usize,                // number of catch handlers around the current statement
+	diagnostics: Vec<ApolloError>,
+}
+
+impl<'a> Analyzer<'a> {
+	pub fn new(program: &'a Program, debug_mode: u8, logging: bool, output_dir: String) -> Self {
+		Analyzer {
+			program,
+			debug_mode,
+			logging,
+			output_dir,
+			enums: HashMap::new(),
+			classes: BUILTIN_ERRORS
+				.iter()
+				.map(|(name, parent)| (name.to_string(), parent.map(|p| p.to_string())))
+				.collect(),
+			handler_depth: 0,
+			diagnostics: Vec::new(),
+		}
+	}
+
+	/// returns every error and warning found in the file
+	pub fn begin(&mut self) -> Vec<ApolloError> {
+		if self.debug_mode > 0 {
+			print_debug(
+				"Analyzing file: ",
+				&self.program.filepath,
+				self.logging,
+				&self.output_dir,
+			);
+		}
+		let program = self.program;
+		for item in &program.items {
+			match &item.kind {
+				ItemKind::Enum(e) => {
+					self.enums.insert(
+						e.name.clone(),
+						e.variants.iter().map(|v| v.name.clone()).collect(),
+					);
+				}
+				ItemKind::Class(class) => {
+					let parent = match &class.parent {
+						Some(Type::Named { name, .. }) => Some(name.clone()),
+						_ => None,
+					};
+					self.classes.insert(class.name.clone(), parent);
+				}
+				_ => {}
+			}
+		}
+		for item in &program.items {
+			self.check_item(item, false);
+		}
+		std::mem::take(&mut self.diagnostics)
+	}
+
+	/// `member` is true for declarations inside a `class` or `extend` block
+	fn check_item(&mut self, item: &Item, member: bool) {
+		self.check_annotations(item, member);
+		match &item.kind {
+			ItemKind::Function(function) => {
+				if let Some(body) = &function.body {
+					self.check_function_body(body);
+				}
+			}
+			ItemKind::Class(class) => {
+				for member in &class.members {
+					self.check_item(member, true);
+				}
+			}
+			ItemKind::Extend(extend) => {
+				for member in &extend.members {
+					self.check_item(member, true);
+				}
+			}
+			ItemKind::Variable(variable) => {
+				if let Some(value) = &variable.value {
+					self.check_expression(value);
+				}
+			}
+			ItemKind::Use(_) | ItemKind::Struct(_) | ItemKind::Enum(_) | ItemKind::Error => {}
+		}
+	}
+
+	/// unknown and misplaced annotations are ignored with a warning, wrong arguments are errors
+	fn check_annotations(&mut self, item: &Item, member: bool) {
+		let placement = match &item.kind {
+			ItemKind::Function(_) if member => "method",
+			ItemKind::Function(_) => "fn",
+			ItemKind::Class(_) => "class",
+			ItemKind::Struct(_) => "struct",
+			ItemKind::Enum(_) => "enum",
+			ItemKind::Extend(_) => "extend",
+			ItemKind::Variable(_) => "var",
+			ItemKind::Use(_) => "use",
+			ItemKind::Error => return,
+		};
+		for annotation in &item.annotations {
+			let name = &annotation.name;
+			let Some((_, placements, parameters)) = ANNOTATIONS.iter().find(|(n, ..)| n == name) else {
+				self.diagnostics.push(ApolloError::warning(
+					format!("Unknown annotation #[{name}]"),
+					annotation.line,
+					annotation.column,
+				));
+				continue;
+			};
+			if !placements.contains(&placement) {
+				self.diagnostics.push(ApolloError::warning(
+					format!(
+						"#[{name}] has no effect on a {placement}, it belongs on a {}",
+						placements.join(" or ")
+					),
+					annotation.line,
+					annotation.column,
+				));
+			}
+			let kinds: Vec<&str> = annotation
+				.arguments
+				.iter()
+				.map(AnnotationArgument::kind)
+				.collect();
+			if kinds != *parameters {
+				self.diagnostics.push(ApolloError::at(
+					format!(
+						"#[{name}] takes ({}) but was given ({})",
+						parameters.join(", "),
+						kinds.join(", ")
+					),
+					annotation.line,
+					annotation.column,
+				));
+			}
+		}
+	}
+
+	/// `retry` never reaches through a function or closure boundary
+	fn check_function_body(&mut self, body: &Block) {
+		let saved = self.handler_depth;
+		self.handler_depth = 0;
+		self.check_block(body);
+		self.handler_depth = saved;
+	}
+
+	fn check_block(&mut self, block: &Block) {
+		for statement in &block.statements {
+			self.check_statement(statement);
+		}
+	}
+
+	fn check_statement(&mut self, statement: &Statement) {
+		match &statement.kind {
+			StatementKind::Variable(variable) => {
+				if let Some(value) = &variable.value {
+					self.check_expression(value);
+				}
+			}
+			StatementKind::Expression(expression) | StatementKind::Panic(expression) => {
+				self.check_expression(expression)
+			}
+			StatementKind::Return(value) => {
+				if let Some(value) = value {
+					self.check_expression(value);
+				}
+			}
+			StatementKind::If {
+				condition,
+				then_block,
+				else_branch,
+			} => {
+				self.check_expression(condition);
+				self.check_block(then_block);
+				if let Some(else_branch) = else_branch {
+					self.check_statement(else_branch);
+				}
+			}
+			StatementKind::While { condition, body } => {
+				self.check_expression(condition);
+				self.check_block(body);
+			}
+			StatementKind::For { arguments, body } => {
+				for argument in arguments {
+					self.check_expression(argument);
+				}
+				self.check_block(body);
+			}
+			StatementKind::Block(block) => self.check_block(block),
+			StatementKind::Catch {
+				body,
+				error_type,
+				handler,
+				..
+			} => {
+				self.check_block(body);
+				self.check_caught_type(error_type, statement);
+				self.handler_depth += 1;
+				self.check_block(handler);
+				self.handler_depth -= 1;
+			}
+			StatementKind::Retry => {
+				if self.handler_depth == 0 {
+					self.diagnostics.push(ApolloError::at(
+						"'retry' can only be used inside of a catch handler".to_string(),
+						statement.line,
+						statement.column,
+					));
+				}
+			}
+			StatementKind::Error => {}
+		}
+	}
+
+	/// the type in `catch(Type e)` has to be a class that derives from `Error`
+	fn check_caught_type(&mut self, error_type: &Type, statement: &Statement) {
+		let Type::Named { name, .. } = error_type else {
+			self.diagnostics.push(ApolloError::at(
+				"Only error classes can be caught".to_string(),
+				statement.line,
+				statement.column,
+			));
+			return;
+		};
+		let mut current = Some(name.clone());
+		let mut visited: Vec<String> = Vec::new();
+		while let Some(class) = current {
+			if class == "Error" {
+				return;
+			}
+			if visited.contains(&class) {
+				break; // inheritance cycle, reported as not deriving from Error
+			}
+			match self.classes.get(&class) {
+				Some(parent) => current = parent.clone(),
+				None if class == *name => {
+					self.diagnostics.push(ApolloError::at(
+						format!("Unknown error class {name}"),
+						statement.line,
+						statement.column,
+					));
+					return;
+				}
+				None => break,
+			}
+			visited.push(class);
+		}
+		self.diagnostics.push(ApolloError::at(
+			format!("{name} cannot be caught because it does not derive from Error"),
+			statement.line,
+			statement.column,
+		));
+	}
+
+	fn check_expression(&mut self, expression: &Expression) {
+		match &expression.kind {
+			ExpressionKind::Literal(_) | ExpressionKind::Identifier(_) | ExpressionKind::SelfValue => {}
+			ExpressionKind::Unary { operand, .. }
+			| ExpressionKind::Postfix { operand, .. }
+			| ExpressionKind::Reference(operand) => self.check_expression(operand),
+			ExpressionKind::Binary { left, right, .. } => {
+				self.check_expression(left);
+				self.check_expression(right);
+			}
+			ExpressionKind::Assign { target, value, .. } => {
+				self.check_expression(target);
+				self.check_expression(value);
+			}
+			ExpressionKind::Call { callee, arguments } => {
+				self.check_expression(callee);
+				for argument in arguments {
+					self.check_expression(argument);
+				}
+			}
+			ExpressionKind::Member { object, .. } => self.check_expression(object),
+			ExpressionKind::Index { object, index } => {
+				self.check_expression(object);
+				self.check_expression(index);
+			}
+			ExpressionKind::New { arguments, .. } | ExpressionKind::ArrayLiteral(arguments) => {
+				for argument in arguments {
+					self.check_expression(argument);
+				}
+			}
+			ExpressionKind::StructLiteral { fields, .. } => {
+				for (_, value) in fields {
+					self.check_expression(value);
+				}
+			}
+			ExpressionKind::Cast { value, .. } => self.check_expression(value),
+			ExpressionKind::Closure { body, .. } => self.check_function_body(body),
+			ExpressionKind::Lambda { body, .. } => self.check_expression(body),
+			ExpressionKind::Match { subject, arms } => {
+				self.check_expression(subject);
+				self.check_match(expression, arms);
+				for arm in arms {
+					self.check_statement(&arm.body);
+				}
+			}
+			ExpressionKind::Format(parts) => {
+				for part in parts {
+					if let FormatPart::Expression(part) = part {
+						self.check_expression(part);
+					}
+				}
+			}
+		}
+	}
+
+	/// reports arms that can never run and matches over an enum that miss a variant
+	fn check_match(&mut self, expression: &Expression, arms: &[MatchArm]) {
+		let mut wildcard: Option<&MatchArm> = None;
+		let mut matched_enum: Option<String> = None;
+		let mut seen: Vec<String> = Vec::new();
+
+		for arm in arms {
+			if let Some(wildcard) = wildcard {
+				self.diagnostics.push(ApolloError::warning(
+					format!(
+						"Unreachable match arm, the '_' arm on line {} already matches everything",
+						wildcard.line
+					),
+					arm.line,
+					arm.column,
+				));
+				continue;
+			}
+
+			let key = match &arm.pattern {
+				Pattern::Wildcard => {
+					wildcard = Some(arm);
+					continue;
+				}
+				Pattern::Literal(literal) => match literal {
+					// `0x10` and `16` are the same pattern
+					Literal::Number { number, .. } => format!("{number:?}"),
+					Literal::String(value) => format!("\"{}\"", value.escape_default()),
+					Literal::Character(value) => format!("'{}'", value.escape_default()),
+					Literal::Boolean(value) => value.to_string(),
+				},
+				Pattern::Variant(path) => {
+					let Some(enum_name) = self.resolve_variant(path, &matched_enum, arm) else {
+						continue;
+					};
+					if let Some(matched) = &matched_enum
+						&& *matched != enum_name
+					{
+						self.diagnostics.push(ApolloError::at(
+							format!("Match arm uses a variant of {enum_name}, but this match is over {matched}"),
+							arm.line,
+							arm.column,
+						));
+						continue;
+					}
+					matched_enum = Some(enum_name);
+					path.last().cloned().unwrap_or_default()
+				}
+			};
+
+			if seen.contains(&key) {
+				self.diagnostics.push(ApolloError::warning(
+					format!("Unreachable match arm, {key} is already matched above"),
+					arm.line,
+					arm.column,
+				));
+			} else {
+				seen.push(key);
+			}
+		}
+
+		if wildcard.is_none()
+			&& let Some(enum_name) = matched_enum
+		{
+			let missing: Vec<String> = self.enums[&enum_name]
+				.iter()
+				.filter(|variant| !seen.contains(variant))
+				.cloned()
+				.collect();
+			if !missing.is_empty() {
+				self.diagnostics.push(ApolloError::at(
+					format!(
+						"Non-exhaustive match over {enum_name}, missing: {}",
+						missing.join(", ")
+					),
+					expression.line,
+					expression.column,
+				));
+			}
+		}
+	}
+
+	/// finds the enum a variant pattern belongs to, `ACE` or `FACES.ACE`
+	fn resolve_variant(
+		&mut self,
+		path: &[String],
+		matched_enum: &Option<String>,
+		arm: &MatchArm,
+	) -> Option<String> {
+		let variant = path.last()?;
+		let error = match path {
+			[enum_name, _] => match self.enums.get(enum_name) {
+				Some(variants) if variants.contains(variant) => return Some(enum_name.clone()),
+				Some(_) => format!("{variant} is not a variant of {enum_name}"),
+				None => format!("Unknown enum {enum_name}"),
+			},
+			[_] => {
+				let mut candidates: Vec<&String> = self
+					.enums
+					.iter()
+					.filter(|(_, variants)| variants.contains(variant))
+					.map(|(name, _)| name)
+					.collect();
+				candidates.sort();
+				match candidates.as_slice() {
+					[] => format!("Unknown enum variant {variant}"),
+					[only] => return Some(only.to_string()),
+					_ => match matched_enum {
+						Some(matched) if candidates.contains(&matched) => return Some(matched.clone()),
+						_ => format!(
+							"{variant} is ambiguous, it is a variant of {}, write ENUM.{variant} instead",
+							candidates
+								.iter()
+								.map(|c| c.to_string())
+								.collect::<Vec<String>>()
+								.join(" and ")
+						),
+					},
+				}
+			}
+			_ => format!("Invalid pattern {}", path.join(".")),
+		};
+		self
+			.diagnostics
+			.push(ApolloError::at(error, arm.line, arm.column));
+		None
+	}
+}
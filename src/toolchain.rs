@@ -0,0 +1,164 @@
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use crate::util;
+
+use util::ApolloError;
+use util::print_debug;
+
+/// compilers tried in order when neither `--cc` nor `CC` name one
+const CANDIDATES: [&str; 3] = ["cc", "gcc", "clang"];
+
+/// Finds the system C compiler and turns generated C into native executables
+pub struct Toolchain {
+	pub cc: String,
+	debug_mode: u8,
+	logging: bool,
+	output_dir: String,
+}
+
+impl Toolchain {
+	/// `--cc` wins over `CC`, otherwise the first of `cc`, `gcc` and `clang` on the `PATH`
+	pub fn find(
+		requested: Option<String>,
+		debug_mode: u8,
+		logging: bool,
+		output_dir: String,
+	) -> Result<Self, ApolloError> {
+		let requested = requested.or_else(|| std::env::var("CC").ok().filter(|cc| !cc.is_empty()));
+		let cc = match &requested {
+			Some(cc) => locate(cc).ok_or_else(|| {
+				ApolloError::new(
+					format!("Could not find the C compiler {cc}"),
+					None,
+					Some("Check the --cc flag or the CC environment variable".to_string()),
+					None,
+				)
+			})?,
+			None => CANDIDATES.iter().find_map(|cc| locate(cc)).ok_or_else(|| {
+				ApolloError::new(
+					"Could not find a C compiler".to_string(),
+					None,
+					Some("Install cc, gcc or clang, or pass one with --cc".to_string()),
+					None,
+				)
+			})?,
+		};
+		let cc = cc.to_string_lossy().to_string();
+		if debug_mode > 0 {
+			print_debug("Using C compiler: ", &cc, logging, &output_dir);
+		}
+		Ok(Toolchain {
+			cc,
+			debug_mode,
+			logging,
+			output_dir,
+		})
+	}
+
+	/// compiles and links `source` into `<output dir>/bin/<name>`, returns the path of the
+	/// executable and any warnings, or every diagnostic when the C compiler fails
+	pub fn build(&self, source: &str) -> Result<(String, Vec<ApolloError>), Vec<ApolloError>> {
+		let bin = format!("{}/bin", self.output_dir.trim_end_matches('/'));
+		if let Err(e) = std::fs::create_dir_all(&bin) {
+			return Err(vec![ApolloError::new(
+				format!("Failed to create {bin}: {e}"),
+				None,
+				None,
+				None,
+			)]);
+		}
+		let name = Path::new(source)
+			.file_stem()
+			.map(|s| s.to_string_lossy().to_string())
+			.unwrap_or_else(|| "main".to_string());
+		let executable = format!("{bin}/{name}{}", std::env::consts::EXE_SUFFIX);
+
+		let mut command = Command::new(&self.cc);
+		command
+			.arg("-std=c11")
+			.arg("-Wall")
+			.arg("-o")
+			.arg(&executable)
+			.arg(source)
+			.arg("-lm");
+		if self.debug_mode > 1 {
+			print_debug(
+				"Running: ",
+				&format!("{command:?}"),
+				self.logging,
+				&self.output_dir,
+			);
+		}
+		let output = command.output().map_err(|e| {
+			vec![ApolloError::new(
+				format!("Failed to run {}: {e}", self.cc),
+				None,
+				None,
+				None,
+			)]
+		})?;
+
+		let stderr = String::from_utf8_lossy(&output.stderr);
+		let mut diagnostics = parse_diagnostics(&stderr);
+		if output.status.success() {
+			return Ok((executable, diagnostics));
+		}
+		if !diagnostics.iter().any(|d| !d.is_warning()) {
+			// linker errors and crashes don't follow the `file:line:column` format
+			diagnostics.push(ApolloError::new(
+				format!("{} failed with {}", self.cc, output.status),
+				None,
+				Some(stderr.trim().to_string()).filter(|s| !s.is_empty()),
+				None,
+			));
+		}
+		Err(diagnostics)
+	}
+}
+
+/// a compiler named by path, or looked up on the `PATH`
+fn locate(cc: &str) -> Option<PathBuf> {
+	let path = Path::new(cc);
+	if path.components().count() > 1 {
+		return path.is_file().then(|| path.to_path_buf());
+	}
+	let paths = std::env::var_os("PATH")?;
+	std::env::split_paths(&paths).find_map(|dir| {
+		let candidate = dir.join(format!("{cc}{}", std::env::consts::EXE_SUFFIX));
+		candidate.is_file().then_some(candidate)
+	})
+}
+
+/// turns `file.c:12:5: error: message` lines into errors located in the generated C, notes and
+/// source excerpts are dropped
+pub fn parse_diagnostics(stderr: &str) -> Vec<ApolloError> {
+	let mut diagnostics = Vec::new();
+	for line in stderr.lines() {
+		let Some((prefix, kind, message)) =
+			["fatal error", "error", "warning"].iter().find_map(|kind| {
+				let (prefix, message) = line.split_once(&format!(": {kind}: "))?;
+				Some((prefix, *kind, message))
+			})
+		else {
+			continue;
+		};
+		let mut parts = prefix.rsplitn(3, ':');
+		let column = parts.next().and_then(|c| c.parse::<usize>().ok());
+		let row = parts.next().and_then(|l| l.parse::<usize>().ok());
+		let diagnostic = match (parts.next(), row, column) {
+			(Some(file), Some(row), Some(column)) => {
+				let message = format!("{message} in {file}");
+				if kind == "warning" {
+					ApolloError::warning(message, row, column)
+				} else {
+					ApolloError::at(message, row, column)
+				}
+			}
+			_ if kind == "warning" => continue,
+			_ => ApolloError::new(format!("{prefix}: {message}"), None, None, None),
+		};
+		diagnostics.push(diagnostic);
+	}
+	diagnostics
+}
@@ -1,4 +1,4 @@
-use crate::util::{BOTTOM, CLEAR, DOWN, LEFT, RIGHT, TOP, UP};
+use crate::util::{DOWN, TOP};
 
 const TOTAL: f32 = 100.0;
 
@@ -27,6 +27,12 @@ pub struct LoadingBar {
 	pub empty_color: &'static str,
 }
 
+impl Default for LoadingBar {
+	fn default() -> Self {
+		Self::new()
+	}
+}
+
 impl LoadingBar {
 	/// default settings for the loading bar
 	pub fn new() -> Self {
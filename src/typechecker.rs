@@ -0,0 +1,1692 @@
+use std::collections::HashMap;
+use std::fmt::Display;
+
+use crate::builtins;
+use crate::lexer::{NumberValue, TokenType};
+use crate::parser;
+use crate::util;
+
+use builtins::{BUILTIN_CLASSES, BUILTIN_ERRORS, FUNCTION_SIGNATURES, METHOD_SIGNATURES};
+use parser::{
+	Block, Class, Expression, ExpressionKind, FormatPart, Function, Item, ItemKind, Literal,
+	MatchArm, Param, Pattern, Program, Statement, StatementKind, Type, Variable,
+};
+use util::ApolloError;
+use util::print_debug;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Primitive {
+	U8,
+	U16,
+	U32,
+	U64,
+	Usize,
+	I8,
+	I16,
+	I32,
+	I64,
+	Isize,
+	F32,
+	F64,
+	Bool,
+	Char,
+	String,
+}
+
+const PRIMITIVES: [Primitive; 15] = [
+	Primitive::U8,
+	Primitive::U16,
+	Primitive::U32,
+	Primitive::U64,
+	Primitive::Usize,
+	Primitive::I8,
+	Primitive::I16,
+	Primitive::I32,
+	Primitive::I64,
+	Primitive::Isize,
+	Primitive::F32,
+	Primitive::F64,
+	Primitive::Bool,
+	Primitive::Char,
+	Primitive::String,
+];
+
+impl Primitive {
+	pub fn from_name(name: &str) -> Option<Primitive> {
+		PRIMITIVES.into_iter().find(|p| p.name() == name)
+	}
+
+	pub fn name(self) -> &'static str {
+		match self {
+			Primitive::U8 => "u8",
+			Primitive::U16 => "u16",
+			Primitive::U32 => "u32",
+			Primitive::U64 => "u64",
+			Primitive::Usize => "usize",
+			Primitive::I8 => "i8",
+			Primitive::I16 => "i16",
+			Primitive::I32 => "i32",
+			Primitive::I64 => "i64",
+			Primitive::Isize => "isize",
+			Primitive::F32 => "f32",
+			Primitive::F64 => "f64",
+			Primitive::Bool => "bool",
+			Primitive::Char => "char",
+			Primitive::String => "string",
+		}
+	}
+
+	pub fn is_integer(self) -> bool {
+		self.is_signed() || self.is_unsigned()
+	}
+
+	pub fn is_unsigned(self) -> bool {
+		matches!(
+			self,
+			Primitive::U8 | Primitive::U16 | Primitive::U32 | Primitive::U64 | Primitive::Usize
+		)
+	}
+
+	pub fn is_signed(self) -> bool {
+		matches!(
+			self,
+			Primitive::I8 | Primitive::I16 | Primitive::I32 | Primitive::I64 | Primitive::Isize
+		)
+	}
+
+	pub fn is_float(self) -> bool {
+		matches!(self, Primitive::F32 | Primitive::F64)
+	}
+
+	pub fn is_numeric(self) -> bool {
+		self.is_integer() || self.is_float()
+	}
+
+	/// size in bits, `usize` and `isize` are treated as 64 bit
+	pub fn width(self) -> u8 {
+		match self {
+			Primitive::U8 | Primitive::I8 | Primitive::Bool | Primitive::Char => 8,
+			Primitive::U16 | Primitive::I16 => 16,
+			Primitive::U32 | Primitive::I32 | Primitive::F32 => 32,
+			_ => 64,
+		}
+	}
+
+	/// whether a numeric literal fits, `negative` for a literal behind a unary minus
+	fn holds(self, number: NumberValue, negative: bool) -> bool {
+		let bits = u32::from(self.width());
+		match number {
+			NumberValue::Float(value) => self != Primitive::F32 || (value as f32).is_finite(),
+			NumberValue::Integer(value) if self.is_unsigned() => {
+				if negative {
+					value == 0
+				} else {
+					value <= u64::MAX >> (64 - bits)
+				}
+			}
+			NumberValue::Integer(value) if self.is_signed() => {
+				let limit = 1u64 << (bits - 1);
+				if negative {
+					value <= limit
+				} else {
+					value < limit
+				}
+			}
+			NumberValue::Integer(_) => true,
+		}
+	}
+
+	/// whether every value of `self` fits into `target` without an `as` cast
+	fn widens_to(self, target: Primitive) -> bool {
+		if self.is_float() || target.is_float() {
+			return self == Primitive::F32 && target == Primitive::F64;
+		}
+		if !self.is_integer() || !target.is_integer() {
+			return false;
+		}
+		if self.is_signed() && target.is_unsigned() {
+			return false;
+		}
+		if self.is_unsigned() && target.is_signed() {
+			return self.width() < target.width();
+		}
+		self.width() < target.width()
+	}
+}
+
+/// The type of a value as seen by the type checker
+#[derive(Debug, Clone, PartialEq)]
+pub enum Ty {
+	Primitive(Primitive),
+	Unit,
+	/// a `NUMBER`, `HEXADECIMAL`, `OCTAL` or `BINARY` literal that hasn't been given a type yet
+	IntLiteral,
+	/// a `FLOAT` literal that hasn't been given a type yet
+	FloatLiteral,
+	/// `[u8; 256]`, the length is `None` when it isn't a literal
+	Array {
+		element: Box<Ty>,
+		length: Option<u64>,
+	},
+	/// `[char]`
+	Slice(Box<Ty>),
+	/// classes, structs, enums and builtin classes like `Vector<Card>`
+	Named {
+		name: String,
+		generics: Vec<Ty>,
+	},
+	/// `@Player`
+	Reference(Box<Ty>),
+	Function(Box<Signature>),
+	/// a class or enum named in expression position, `FACES[i]` or `InvalidInstruction(...)`
+	TypeName(String),
+	Module,
+	/// `Any`, members of builtin types that aren't known here, or the result of an earlier error
+	Unknown,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Signature {
+	pub params: Vec<Ty>,
+	/// the last parameter takes any number of arguments
+	pub variadic: bool,
+	pub returns: Ty,
+}
+
+impl Ty {
+	pub fn from_type(ty: &Type) -> Ty {
+		match ty {
+			Type::Named { name, generics } => match name.as_str() {
+				"Unit" => Ty::Unit,
+				"Any" => Ty::Unknown,
+				// the builtin wrapper classes are the primitives they wrap
+				"Char" => Ty::Primitive(Primitive::Char),
+				"String" => Ty::Primitive(Primitive::String),
+				_ => match Primitive::from_name(name) {
+					Some(primitive) => Ty::Primitive(primitive),
+					None => Ty::Named {
+						name: name.clone(),
+						generics: generics.iter().map(Ty::from_type).collect(),
+					},
+				},
+			},
+			Type::Array { element, length } => Ty::Array {
+				element: Box::new(Ty::from_type(element)),
+				length: match &length.kind {
+					ExpressionKind::Literal(Literal::Number {
+						number: NumberValue::Integer(length),
+						..
+					}) => Some(*length),
+					_ => None,
+				},
+			},
+			Type::Slice(element) => Ty::Slice(Box::new(Ty::from_type(element))),
+			// `string[progLen]` is still a string, the length only matters to the backend
+			Type::Sized { base, .. } => Ty::from_type(base),
+			Type::Reference(inner) => Ty::Reference(Box::new(Ty::from_type(inner))),
+		}
+	}
+
+	/// parses the type names used in the builtin signature tables, `T` is `element`
+	fn from_builtin(name: &str, element: &Ty) -> Ty {
+		if name == "T" {
+			return element.clone();
+		}
+		if let Some(inner) = name.strip_prefix('[').and_then(|n| n.strip_suffix(']')) {
+			return Ty::Slice(Box::new(Ty::from_builtin(inner, element)));
+		}
+		Ty::from_type(&Type::Named {
+			name: name.to_string(),
+			generics: Vec::new(),
+		})
+	}
+
+	/// looks through references, `@Player` has the same members as `Player`
+	pub fn referent(&self) -> &Ty {
+		match self {
+			Ty::Reference(inner) => inner.referent(),
+			ty => ty,
+		}
+	}
+
+	fn primitive(&self) -> Option<Primitive> {
+		match self.referent() {
+			Ty::Primitive(primitive) => Some(*primitive),
+			_ => None,
+		}
+	}
+
+	fn is(&self, primitive: Primitive) -> bool {
+		self.primitive() == Some(primitive)
+	}
+
+	fn is_unknown(&self) -> bool {
+		matches!(self.referent(), Ty::Unknown)
+	}
+
+	fn is_numeric(&self) -> bool {
+		match self.referent() {
+			Ty::IntLiteral | Ty::FloatLiteral | Ty::Unknown => true,
+			ty => ty.primitive().is_some_and(Primitive::is_numeric),
+		}
+	}
+
+	fn is_integer(&self) -> bool {
+		match self.referent() {
+			Ty::IntLiteral | Ty::Unknown => true,
+			ty => ty.primitive().is_some_and(Primitive::is_integer),
+		}
+	}
+
+	/// the type a variable gets when it is declared without one, `var x = 1;` is an `i32`
+	pub fn concrete(self) -> Ty {
+		match self {
+			Ty::IntLiteral => Ty::Primitive(Primitive::I32),
+			Ty::FloatLiteral => Ty::Primitive(Primitive::F64),
+			ty => ty,
+		}
+	}
+}
+
+impl Display for Ty {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		match self {
+			Ty::Primitive(primitive) => write!(f, "{}", primitive.name()),
+			Ty::Unit => write!(f, "Unit"),
+			Ty::IntLiteral => write!(f, "integer literal"),
+			Ty::FloatLiteral => write!(f, "float literal"),
+			Ty::Array {
+				element,
+				length: Some(length),
+			} => write!(f, "[{element}; {length}]"),
+			Ty::Array { element, .. } => write!(f, "[{element}; _]"),
+			Ty::Slice(element) => write!(f, "[{element}]"),
+			Ty::Named { name, generics } if generics.is_empty() => write!(f, "{name}"),
+			Ty::Named { name, generics } => write!(
+				f,
+				"{name}<{}>",
+				generics
+					.iter()
+					.map(|g| g.to_string())
+					.collect::<Vec<String>>()
+					.join(", ")
+			),
+			Ty::Reference(inner) => write!(f, "@{inner}"),
+			Ty::Function(signature) => write!(
+				f,
+				"fn({}) -> {}",
+				signature
+					.params
+					.iter()
+					.map(|p| p.to_string())
+					.collect::<Vec<String>>()
+					.join(", "),
+				signature.returns
+			),
+			Ty::TypeName(name) => write!(f, "type {name}"),
+			Ty::Module => write!(f, "module"),
+			Ty::Unknown => write!(f, "Any"),
+		}
+	}
+}
+
+enum Member {
+	Field { ty: Ty, constant: bool },
+	Method(Signature),
+}
+
+enum MemberLookup {
+	Found(Member),
+	/// the receiver is a builtin whose members aren't known, so anything goes
+	Unknown,
+	Missing,
+}
+
+#[derive(Debug, Default)]
+struct ClassInfo {
+	parent: Option<String>,
+	constructor: Vec<Ty>,
+	fields: HashMap<String, (Ty, bool)>, // name -> (type, constant)
+	methods: HashMap<String, Signature>,
+}
+
+/// Infers the type of every expression and checks assignments, calls and returns
+pub struct TypeChecker<'a> {
+	program: &'a Program,
+	debug_mode: u8,
+	logging: bool,
+	output_dir: String,
+
+	classes: HashMap<String, ClassInfo>,
+	structs: HashMap<String, Vec<(String, Ty)>>,
+	enums: HashMap<String, Vec<String>>,
+	functions: HashMap<String, Signature>,
+	globals: HashMap<String, (Ty, bool)>,
+	extensions: HashMap<&'static str, HashMap<String, Signature>>, // methods added to primitives
+	scopes: Vec<HashMap<String, (Ty, bool)>>,                      // locals, innermost last
+	self_type: Option<Ty>,
+	returns: Vec<Ty>, // return type of the enclosing functions, `Any` for closures
+	diagnostics: Vec<ApolloError>,
+}
+
+impl<'a> TypeChecker<'a> {
+	pub fn new(program: &'a Program, debug_mode: u8, logging: bool, output_dir: String) -> Self {
+		TypeChecker {
+			program,
+			debug_mode,
+			logging,
+			output_dir,
+			classes: HashMap::new(),
+			structs: HashMap::new(),
+			enums: HashMap::new(),
+			functions: HashMap::new(),
+			globals: HashMap::new(),
+			extensions: HashMap::new(),
+			scopes: Vec::new(),
+			self_type: None,
+			returns: Vec::new(),
+			diagnostics: Vec::new(),
+		}
+	}
+
+	/// returns every type error found in the file
+	pub fn begin(&mut self) -> Vec<ApolloError> {
+		if self.debug_mode > 0 {
+			print_debug(
+				"Type checking file: ",
+				&self.program.filepath,
+				self.logging,
+				&self.output_dir,
+			);
+		}
+		self.declare();
+		let program = self.program;
+		for item in &program.items {
+			self.check_item(item);
+		}
+		std::mem::take(&mut self.diagnostics)
+	}
+
+	/// records the declarations of the file without checking any bodies, later stages use this
+	/// together with `type_of` to look up the types of expressions
+	pub fn declare(&mut self) {
+		let program = self.program;
+		for item in &program.items {
+			self.declare_item(item);
+		}
+	}
+
+	/// the type of an expression in the current scope, without reporting anything
+	pub fn type_of(&mut self, expression: &Expression) -> Ty {
+		self.peek_type(expression)
+	}
+
+	pub fn enter_scope(&mut self) {
+		self.scopes.push(HashMap::new());
+	}
+
+	pub fn exit_scope(&mut self) {
+		self.scopes.pop();
+	}
+
+	/// changes what `self` refers to, returns the previous type
+	pub fn set_self_type(&mut self, ty: Option<Ty>) -> Option<Ty> {
+		std::mem::replace(&mut self.self_type, ty)
+	}
+
+	/// records the signature of every top-level declaration so bodies can refer to later ones
+	fn declare_item(&mut self, item: &Item) {
+		match &item.kind {
+			ItemKind::Function(function) => {
+				self.functions.insert(function.key(), signature(function));
+			}
+			ItemKind::Class(class) => {
+				let info = ClassInfo {
+					parent: match &class.parent {
+						Some(Type::Named { name, .. }) => Some(name.clone()),
+						_ => None,
+					},
+					constructor: class.params.iter().map(|p| Ty::from_type(&p.ty)).collect(),
+					fields: class
+						.params
+						.iter()
+						.map(|p| (p.name.clone(), (Ty::from_type(&p.ty), p.constant)))
+						.collect(),
+					methods: HashMap::new(),
+				};
+				self.classes.insert(class.name.clone(), info);
+				self.declare_members(&class.name, &class.members);
+			}
+			ItemKind::Extend(extend) => {
+				if let Type::Named { name, .. } = &extend.target {
+					self.declare_members(name, &extend.members);
+				}
+			}
+			ItemKind::Struct(s) => {
+				let fields = s
+					.fields
+					.iter()
+					.map(|f| (f.name.clone(), Ty::from_type(&f.ty)))
+					.collect();
+				self.structs.insert(s.name.clone(), fields);
+			}
+			ItemKind::Enum(e) => {
+				let variants = e.variants.iter().map(|v| v.name.clone()).collect();
+				self.enums.insert(e.name.clone(), variants);
+			}
+			ItemKind::Variable(variable) => {
+				let ty = variable
+					.ty
+					.as_ref()
+					.map(Ty::from_type)
+					.unwrap_or(Ty::Unknown);
+				self
+					.globals
+					.insert(variable.name.clone(), (ty, variable.constant));
+			}
+			ItemKind::Use(_) | ItemKind::Error => {}
+		}
+	}
+
+	/// members of `class String` and `extend Char` become methods of the primitive
+	fn declare_members(&mut self, owner: &str, members: &[Item]) {
+		for member in members {
+			match &member.kind {
+				ItemKind::Function(function) => {
+					let key = function.key();
+					let signature = signature(function);
+					match receiver_primitive(owner) {
+						Some(primitive) => {
+							self
+								.extensions
+								.entry(primitive.name())
+								.or_default()
+								.insert(key, signature);
+						}
+						None => {
+							self
+								.classes
+								.entry(owner.to_string())
+								.or_default()
+								.methods
+								.insert(key, signature);
+						}
+					}
+				}
+				ItemKind::Variable(variable) if receiver_primitive(owner).is_none() => {
+					let ty = variable
+						.ty
+						.as_ref()
+						.map(Ty::from_type)
+						.unwrap_or(Ty::Unknown);
+					self
+						.classes
+						.entry(owner.to_string())
+						.or_default()
+						.fields
+						.insert(variable.name.clone(), (ty, variable.constant));
+				}
+				_ => {}
+			}
+		}
+	}
+
+	fn check_item(&mut self, item: &Item) {
+		match &item.kind {
+			ItemKind::Function(function) => self.check_function(function),
+			ItemKind::Class(class) => self.check_class(class),
+			ItemKind::Extend(extend) => {
+				let saved = self.self_type.replace(Ty::from_type(&extend.target));
+				for member in &extend.members {
+					self.check_item(member);
+				}
+				self.self_type = saved;
+			}
+			ItemKind::Enum(e) => {
+				for variant in &e.variants {
+					if let Some(value) = &variant.value {
+						let ty = self.check_expression(value, None);
+						if !ty.is_integer() {
+							self.mismatch(&Ty::IntLiteral, &ty, value);
+						}
+					}
+				}
+			}
+			ItemKind::Variable(variable) => {
+				let ty = self.check_variable(variable, item.line, item.column);
+				if self.self_type.is_none() {
+					self
+						.globals
+						.insert(variable.name.clone(), (ty, variable.constant));
+				}
+			}
+			ItemKind::Use(_) | ItemKind::Struct(_) | ItemKind::Error => {}
+		}
+	}
+
+	fn check_class(&mut self, class: &Class) {
+		let self_type = match receiver_primitive(&class.name) {
+			Some(primitive) => Ty::Primitive(primitive),
+			None => Ty::Named {
+				name: class.name.clone(),
+				generics: Vec::new(),
+			},
+		};
+		let saved = self.self_type.replace(self_type);
+		for member in &class.members {
+			self.check_item(member);
+		}
+		self.self_type = saved;
+	}
+
+	fn check_function(&mut self, function: &Function) {
+		let Some(body) = &function.body else {
+			return;
+		};
+		let returns = function
+			.return_type
+			.as_ref()
+			.map(Ty::from_type)
+			.unwrap_or(Ty::Unit);
+		self.returns.push(returns);
+		self.scopes.push(HashMap::new());
+		self.define_params(&function.params);
+		self.check_statements(body);
+		self.scopes.pop();
+		self.returns.pop();
+	}
+
+	fn define_params(&mut self, params: &[Param]) {
+		for param in params {
+			let mut ty = Ty::from_type(&param.ty);
+			if param.variadic {
+				ty = Ty::Slice(Box::new(ty));
+			}
+			self.define(&param.name, ty, param.constant);
+		}
+	}
+
+	fn check_block(&mut self, block: &Block) {
+		self.scopes.push(HashMap::new());
+		self.check_statements(block);
+		self.scopes.pop();
+	}
+
+	/// checks a block without opening a new scope, used for function bodies
+	fn check_statements(&mut self, block: &Block) {
+		for statement in &block.statements {
+			self.check_statement(statement);
+		}
+	}
+
+	fn check_statement(&mut self, statement: &Statement) {
+		match &statement.kind {
+			StatementKind::Variable(variable) => {
+				let ty = self.check_variable(variable, statement.line, statement.column);
+				self.define(&variable.name, ty, variable.constant);
+			}
+			StatementKind::Expression(expression) | StatementKind::Panic(expression) => {
+				self.check_expression(expression, None);
+			}
+			StatementKind::Return(value) => self.check_return(value.as_ref(), statement),
+			StatementKind::If {
+				condition,
+				then_block,
+				else_branch,
+			} => {
+				self.check_condition(condition);
+				self.check_block(then_block);
+				if let Some(else_branch) = else_branch {
+					self.check_statement(else_branch);
+				}
+			}
+			StatementKind::While { condition, body } => {
+				self.check_condition(condition);
+				self.check_block(body);
+			}
+			StatementKind::For { arguments, body } => {
+				for argument in arguments {
+					self.check_expression(argument, None);
+				}
+				self.check_block(body);
+			}
+			StatementKind::Block(block) => self.check_block(block),
+			StatementKind::Catch {
+				body,
+				error_type,
+				binding,
+				handler,
+			} => {
+				self.check_block(body);
+				self.scopes.push(HashMap::new());
+				self.define(binding, Ty::from_type(error_type), true);
+				self.check_statements(handler);
+				self.scopes.pop();
+			}
+			StatementKind::Retry | StatementKind::Error => {}
+		}
+	}
+
+	/// returns the type the declared name gets, the annotation if there is one
+	fn check_variable(&mut self, variable: &Variable, line: usize, column: usize) -> Ty {
+		let declared = variable.ty.as_ref().map(Ty::from_type);
+		let Some(value) = &variable.value else {
+			return declared.unwrap_or(Ty::Unknown);
+		};
+		let actual = self.check_expression(value, declared.as_ref());
+		match declared {
+			Some(declared) => {
+				if !self.assignable(&declared, &actual) {
+					self.diagnostics.push(ApolloError::at(
+						format!(
+							"Mismatched types, {} is declared as {declared}, but is assigned {actual}",
+							variable.name
+						),
+						line,
+						column,
+					));
+				}
+				declared
+			}
+			None => actual.concrete(),
+		}
+	}
+
+	fn check_return(&mut self, value: Option<&Expression>, statement: &Statement) {
+		let expected = self.returns.last().cloned().unwrap_or(Ty::Unknown);
+		match value {
+			Some(value) => {
+				let actual = self.check_expression(value, Some(&expected));
+				if expected == Ty::Unit && !actual.is_unknown() {
+					self.diagnostics.push(ApolloError::at(
+						format!("This function returns Unit, but a value of type {actual} is returned"),
+						value.line,
+						value.column,
+					));
+				} else if !self.assignable(&expected, &actual) {
+					self.diagnostics.push(ApolloError::at(
+						format!("Mismatched return type, expected {expected}, found {actual}"),
+						value.line,
+						value.column,
+					));
+				}
+			}
+			None if expected != Ty::Unit && !expected.is_unknown() => {
+				self.diagnostics.push(ApolloError::at(
+					format!("Missing return value, this function returns {expected}"),
+					statement.line,
+					statement.column,
+				));
+			}
+			None => {}
+		}
+	}
+
+	fn check_condition(&mut self, condition: &Expression) {
+		let ty = self.check_expression(condition, None);
+		if !ty.is(Primitive::Bool) && !ty.is_unknown() {
+			self.diagnostics.push(ApolloError::at(
+				format!("Conditions have to be bool, found {ty}"),
+				condition.line,
+				condition.column,
+			));
+		}
+	}
+
+	/// infers the type of an expression, `expected` is used by `new` and array literals
+	fn check_expression(&mut self, expression: &Expression, expected: Option<&Ty>) -> Ty {
+		match &expression.kind {
+			ExpressionKind::Literal(literal) => {
+				self.check_literal_range(literal, false, expected, expression);
+				literal_type(literal)
+			}
+			ExpressionKind::Identifier(name) => self.lookup(name),
+			ExpressionKind::SelfValue => self.self_type.clone().unwrap_or(Ty::Unknown),
+			ExpressionKind::Unary { operator, operand } => {
+				self.check_unary(*operator, operand, expected, expression)
+			}
+			ExpressionKind::Postfix { operator, operand } => {
+				self.check_unary(*operator, operand, expected, expression)
+			}
+			ExpressionKind::Binary {
+				left,
+				operator,
+				right,
+			} => {
+				let left = self.check_expression(left, None);
+				let right = self.check_expression(right, Some(&left));
+				self
+					.binary_type(*operator, &left, &right)
+					.unwrap_or_else(|| {
+						self.diagnostics.push(ApolloError::at(
+							format!(
+								"Cannot apply '{}' to {left} and {right}",
+								operator_symbol(*operator)
+							),
+							expression.line,
+							expression.column,
+						));
+						Ty::Unknown
+					})
+			}
+			ExpressionKind::Assign {
+				target,
+				operator,
+				value,
+			} => self.check_assign(target, *operator, value),
+			ExpressionKind::Call { callee, arguments } => self.check_call(callee, arguments),
+			ExpressionKind::Member { object, name } => {
+				let object_ty = self.check_expression(object, None);
+				match self.find_member(&object_ty, name) {
+					MemberLookup::Found(Member::Field { ty, .. }) => ty,
+					MemberLookup::Found(Member::Method(signature)) => Ty::Function(Box::new(signature)),
+					MemberLookup::Unknown => Ty::Unknown,
+					MemberLookup::Missing => {
+						self.diagnostics.push(ApolloError::at(
+							format!("{object_ty} has no member named {name}"),
+							expression.line,
+							expression.column,
+						));
+						Ty::Unknown
+					}
+				}
+			}
+			ExpressionKind::Index { object, index } => self.check_index(object, index, expression),
+			ExpressionKind::New { ty, arguments } => {
+				self.check_new(ty.as_ref(), arguments, expected, expression)
+			}
+			ExpressionKind::StructLiteral { name, fields } => self.check_struct_literal(name, fields),
+			ExpressionKind::ArrayLiteral(elements) => {
+				self.check_array_literal(elements, expected, expression)
+			}
+			ExpressionKind::Cast { value, ty } => {
+				let from = self.check_expression(value, None);
+				let to = Ty::from_type(ty);
+				if !self.castable(&from, &to) {
+					self.diagnostics.push(ApolloError::at(
+						format!("Cannot cast {from} as {to}"),
+						expression.line,
+						expression.column,
+					));
+				}
+				to
+			}
+			ExpressionKind::Reference(operand) => {
+				Ty::Reference(Box::new(self.check_expression(operand, None)))
+			}
+			ExpressionKind::Closure { params, body } => {
+				self.returns.push(Ty::Unknown);
+				self.scopes.push(HashMap::new());
+				self.define_params(params);
+				self.check_statements(body);
+				self.scopes.pop();
+				self.returns.pop();
+				Ty::Function(Box::new(Signature {
+					params: params.iter().map(|p| Ty::from_type(&p.ty)).collect(),
+					variadic: false,
+					returns: Ty::Unknown,
+				}))
+			}
+			ExpressionKind::Lambda { param, body } => {
+				self.scopes.push(HashMap::new());
+				self.define(param, Ty::Unknown, true);
+				let returns = self.check_expression(body, None);
+				self.scopes.pop();
+				Ty::Function(Box::new(Signature {
+					params: vec![Ty::Unknown],
+					variadic: false,
+					returns,
+				}))
+			}
+			ExpressionKind::Match { subject, arms } => {
+				let subject = self.check_expression(subject, None);
+				self.check_match(&subject, arms);
+				Ty::Unit
+			}
+			ExpressionKind::Format(parts) => {
+				for part in parts {
+					if let FormatPart::Expression(part) = part {
+						self.check_format_part(part);
+					}
+				}
+				Ty::Primitive(Primitive::String)
+			}
+		}
+	}
+
+	/// embedded expressions have to be something that can be turned into text
+	fn check_format_part(&mut self, part: &Expression) {
+		let ty = self.check_expression(part, None);
+		let enum_value = matches!(&ty, Ty::Named { name, .. } if self.enums.contains_key(name));
+		if !enum_value && !self.castable(&ty, &Ty::Primitive(Primitive::String)) {
+			self.diagnostics.push(ApolloError::at(
+				format!("Cannot insert {ty} into a string"),
+				part.line,
+				part.column,
+			));
+		}
+	}
+
+	/// a number that doesn't fit its suffix, or the type it is used as, like `300` given to a `u8`
+	fn check_literal_range(
+		&mut self,
+		literal: &Literal,
+		negative: bool,
+		expected: Option<&Ty>,
+		at: &Expression,
+	) {
+		let Literal::Number {
+			value,
+			number,
+			suffix,
+			..
+		} = literal
+		else {
+			return;
+		};
+		let primitive = match suffix {
+			Some(suffix) => Primitive::from_name(suffix),
+			None => expected.and_then(|ty| ty.referent().primitive()),
+		};
+		if let Some(primitive) = primitive
+			&& !primitive.holds(*number, negative)
+		{
+			let sign = if negative { "-" } else { "" };
+			self.diagnostics.push(ApolloError::at(
+				format!("{sign}{value} is out of range for {}", primitive.name()),
+				at.line,
+				at.column,
+			));
+		}
+	}
+
+	/// prefix operators and the postfix `++`, `--` and custom operators
+	fn check_unary(
+		&mut self,
+		operator: TokenType,
+		operand: &Expression,
+		expected: Option<&Ty>,
+		expression: &Expression,
+	) -> Ty {
+		let ty = match (operator, &operand.kind) {
+			// `-128i8` fits even though `128i8` doesn't, so the sign is checked together with the literal
+			(TokenType::MINUS, ExpressionKind::Literal(literal @ Literal::Number { .. })) => {
+				self.check_literal_range(literal, true, expected, expression);
+				literal_type(literal)
+			}
+			_ => self.check_expression(operand, None),
+		};
+		if ty.is_unknown() {
+			return Ty::Unknown;
+		}
+		let symbol = operator_symbol(operator);
+		if let Ty::Named { .. } = ty.referent() {
+			let key = format!("{symbol}Unary");
+			if let MemberLookup::Found(Member::Method(signature)) = self.find_member(&ty, &key) {
+				return signature.returns;
+			}
+		}
+		let valid = match operator {
+			TokenType::BANG => ty.is(Primitive::Bool),
+			TokenType::SQUIGGLE => ty.is_integer(),
+			TokenType::MINUS | TokenType::INCREMENT | TokenType::DECREMENT => ty.is_numeric(),
+			_ => false,
+		};
+		if !valid {
+			self.diagnostics.push(ApolloError::at(
+				format!("{ty} does not support the '{symbol}' operator"),
+				expression.line,
+				expression.column,
+			));
+			return Ty::Unknown;
+		}
+		ty.referent().clone()
+	}
+
+	/// the type of `left <operator> right`, `None` when the operator doesn't apply
+	fn binary_type(&self, operator: TokenType, left: &Ty, right: &Ty) -> Option<Ty> {
+		let (left, right) = (left.referent(), right.referent());
+		let comparison = matches!(
+			operator,
+			TokenType::EQL
+				| TokenType::BANGASSIGN
+				| TokenType::LESS
+				| TokenType::LESSEQL
+				| TokenType::GREATER
+				| TokenType::GREATEREQL
+				| TokenType::BOOLOR
+				| TokenType::BOOLAND
+		);
+		if left.is_unknown() || right.is_unknown() {
+			return Some(match operator {
+				_ if comparison => Ty::Primitive(Primitive::Bool),
+				_ if left.is_unknown() => right.clone(),
+				_ => left.clone(),
+			});
+		}
+		let bool_type = Ty::Primitive(Primitive::Bool);
+		match operator {
+			TokenType::EQL | TokenType::BANGASSIGN => {
+				(self.assignable(left, right) || self.assignable(right, left)).then_some(bool_type)
+			}
+			TokenType::LESS | TokenType::LESSEQL | TokenType::GREATER | TokenType::GREATEREQL => {
+				let ordered = self.unify_numeric(left, right).is_some()
+					|| (left == right && (left.is(Primitive::Char) || left.is(Primitive::String)));
+				ordered.then_some(bool_type)
+			}
+			TokenType::BOOLOR | TokenType::BOOLAND => {
+				(left.is(Primitive::Bool) && right.is(Primitive::Bool)).then_some(bool_type)
+			}
+			TokenType::PLUS if left.is(Primitive::String) || right.is(Primitive::String) => {
+				let text = |ty: &Ty| ty.is(Primitive::String) || ty.is(Primitive::Char);
+				(text(left) && text(right)).then_some(Ty::Primitive(Primitive::String))
+			}
+			TokenType::PLUS
+			| TokenType::MINUS
+			| TokenType::STAR
+			| TokenType::DIVIDE
+			| TokenType::PERCENT => self.unify_numeric(left, right),
+			TokenType::BAR | TokenType::CARROT | TokenType::AMPERSAND => {
+				if left.is(Primitive::Bool) && right.is(Primitive::Bool) {
+					return Some(bool_type);
+				}
+				self.unify_numeric(left, right).filter(|ty| ty.is_integer())
+			}
+			TokenType::LEFTSHIFT | TokenType::RIGHTSHIFT => {
+				(left.is_integer() && right.is_integer()).then(|| left.clone())
+			}
+			_ => None,
+		}
+	}
+
+	/// the wider of two numeric types, literals take the type of the other side
+	fn unify_numeric(&self, left: &Ty, right: &Ty) -> Option<Ty> {
+		if !left.is_numeric() || !right.is_numeric() {
+			return None;
+		}
+		if self.assignable(left, right) {
+			Some(left.clone())
+		} else if self.assignable(right, left) {
+			Some(right.clone())
+		} else {
+			None
+		}
+	}
+
+	fn check_assign(&mut self, target: &Expression, operator: TokenType, value: &Expression) -> Ty {
+		let target_ty = self.check_expression(target, None);
+		self.check_mutable(target);
+		let value_ty = self.check_expression(value, Some(&target_ty));
+		let result = match compound_operator(operator) {
+			Some(operator) => self.binary_type(operator, &target_ty, &value_ty),
+			None => Some(value_ty.clone()),
+		};
+		match result {
+			Some(result) if self.assignable(&target_ty, &result) => {}
+			_ => self.diagnostics.push(ApolloError::at(
+				format!(
+					"Mismatched types, cannot assign {value_ty} to {target_ty} with '{}'",
+					operator_symbol(operator)
+				),
+				value.line,
+				value.column,
+			)),
+		}
+		target_ty
+	}
+
+	/// constants and `const` fields can only be set by their declaration
+	fn check_mutable(&mut self, target: &Expression) {
+		let constant = match &target.kind {
+			ExpressionKind::Identifier(name) => self
+				.binding(name)
+				.map(|(_, constant)| constant)
+				.map(|constant| (name.clone(), constant)),
+			ExpressionKind::Member { object, name } => {
+				let object_ty = self.peek_type(object);
+				match self.find_member(&object_ty, name) {
+					MemberLookup::Found(Member::Field { constant, .. }) => Some((name.clone(), constant)),
+					_ => None,
+				}
+			}
+			_ => None,
+		};
+		if let Some((name, true)) = constant {
+			self.diagnostics.push(ApolloError::at(
+				format!("Cannot assign to {name} because it is a constant"),
+				target.line,
+				target.column,
+			));
+		}
+	}
+
+	/// the type of an expression that has already been checked, without reporting anything twice
+	fn peek_type(&mut self, expression: &Expression) -> Ty {
+		let count = self.diagnostics.len();
+		let ty = self.check_expression(expression, None);
+		self.diagnostics.truncate(count);
+		ty
+	}
+
+	fn check_call(&mut self, callee: &Expression, arguments: &[Expression]) -> Ty {
+		let callee_ty = self.check_expression(callee, None);
+		let name = match &callee.kind {
+			ExpressionKind::Identifier(name) | ExpressionKind::Member { name, .. } => name.clone(),
+			_ => callee_ty.to_string(),
+		};
+		match callee_ty {
+			Ty::Function(signature) => {
+				self.check_arguments(&name, &signature, arguments, callee);
+				signature.returns
+			}
+			Ty::TypeName(class) => self.check_constructor(&class, arguments, callee),
+			Ty::Unknown => {
+				for argument in arguments {
+					self.check_expression(argument, None);
+				}
+				Ty::Unknown
+			}
+			ty => {
+				self.diagnostics.push(ApolloError::at(
+					format!("{name} is not a function, it is {ty}"),
+					callee.line,
+					callee.column,
+				));
+				for argument in arguments {
+					self.check_expression(argument, None);
+				}
+				Ty::Unknown
+			}
+		}
+	}
+
+	fn check_arguments(
+		&mut self,
+		name: &str,
+		signature: &Signature,
+		arguments: &[Expression],
+		at: &Expression,
+	) {
+		let required = signature.params.len() - usize::from(signature.variadic);
+		let count_ok = match signature.variadic {
+			true => arguments.len() >= required,
+			false => arguments.len() == required,
+		};
+		if !count_ok {
+			self.diagnostics.push(ApolloError::at(
+				format!(
+					"{name} takes {}{required} argument{}, but {} {} given",
+					if signature.variadic { "at least " } else { "" },
+					if required == 1 { "" } else { "s" },
+					arguments.len(),
+					if arguments.len() == 1 { "was" } else { "were" }
+				),
+				at.line,
+				at.column,
+			));
+		}
+		for (i, argument) in arguments.iter().enumerate() {
+			let expected = signature
+				.params
+				.get(i)
+				.or(signature.params.last().filter(|_| signature.variadic))
+				.cloned()
+				.unwrap_or(Ty::Unknown);
+			let actual = self.check_expression(argument, Some(&expected));
+			if !self.assignable(&expected, &actual) {
+				self.diagnostics.push(ApolloError::at(
+					format!(
+						"Mismatched types, argument {} of {name} is {expected}, found {actual}",
+						i + 1
+					),
+					argument.line,
+					argument.column,
+				));
+			}
+		}
+	}
+
+	/// `Player(...)`, `new (...)` and `Game new`
+	fn check_constructor(&mut self, class: &str, arguments: &[Expression], at: &Expression) -> Ty {
+		let ty = Ty::Named {
+			name: class.to_string(),
+			generics: Vec::new(),
+		};
+		// the implicit constructor stays available next to a custom `fn new`
+		let signature = self.classes.get(class).map(|info| {
+			let implicit = Signature {
+				params: info.constructor.clone(),
+				variadic: false,
+				returns: ty.clone(),
+			};
+			match info.methods.get("new") {
+				Some(custom) if arguments.len() != implicit.params.len() => custom.clone(),
+				_ => implicit,
+			}
+		});
+		match signature {
+			Some(signature) => self.check_arguments(class, &signature, arguments, at),
+			None => {
+				for argument in arguments {
+					self.check_expression(argument, None);
+				}
+			}
+		}
+		ty
+	}
+
+	fn check_new(
+		&mut self,
+		ty: Option<&Type>,
+		arguments: &[Expression],
+		expected: Option<&Ty>,
+		expression: &Expression,
+	) -> Ty {
+		let target = match ty.map(Ty::from_type) {
+			Some(target) => target,
+			None => match expected.map(|ty| ty.referent().clone()) {
+				Some(target) if !target.is_unknown() => target,
+				_ => {
+					self.diagnostics.push(ApolloError::at(
+						"Cannot infer what 'new' creates here, add a type to the declaration".to_string(),
+						expression.line,
+						expression.column,
+					));
+					Ty::Unknown
+				}
+			},
+		};
+		match &target {
+			Ty::Named { name, generics } if generics.is_empty() && self.classes.contains_key(name) => {
+				self.check_constructor(name, arguments, expression);
+			}
+			_ => {
+				for argument in arguments {
+					self.check_expression(argument, None);
+				}
+			}
+		}
+		target
+	}
+
+	fn check_struct_literal(&mut self, name: &str, fields: &[(String, Expression)]) -> Ty {
+		for (field, value) in fields {
+			let expected = match self.structs.get(name) {
+				Some(known) => known
+					.iter()
+					.find(|(n, _)| n == field)
+					.map(|(_, ty)| ty.clone()),
+				None => {
+					let ty = Ty::Named {
+						name: name.to_string(),
+						generics: Vec::new(),
+					};
+					match self.find_member(&ty, field) {
+						MemberLookup::Found(Member::Field { ty, .. }) => Some(ty),
+						_ => None,
+					}
+				}
+			}
+			.unwrap_or(Ty::Unknown);
+			let actual = self.check_expression(value, Some(&expected));
+			if !self.assignable(&expected, &actual) {
+				self.diagnostics.push(ApolloError::at(
+					format!("Mismatched types, {name}.{field} is {expected}, found {actual}"),
+					value.line,
+					value.column,
+				));
+			}
+		}
+		Ty::Named {
+			name: name.to_string(),
+			generics: Vec::new(),
+		}
+	}
+
+	fn check_array_literal(
+		&mut self,
+		elements: &[Expression],
+		expected: Option<&Ty>,
+		expression: &Expression,
+	) -> Ty {
+		let (element, length) = match expected.map(Ty::referent) {
+			Some(Ty::Array { element, length }) => (Some(element.as_ref().clone()), *length),
+			Some(Ty::Slice(element)) => (Some(element.as_ref().clone()), None),
+			_ => (None, None),
+		};
+		if let Some(length) = length
+			&& length != elements.len() as u64
+		{
+			self.diagnostics.push(ApolloError::at(
+				format!(
+					"Expected an array of {length} elements, found {}",
+					elements.len()
+				),
+				expression.line,
+				expression.column,
+			));
+		}
+		let mut element_ty = element.clone();
+		for value in elements {
+			let actual = self.check_expression(value, element.as_ref());
+			match &element_ty {
+				Some(expected) if !self.assignable(expected, &actual) => {
+					self.mismatch(expected, &actual, value);
+				}
+				Some(_) => {}
+				None => element_ty = Some(actual),
+			}
+		}
+		// a wrong length was reported above, keep it from being reported again by the caller
+		Ty::Array {
+			element: Box::new(element_ty.unwrap_or(Ty::Unknown)),
+			length: length.or(Some(elements.len() as u64)),
+		}
+	}
+
+	fn check_index(
+		&mut self,
+		object: &Expression,
+		index: &Expression,
+		expression: &Expression,
+	) -> Ty {
+		let object_ty = self.check_expression(object, None);
+		let index_ty = self.check_expression(index, None);
+		if !index_ty.is_integer() {
+			self.diagnostics.push(ApolloError::at(
+				format!("Indices have to be integers, found {index_ty}"),
+				index.line,
+				index.column,
+			));
+		}
+		match object_ty.referent() {
+			Ty::Array { element, .. } | Ty::Slice(element) => element.as_ref().clone(),
+			Ty::Primitive(Primitive::String) => Ty::Primitive(Primitive::Char),
+			// enums can be indexed to get the entry at that position
+			Ty::TypeName(name) if self.enums.contains_key(name) => Ty::Named {
+				name: name.clone(),
+				generics: Vec::new(),
+			},
+			Ty::Named { name, generics } if name == "Vector" => {
+				generics.first().cloned().unwrap_or(Ty::Unknown)
+			}
+			Ty::Unknown => Ty::Unknown,
+			Ty::Named { name, .. } if !self.is_user_type(name) => Ty::Unknown,
+			ty => {
+				self.diagnostics.push(ApolloError::at(
+					format!("Cannot index into {ty}"),
+					expression.line,
+					expression.column,
+				));
+				Ty::Unknown
+			}
+		}
+	}
+
+	/// literal patterns have to match the type of the subject
+	fn check_match(&mut self, subject: &Ty, arms: &[MatchArm]) {
+		for arm in arms {
+			if let Pattern::Literal(literal) = &arm.pattern {
+				let pattern = literal_type(literal);
+				if !self.assignable(subject, &pattern) && !self.assignable(&pattern, subject) {
+					self.diagnostics.push(ApolloError::at(
+						format!("Mismatched types, this pattern is {pattern}, but the match is over {subject}"),
+						arm.line,
+						arm.column,
+					));
+				}
+			}
+			self.check_statement(&arm.body);
+		}
+	}
+
+	/// whether a value of type `actual` can be stored where `expected` is required
+	fn assignable(&self, expected: &Ty, actual: &Ty) -> bool {
+		match (expected, actual) {
+			(Ty::Unknown, _) | (_, Ty::Unknown) => true,
+			(Ty::Primitive(e), Ty::IntLiteral) => e.is_numeric(),
+			(Ty::Primitive(e), Ty::FloatLiteral) => e.is_float(),
+			(Ty::FloatLiteral, Ty::IntLiteral) => true,
+			(Ty::Primitive(e), Ty::Primitive(a)) => e == a || a.widens_to(*e),
+			(
+				Ty::Array {
+					element: e,
+					length: n,
+				},
+				Ty::Array {
+					element: a,
+					length: m,
+				},
+			) => self.assignable(e, a) && (n == m || n.is_none() || m.is_none()),
+			(Ty::Slice(e), Ty::Array { element: a, .. }) | (Ty::Slice(e), Ty::Slice(a)) => {
+				self.assignable(e, a)
+			}
+			(Ty::Reference(e), Ty::Reference(a)) => self.assignable(e, a),
+			// passing `@player` where a `Player` is expected copies the value
+			(_, Ty::Reference(a)) => self.assignable(expected, a),
+			(
+				Ty::Named {
+					name: e,
+					generics: eg,
+				},
+				Ty::Named {
+					name: a,
+					generics: ag,
+				},
+			) => {
+				let generics = eg.is_empty()
+					|| ag.is_empty()
+					|| (eg.len() == ag.len() && eg.iter().zip(ag).all(|(e, a)| self.assignable(e, a)));
+				(e == a && generics) || self.derives_from(a, e)
+			}
+			_ => expected == actual,
+		}
+	}
+
+	fn castable(&self, from: &Ty, to: &Ty) -> bool {
+		if self.assignable(to, from) || from.is_unknown() || to.is_unknown() {
+			return true;
+		}
+		let scalar = |ty: &Ty| {
+			matches!(ty, Ty::IntLiteral | Ty::FloatLiteral)
+				|| ty
+					.primitive()
+					.is_some_and(|p| p.is_numeric() || p == Primitive::Char || p == Primitive::Bool)
+		};
+		(scalar(from) && scalar(to)) || (scalar(from) && to.is(Primitive::String))
+	}
+
+	fn derives_from(&self, class: &str, ancestor: &str) -> bool {
+		let mut current = self.parent_of(class);
+		let mut depth = 0;
+		while let Some(name) = current {
+			if name == ancestor {
+				return true;
+			}
+			depth += 1;
+			if depth > self.classes.len() + BUILTIN_ERRORS.len() {
+				break; // inheritance cycle
+			}
+			current = self.parent_of(&name);
+		}
+		false
+	}
+
+	fn parent_of(&self, class: &str) -> Option<String> {
+		match self.classes.get(class) {
+			Some(info) => info.parent.clone(),
+			None => BUILTIN_ERRORS
+				.iter()
+				.find(|(name, _)| *name == class)
+				.and_then(|(_, parent)| parent.map(|p| p.to_string())),
+		}
+	}
+
+	fn is_user_type(&self, name: &str) -> bool {
+		self.classes.contains_key(name)
+			|| self.structs.contains_key(name)
+			|| self.enums.contains_key(name)
+	}
+
+	fn find_member(&self, ty: &Ty, name: &str) -> MemberLookup {
+		match ty.referent() {
+			Ty::Named {
+				name: owner,
+				generics,
+			} => {
+				if let Some(fields) = self.structs.get(owner) {
+					return match fields.iter().find(|(n, _)| n == name) {
+						Some((_, ty)) => MemberLookup::Found(Member::Field {
+							ty: ty.clone(),
+							constant: false,
+						}),
+						None => MemberLookup::Missing,
+					};
+				}
+				if self.enums.contains_key(owner) {
+					return MemberLookup::Unknown; // `.value` and friends of an enum entry
+				}
+				if self.classes.contains_key(owner) {
+					return self.class_member(owner, name);
+				}
+				let element = generics.first().cloned().unwrap_or(Ty::Unknown);
+				self.builtin_method(owner, name, &element)
+			}
+			Ty::Primitive(primitive) => {
+				if let Some(signature) = self
+					.extensions
+					.get(primitive.name())
+					.and_then(|methods| methods.get(name))
+				{
+					return MemberLookup::Found(Member::Method(signature.clone()));
+				}
+				self.builtin_method(primitive.name(), name, &Ty::Unknown)
+			}
+			Ty::TypeName(owner) => match self.enums.get(owner) {
+				Some(variants) if variants.iter().any(|v| v == name) => {
+					MemberLookup::Found(Member::Field {
+						ty: Ty::Named {
+							name: owner.clone(),
+							generics: Vec::new(),
+						},
+						constant: true,
+					})
+				}
+				Some(_) => MemberLookup::Missing,
+				None => MemberLookup::Unknown,
+			},
+			Ty::Unit | Ty::Function(_) | Ty::IntLiteral | Ty::FloatLiteral => MemberLookup::Missing,
+			_ => MemberLookup::Unknown,
+		}
+	}
+
+	/// walks the parent chain, a chain ending in a builtin class allows any member
+	fn class_member(&self, class: &str, name: &str) -> MemberLookup {
+		let mut current = Some(class.to_string());
+		let mut depth = 0;
+		while let Some(owner) = current {
+			let Some(info) = self.classes.get(&owner) else {
+				return MemberLookup::Unknown;
+			};
+			if let Some((ty, constant)) = info.fields.get(name) {
+				return MemberLookup::Found(Member::Field {
+					ty: ty.clone(),
+					constant: *constant,
+				});
+			}
+			if let Some(signature) = info.methods.get(name) {
+				return MemberLookup::Found(Member::Method(signature.clone()));
+			}
+			depth += 1;
+			if depth > self.classes.len() || BUILTIN_CLASSES.contains(&owner.as_str()) {
+				return MemberLookup::Unknown;
+			}
+			current = info.parent.clone();
+		}
+		MemberLookup::Missing
+	}
+
+	fn builtin_method(&self, receiver: &str, name: &str, element: &Ty) -> MemberLookup {
+		match METHOD_SIGNATURES
+			.iter()
+			.find(|(r, n, _, _)| *r == receiver && *n == name)
+		{
+			Some((_, _, params, returns)) => {
+				MemberLookup::Found(Member::Method(builtin_signature(params, returns, element)))
+			}
+			None => MemberLookup::Unknown,
+		}
+	}
+
+	/// locals, then members of the enclosing class, then top-level declarations and builtins
+	fn lookup(&self, name: &str) -> Ty {
+		if let Some((ty, _)) = self.binding(name) {
+			return ty;
+		}
+		if let Some(signature) = self.functions.get(name) {
+			return Ty::Function(Box::new(signature.clone()));
+		}
+		if self.is_user_type(name)
+			|| BUILTIN_CLASSES.contains(&name)
+			|| BUILTIN_ERRORS.iter().any(|(error, _)| *error == name)
+		{
+			return Ty::TypeName(name.to_string());
+		}
+		if let Some((_, params, returns)) = FUNCTION_SIGNATURES.iter().find(|(n, _, _)| *n == name) {
+			return Ty::Function(Box::new(builtin_signature(params, returns, &Ty::Unknown)));
+		}
+		if builtins::module_exports(name).is_some() {
+			return Ty::Module;
+		}
+		Ty::Unknown
+	}
+
+	/// type and constness of a variable, parameter, field of `self` or top-level constant
+	fn binding(&self, name: &str) -> Option<(Ty, bool)> {
+		for scope in self.scopes.iter().rev() {
+			if let Some(binding) = scope.get(name) {
+				return Some(binding.clone());
+			}
+		}
+		if let Some(self_type) = &self.self_type
+			&& let MemberLookup::Found(member) = self.find_member(self_type, name)
+		{
+			return Some(match member {
+				Member::Field { ty, constant } => (ty, constant),
+				Member::Method(signature) => (Ty::Function(Box::new(signature)), true),
+			});
+		}
+		self.globals.get(name).cloned()
+	}
+
+	/// binds a name in the innermost scope, or at the top level when no scope is open
+	pub fn define(&mut self, name: &str, ty: Ty, constant: bool) {
+		match self.scopes.last_mut() {
+			Some(scope) => {
+				scope.insert(name.to_string(), (ty, constant));
+			}
+			None => {
+				self.globals.insert(name.to_string(), (ty, constant));
+			}
+		}
+	}
+
+	fn mismatch(&mut self, expected: &Ty, actual: &Ty, at: &Expression) {
+		self.diagnostics.push(ApolloError::at(
+			format!("Mismatched types, expected {expected}, found {actual}"),
+			at.line,
+			at.column,
+		));
+	}
+}
+
+/// a missing return type means the function returns `Unit`
+fn signature(function: &Function) -> Signature {
+	Signature {
+		params: function
+			.params
+			.iter()
+			.map(|p| Ty::from_type(&p.ty))
+			.collect(),
+		variadic: function.params.last().is_some_and(|p| p.variadic),
+		returns: function
+			.return_type
+			.as_ref()
+			.map(Ty::from_type)
+			.unwrap_or(Ty::Unit),
+	}
+}
+
+fn builtin_signature(params: &[&str], returns: &str, element: &Ty) -> Signature {
+	Signature {
+		params: params
+			.iter()
+			.map(|p| Ty::from_builtin(p.trim_start_matches("..."), element))
+			.collect(),
+		variadic: params.last().is_some_and(|p| p.starts_with("...")),
+		returns: Ty::from_builtin(returns, element),
+	}
+}
+
+/// `class String` and `extend Char` add methods to the primitive they wrap
+pub fn receiver_primitive(owner: &str) -> Option<Primitive> {
+	match owner {
+		"String" => Some(Primitive::String),
+		"Char" => Some(Primitive::Char),
+		_ => Primitive::from_name(owner),
+	}
+}
+
+fn literal_type(literal: &Literal) -> Ty {
+	match literal {
+		Literal::Number {
+			suffix: Some(suffix),
+			..
+		} => Primitive::from_name(suffix).map_or(Ty::Unknown, Ty::Primitive),
+		Literal::Number {
+			kind: TokenType::FLOAT,
+			..
+		} => Ty::FloatLiteral,
+		Literal::Number { .. } => Ty::IntLiteral,
+		Literal::String(_) => Ty::Primitive(Primitive::String),
+		Literal::Character(_) => Ty::Primitive(Primitive::Char),
+		Literal::Boolean(_) => Ty::Primitive(Primitive::Bool),
+	}
+}
+
+/// `+=` -> `+`, `None` for plain `=`
+pub fn compound_operator(operator: TokenType) -> Option<TokenType> {
+	match operator {
+		TokenType::ADDASSIGN => Some(TokenType::PLUS),
+		TokenType::SUBASSIGN => Some(TokenType::MINUS),
+		TokenType::STARASSIGN => Some(TokenType::STAR),
+		TokenType::DIVASSIGN => Some(TokenType::DIVIDE),
+		TokenType::MODASSIGN => Some(TokenType::PERCENT),
+		TokenType::LEFTSHIFTASSIGN => Some(TokenType::LEFTSHIFT),
+		TokenType::RIGHTSHIFTASSIGN => Some(TokenType::RIGHTSHIFT),
+		TokenType::BITANDASSIGN => Some(TokenType::AMPERSAND),
+		TokenType::BITORASSIGN => Some(TokenType::BAR),
+		TokenType::CARROTASSIGN => Some(TokenType::CARROT),
+		_ => None,
+	}
+}
+
+pub fn operator_symbol(operator: TokenType) -> &'static str {
+	match operator {
+		TokenType::PLUS => "+",
+		TokenType::MINUS => "-",
+		TokenType::STAR => "*",
+		TokenType::DIVIDE => "/",
+		TokenType::PERCENT => "%",
+		TokenType::INCREMENT => "++",
+		TokenType::DECREMENT => "--",
+		TokenType::BANG => "!",
+		TokenType::SQUIGGLE => "~",
+		TokenType::QUESTION => "?",
+		TokenType::EQL => "==",
+		TokenType::BANGASSIGN => "!=",
+		TokenType::LESS => "<",
+		TokenType::LESSEQL => "<=",
+		TokenType::GREATER => ">",
+		TokenType::GREATEREQL => ">=",
+		TokenType::BOOLOR => "||",
+		TokenType::BOOLAND => "&&",
+		TokenType::BAR => "|",
+		TokenType::CARROT => "^",
+		TokenType::AMPERSAND => "&",
+		TokenType::LEFTSHIFT => "<<",
+		TokenType::RIGHTSHIFT => ">>",
+		TokenType::ASSIGN => "=",
+		TokenType::ADDASSIGN => "+=",
+		TokenType::SUBASSIGN => "-=",
+		TokenType::STARASSIGN => "*=",
+		TokenType::DIVASSIGN => "/=",
+		TokenType::MODASSIGN => "%=",
+		TokenType::LEFTSHIFTASSIGN => "<<=",
+		TokenType::RIGHTSHIFTASSIGN => ">>=",
+		TokenType::BITANDASSIGN => "&=",
+		TokenType::BITORASSIGN => "|=",
+		TokenType::CARROTASSIGN => "^=",
+		_ => "?",
+	}
+}
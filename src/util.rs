@@ -1,10 +1,16 @@
+use std::fmt::Display;
 use std::io::Write;
 
+use crate::lexer::Span;
+
 pub struct ApolloError {
 	message: String,
 	index: Option<usize>,
 	additional_info: Option<String>,
 	additional_data: Option<usize>,
+	position: Option<(usize, usize)>, // line, column
+	span: Option<Box<Span>>,          // source range the error covers, set for syntax errors
+	warning: bool,                    // warnings are reported but don't stop compilation
 }
 
 impl ApolloError {
@@ -19,21 +25,86 @@ impl ApolloError {
 			index,
 			additional_info,
 			additional_data,
+			position: None,
+			span: None,
+			warning: false,
 		}
 	}
 
-	pub fn to_string(&self) -> String {
-		let mut error_message = format!("{}Error: {}{}", ERR, MSG, self.message);
-		if let Some(index) = self.index {
-			error_message.push_str(&format!("{} at index {}{}", ERR, INFO, index));
+	/// error located at a line and column in the source file
+	pub fn at(message: String, line: usize, column: usize) -> Self {
+		ApolloError {
+			message,
+			index: None,
+			additional_info: None,
+			additional_data: None,
+			position: Some((line, column)),
+			span: None,
+			warning: false,
+		}
+	}
+
+	/// error covering a range of the source file, located at its start
+	pub fn spanning(message: String, span: Span) -> Self {
+		ApolloError {
+			span: Some(Box::new(span)),
+			..Self::at(message, span.start_line, span.start_column)
 		}
-		error_message.push_str(RESET);
-		// additional data and info currently unused
-		error_message
+	}
+
+	/// warning located at a line and column in the source file
+	pub fn warning(message: String, line: usize, column: usize) -> Self {
+		ApolloError {
+			warning: true,
+			..Self::at(message, line, column)
+		}
+	}
+
+	pub fn is_warning(&self) -> bool {
+		self.warning
+	}
+
+	pub fn message(&self) -> &str {
+		&self.message
+	}
+
+	pub fn position(&self) -> Option<(usize, usize)> {
+		self.position
+	}
+
+	pub fn span(&self) -> Option<Span> {
+		self.span.as_deref().copied()
+	}
+
+	pub fn additional_data(&self) -> Option<usize> {
+		self.additional_data
 	}
 
 	pub fn print(&self) {
-		eprintln!("{}", self.to_string());
+		eprintln!("{self}");
+	}
+}
+
+impl Display for ApolloError {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		if self.warning {
+			write!(f, "{INFO}Warning: {MSG}{}", self.message)?;
+		} else {
+			write!(f, "{ERR}Error: {MSG}{}", self.message)?;
+		}
+		if let Some(index) = self.index {
+			write!(f, "{ERR} at index {INFO}{index}")?;
+		}
+		if let Some((line, column)) = self.position {
+			write!(
+				f,
+				"{ERR} Located @ Line: {INFO}{line}{ERR}, Column: {INFO}{column}{ERR}."
+			)?;
+		}
+		if let Some(info) = &self.additional_info {
+			write!(f, "\n\t{MSG}{info}")?;
+		}
+		write!(f, "{RESET}")
 	}
 }
 
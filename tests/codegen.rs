@@ -0,0 +1,69 @@
+use std::path::Path;
+use std::process::Command;
+
+use apollo::compiler::Compiler;
+use apollo::lexer::Lexer;
+use apollo::parser::Parser;
+use apollo::toolchain::Toolchain;
+
+/// generates C for `source`, builds it with the system compiler and returns what it prints
+fn run(name: &str, source: &str) -> String {
+	let output_dir = Path::new(env!("CARGO_TARGET_TMPDIR")).join("codegen");
+	std::fs::create_dir_all(&output_dir).unwrap();
+	let output_dir = output_dir.to_string_lossy().to_string();
+
+	let lexer = Lexer::from_source(source, &format!("{name}.sun"));
+	let program = match Parser::from_lexer(lexer, 0, false, output_dir.clone()).begin() {
+		Ok(program) => program,
+		Err(errors) => panic!("{name}: {}", errors[0].message()),
+	};
+	let c = Compiler::new(&program, 0, false, output_dir.clone())
+		.begin()
+		.unwrap_or_else(|e| panic!("{name}: {}", e.message()));
+	let toolchain = Toolchain::find(None, 0, false, output_dir)
+		.unwrap_or_else(|e| panic!("{name}: {}", e.message()));
+	let (executable, _) = toolchain
+		.build(&c)
+		.unwrap_or_else(|errors| panic!("{name}: {}", errors[0].message()));
+	let output = Command::new(executable).output().unwrap();
+	assert!(
+		output.status.success(),
+		"{name} exited with {}",
+		output.status
+	);
+	String::from_utf8(output.stdout).unwrap()
+}
+
+#[test]
+fn retry_reruns_the_guarded_block_until_it_succeeds() {
+	let source = "#[entry]
+fn main() -> Unit {
+	var attempts: u8 = 0;
+	{
+		attempts += 1;
+		println(\"attempt {attempts}\");
+		if(attempts < 3) { panic new IllegalArgument(\"too early\"); }
+	} catch(IllegalArgument e) { retry; }
+	println(\"done after {attempts}\");
+}
+";
+	assert_eq!(
+		run("retry", source),
+		"attempt 1\nattempt 2\nattempt 3\ndone after 3\n"
+	);
+}
+
+#[test]
+fn handlers_run_once_without_retry() {
+	let source = "#[entry]
+fn main() -> Unit {
+	{
+		println(\"guarded\");
+		panic new IllegalArgument(\"failed\");
+		println(\"unreachable\");
+	} catch(IllegalArgument e) { println(\"handled\"); }
+	println(\"after\");
+}
+";
+	assert_eq!(run("handled", source), "guarded\nhandled\nafter\n");
+}
@@ -0,0 +1,71 @@
+use std::path::Path;
+
+use apollo::cst::SyntaxTree;
+use apollo::lexer::Lexer;
+
+fn sources(dir: &Path, found: &mut Vec<std::path::PathBuf>) {
+	for entry in std::fs::read_dir(dir).unwrap() {
+		let path = entry.unwrap().path();
+		if path.is_dir() {
+			sources(&path, found);
+		} else if path.extension().is_some_and(|e| e == "sun") {
+			found.push(path);
+		}
+	}
+}
+
+#[test]
+fn examples_reprint_exactly() {
+	let mut found = Vec::new();
+	sources(Path::new("ex"), &mut found);
+	assert!(!found.is_empty());
+	for path in found {
+		let source = std::fs::read_to_string(&path).unwrap();
+		let tree = SyntaxTree::new(Lexer::from_source(&source, &path.to_string_lossy()));
+		assert!(
+			tree.to_string() == source,
+			"{} did not reprint unchanged",
+			path.display()
+		);
+	}
+}
+
+/// unterminated constructs and rejected text still come back byte for byte
+#[test]
+fn broken_sources_reprint_exactly() {
+	for source in [
+		"fn main() {\r\n\tprintln(\"hi\" // no close\n",
+		"/* outer /* inner */ still open",
+		"var x = 0xZZ @ 12fxboa5;\t\n\"unterminated",
+		"#[link: a",
+		"}) ] fn",
+		"",
+	] {
+		let tree = SyntaxTree::new(Lexer::from_source(source, "broken.sun"));
+		assert_eq!(tree.to_string(), source);
+	}
+}
+
+#[test]
+fn same_line_comments_trail_their_token() {
+	let tree = SyntaxTree::new(Lexer::from_source(
+		"var x = 1; // one\n// two\nvar y = 2;",
+		"trivia.sun",
+	));
+	let text = |t: &[apollo::cst::Trivia]| t.iter().map(|t| t.text.as_str()).collect::<String>();
+	let tokens: Vec<_> = tree
+		.nodes
+		.iter()
+		.map(|n| match n {
+			apollo::cst::CstNode::Token(t) => t,
+			apollo::cst::CstNode::Group { open, .. } => open,
+		})
+		.collect();
+	let semicolon = tokens.iter().find(|t| t.text == ";").unwrap();
+	assert_eq!(text(&semicolon.trailing), " // one");
+	let next = tokens
+		.iter()
+		.find(|t| t.text == "var" && !t.leading.is_empty())
+		.unwrap();
+	assert_eq!(text(&next.leading), "\n// two\n");
+}
@@ -0,0 +1,216 @@
+use apollo::lexer::Lexer;
+use apollo::parser::Parser;
+use apollo::resolver::Resolver;
+use apollo::semantic::Analyzer;
+use apollo::typechecker::TypeChecker;
+
+/// every diagnostic the checking passes report for `source`, as `line:column: message`
+fn diagnostics(source: &str) -> Vec<String> {
+	let lexer = Lexer::from_source(source, "test.sun");
+	let (program, mut diagnostics) = Parser::from_lexer(lexer, 0, false, String::new()).parse();
+	if diagnostics.is_empty() {
+		diagnostics.extend(Resolver::new(&program, 0, false, String::new()).begin());
+		diagnostics.extend(Analyzer::new(&program, 0, false, String::new()).begin());
+	}
+	if diagnostics.iter().all(|d| d.is_warning()) {
+		diagnostics.extend(TypeChecker::new(&program, 0, false, String::new()).begin());
+	}
+	diagnostics
+		.iter()
+		.map(|d| {
+			let (line, column) = d.position().unwrap_or((0, 0));
+			format!("{line}:{column}: {}", d.message())
+		})
+		.collect()
+}
+
+const SUITS: &str = "enum SUITS { DIAMONDS, CLUBS, SPADES, HEARTS }\n";
+
+#[test]
+fn matches_over_enums_must_cover_every_variant() {
+	let source = format!(
+		"{SUITS}fn name(suit: SUITS) -> u8 {{\n\tmatch suit {{\n\t\tDIAMONDS -> return 1;\n\t\tSUITS.CLUBS -> return 2;\n\t}}\n\treturn 0;\n}}\n"
+	);
+	assert_eq!(
+		diagnostics(&source),
+		["3:2: Non-exhaustive match over SUITS, missing: SPADES, HEARTS"]
+	);
+}
+
+#[test]
+fn wildcards_make_matches_exhaustive() {
+	let source = format!(
+		"{SUITS}fn name(suit: SUITS) -> u8 {{\n\tmatch suit {{\n\t\tDIAMONDS -> return 1;\n\t\t_ -> return 2;\n\t}}\n\treturn 0;\n}}\n"
+	);
+	assert_eq!(diagnostics(&source), Vec::<String>::new());
+}
+
+#[test]
+fn arms_after_a_wildcard_are_unreachable() {
+	let source = "fn name(c: char) -> u8 {\n\tmatch c {\n\t\t_ -> return 1;\n\t\t'a' -> return 2;\n\t}\n\treturn 0;\n}\n";
+	assert_eq!(
+		diagnostics(source),
+		["4:3: Unreachable match arm, the '_' arm on line 3 already matches everything"]
+	);
+}
+
+#[test]
+fn retry_is_allowed_in_catch_handlers() {
+	let source = "fn f() {\n\t{ f(); } catch(IllegalArgument e) { retry; }\n}\n";
+	assert_eq!(diagnostics(source), Vec::<String>::new());
+}
+
+#[test]
+fn retry_outside_a_catch_handler_is_an_error() {
+	let source = "fn f() {\n\t{ retry; } catch(IllegalArgument e) {}\n}\n";
+	assert_eq!(
+		diagnostics(source),
+		["2:4: 'retry' can only be used inside of a catch handler"]
+	);
+}
+
+#[test]
+fn retry_does_not_reach_through_closures() {
+	let source = "fn f(xs: [u8]) {\n\t{ f(xs); } catch(IllegalArgument e) {\n\t\txs.foreach { |x: u8| retry; }\n\t}\n}\n";
+	assert_eq!(
+		diagnostics(source),
+		["3:24: 'retry' can only be used inside of a catch handler"]
+	);
+}
+
+#[test]
+fn caught_types_must_derive_from_error() {
+	let source = "class Foo {}\nfn f() {\n\t{ f(); } catch(Foo e) {}\n}\n";
+	assert_eq!(
+		diagnostics(source),
+		["3:2: Foo cannot be caught because it does not derive from Error"]
+	);
+}
+
+#[test]
+fn locals_are_used_before_their_declaration() {
+	let source = "fn f() -> u8 {\n\tvar a: u8 = b;\n\tvar b: u8 = 1;\n\treturn a;\n}\n";
+	assert_eq!(
+		diagnostics(source),
+		["2:14: b is used before its declaration on line 3"]
+	);
+}
+
+#[test]
+fn constants_only_see_constants_declared_above() {
+	let source = "const A: u8 = B;\nconst B: u8 = 1;\n";
+	assert_eq!(
+		diagnostics(source),
+		["1:15: B is used before its declaration on line 2"]
+	);
+}
+
+#[test]
+fn functions_can_call_functions_declared_below() {
+	let source = "fn f() { g(); }\nfn g() {}\n";
+	assert_eq!(diagnostics(source), Vec::<String>::new());
+}
+
+#[test]
+fn unknown_names_are_undefined() {
+	let source = "fn f() { g(); }\n";
+	assert_eq!(diagnostics(source), ["1:10: Undefined identifier g"]);
+}
+
+#[test]
+fn self_outside_a_class_is_an_error() {
+	let source = "fn f() -> u8 {\n\treturn self.value;\n}\n";
+	assert_eq!(
+		diagnostics(source),
+		["2:9: 'self' can only be used inside of a class or extend block"]
+	);
+}
+
+#[test]
+fn self_inside_a_class_resolves() {
+	let source = "class Player(health: u8) {\n\tfn hurt() { self.health -= 1; }\n}\n";
+	assert_eq!(diagnostics(source), Vec::<String>::new());
+}
+
+#[test]
+fn integer_literals_must_fit_their_type() {
+	let source = "fn f() {\n\tvar a: u8 = 255;\n\tvar b: u8 = 256;\n\tvar c: u8 = -1;\n\tvar d: i8 = -128;\n\tvar e = 128i8;\n}\n";
+	assert_eq!(
+		diagnostics(source),
+		[
+			"3:14: 256 is out of range for u8",
+			"4:14: -1 is out of range for u8",
+			"6:10: 128i8 is out of range for i8",
+		]
+	);
+}
+
+#[test]
+fn calls_need_the_declared_number_of_arguments() {
+	let source = "fn add(a: u8, b: u8) -> u8 { return a + b; }\nfn f() {\n\tadd(1);\n\tadd(1, 2, 3);\n\tadd(1, 2);\n}\n";
+	assert_eq!(
+		diagnostics(source),
+		[
+			"3:2: add takes 2 arguments, but 1 was given",
+			"4:2: add takes 2 arguments, but 3 were given",
+		]
+	);
+}
+
+#[test]
+fn array_literals_must_match_the_declared_length() {
+	let source = "fn f() {\n\tvar a: [u8; 3] = {1, 2};\n\tvar b: [u8; 2] = {1, 2};\n}\n";
+	assert_eq!(
+		diagnostics(source),
+		["2:19: Expected an array of 3 elements, found 2"]
+	);
+}
+
+#[test]
+fn known_annotations_in_the_right_place_are_quiet() {
+	let source = "#[extern: \"puts\"]\nfn puts(s: string);\n#[entry]\nfn main() {}\n";
+	assert_eq!(diagnostics(source), Vec::<String>::new());
+}
+
+#[test]
+fn unknown_annotations_are_warnings() {
+	let source = "#[inline]\nfn f() {}\n";
+	assert_eq!(diagnostics(source), ["1:1: Unknown annotation #[inline]"]);
+}
+
+#[test]
+fn misplaced_annotations_are_warnings() {
+	let source = "#[entry]\nclass Foo {}\nclass Bar {\n\t#[entry]\n\tfn f() {}\n}\n";
+	assert_eq!(
+		diagnostics(source),
+		[
+			"1:1: #[entry] has no effect on a class, it belongs on a fn",
+			"4:2: #[entry] has no effect on a method, it belongs on a fn",
+		]
+	);
+}
+
+#[test]
+fn annotation_arguments_must_match_the_registry() {
+	let source = "#[extern]\nfn a();\n#[extern: puts]\nfn b();\n#[entry: 1, true]\nfn main() {}\n";
+	assert_eq!(
+		diagnostics(source),
+		[
+			"1:1: #[extern] takes (string) but was given ()",
+			"3:1: #[extern] takes (string) but was given (name)",
+			"5:1: #[entry] takes () but was given (number, bool)",
+		]
+	);
+}
+
+#[test]
+fn only_fields_and_functions_belong_in_class_and_extend_blocks() {
+	let source = "class Foo {\n\tenum E { A }\n\tfn f() {}\n}\nextend u8 {\n\tvar x: u8 = 1;\n}\n";
+	assert_eq!(
+		diagnostics(source),
+		[
+			"2:2: This declaration is not allowed inside a class block",
+			"6:2: This declaration is not allowed inside an extend block",
+		]
+	);
+}
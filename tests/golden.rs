@@ -0,0 +1,160 @@
+//! Snapshot tests over every `.sun` file in `ex/`, the token stream, AST dump, diagnostics and
+//! generated C of each file are compared against `tests/golden/<path>.<stage>`.
+//! Run with `BLESS=1` to write new snapshots after an intended change.
+
+use std::path::{Path, PathBuf};
+
+use apollo::compiler::Compiler;
+use apollo::lexer::Lexer;
+use apollo::parser::Parser;
+use apollo::resolver::Resolver;
+use apollo::semantic::Analyzer;
+use apollo::typechecker::TypeChecker;
+use apollo::util::ApolloError;
+
+fn sources(dir: &Path, found: &mut Vec<PathBuf>) {
+	for entry in std::fs::read_dir(dir).unwrap() {
+		let path = entry.unwrap().path();
+		if path.is_dir() {
+			sources(&path, found);
+		} else if path.extension().is_some_and(|e| e == "sun") {
+			found.push(path);
+		}
+	}
+}
+
+fn tokens(source: &str, name: &str) -> String {
+	let mut out = String::new();
+	for token in Lexer::from_source(source, name) {
+		match token {
+			Ok(token) => {
+				let span = token.span;
+				out.push_str(&format!(
+					"{}:{}-{}:{} {} {:?}\n",
+					span.start_line,
+					span.start_column,
+					span.end_line,
+					span.end_column,
+					token.token_type,
+					token.value
+				));
+			}
+			Err(error) => out.push_str(&diagnostic(&error)),
+		}
+	}
+	out
+}
+
+fn diagnostic(error: &ApolloError) -> String {
+	let severity = if error.is_warning() {
+		"warning"
+	} else {
+		"error"
+	};
+	match error.position() {
+		Some((line, column)) => format!("{severity} {line}:{column}: {}\n", error.message()),
+		None => format!("{severity}: {}\n", error.message()),
+	}
+}
+
+/// runs the stages the way `apollo build` does and keeps the output of each one that ran
+fn snapshots(path: &Path) -> Vec<(&'static str, Option<String>)> {
+	let name = path.to_string_lossy().replace('\\', "/");
+	let source = std::fs::read_to_string(path).unwrap();
+	let output_dir = Path::new(env!("CARGO_TARGET_TMPDIR")).join("golden");
+	std::fs::create_dir_all(&output_dir).unwrap();
+	let output_dir = output_dir.to_string_lossy().to_string();
+
+	let lexer = Lexer::from_source(&source, &name);
+	let (program, mut diagnostics) = Parser::from_lexer(lexer, 0, false, output_dir.clone()).parse();
+	let ast = format!("{:#?}\n", program.items);
+
+	let mut c = None;
+	if diagnostics.is_empty() {
+		diagnostics.extend(Resolver::new(&program, 0, false, output_dir.clone()).begin());
+		diagnostics.extend(Analyzer::new(&program, 0, false, output_dir.clone()).begin());
+	}
+	if diagnostics.iter().all(|d| d.is_warning()) {
+		diagnostics.extend(TypeChecker::new(&program, 0, false, output_dir.clone()).begin());
+	}
+	if diagnostics.iter().all(|d| d.is_warning()) {
+		match Compiler::new(&program, 0, false, output_dir).begin() {
+			Ok(path) => c = Some(std::fs::read_to_string(path).unwrap()),
+			Err(error) => diagnostics.push(error),
+		}
+	}
+
+	vec![
+		("tokens", Some(tokens(&source, &name))),
+		("ast", Some(ast)),
+		(
+			"diagnostics",
+			Some(diagnostics.iter().map(diagnostic).collect()),
+		),
+		("c", c),
+	]
+}
+
+/// where the line of `actual` first differs from `expected`
+fn first_difference(expected: &str, actual: &str) -> String {
+	let mut expected_lines = expected.lines();
+	let mut actual_lines = actual.lines();
+	for line in 1.. {
+		match (expected_lines.next(), actual_lines.next()) {
+			(Some(e), Some(a)) if e == a => continue,
+			(e, a) => {
+				return format!(
+					"line {line}\n\texpected: {}\n\t  actual: {}",
+					e.unwrap_or("<end of file>"),
+					a.unwrap_or("<end of file>")
+				);
+			}
+		}
+	}
+	unreachable!()
+}
+
+#[test]
+fn examples_match_snapshots() {
+	let bless = std::env::var_os("BLESS").is_some();
+	let mut files = Vec::new();
+	sources(Path::new("ex"), &mut files);
+	files.sort();
+	assert!(!files.is_empty());
+
+	let mut failures = Vec::new();
+	for file in &files {
+		let relative = file.strip_prefix("ex").unwrap();
+		for (stage, actual) in snapshots(file) {
+			let snapshot = Path::new("tests/golden")
+				.join(relative)
+				.with_extension(stage);
+			let expected = std::fs::read_to_string(&snapshot).ok();
+			if expected == actual {
+				continue;
+			}
+			if bless {
+				match &actual {
+					Some(actual) => {
+						std::fs::create_dir_all(snapshot.parent().unwrap()).unwrap();
+						std::fs::write(&snapshot, actual).unwrap();
+					}
+					None => std::fs::remove_file(&snapshot).unwrap(),
+				}
+				continue;
+			}
+			let reason = match (&expected, &actual) {
+				(None, _) => "no snapshot was written yet".to_string(),
+				(_, None) => "the stage no longer runs".to_string(),
+				(Some(expected), Some(actual)) => first_difference(expected, actual),
+			};
+			failures.push(format!("{}: {reason}", snapshot.display()));
+		}
+	}
+	assert!(
+		failures.is_empty(),
+		"{} snapshot(s) differ, rerun with BLESS=1 if the change is intended\n{}",
+		failures.len(),
+		failures.join("\n")
+	);
+}
@@ -0,0 +1,240 @@
+/* generated by the Apollo compiler from ex/Hello World/hello.sun */
+#include <ctype.h>
+#include <math.h>
+#include <setjmp.h>
+#include <stdarg.h>
+#include <stdbool.h>
+#include <stddef.h>
+#include <stdint.h>
+#include <stdio.h>
+#include <stdlib.h>
+#include <string.h>
+#include <time.h>
+
+enum {
+	APOLLO_Error,
+	APOLLO_IllegalArgument,
+	APOLLO_IllegalState,
+	APOLLO_IndexOutOfBounds,
+};
+
+static inline int apollo_error_parent(int type) {
+	static const int parents[] = { -1, 0, 0, 0 };
+	return parents[type];
+}
+
+static inline const char* apollo_error_name(int type) {
+	static const char* const names[] = { "Error", "IllegalArgument", "IllegalState", "IndexOutOfBounds" };
+	return names[type];
+}
+
+typedef struct {
+	char* data;
+	size_t len;
+} slice_char;
+
+typedef struct {
+	int type;
+	const char* message;
+} apollo_error;
+
+typedef struct apollo_handler {
+	jmp_buf env;
+	struct apollo_handler* prev;
+} apollo_handler;
+
+static apollo_handler* apollo_handlers = NULL;
+static apollo_error apollo_current_error;
+
+static inline apollo_error apollo_make_error(int type, const char* message) {
+	apollo_error error = { type, message };
+	return error;
+}
+
+static inline _Noreturn void apollo_throw_error(apollo_error error) {
+	apollo_current_error = error;
+	if (apollo_handlers == NULL) {
+		fprintf(stderr, "Uncaught %s: %s\n", apollo_error_name(error.type), error.message);
+		exit(1);
+	}
+	longjmp(apollo_handlers->env, 1);
+}
+
+static inline _Noreturn void apollo_throw(int type, const char* message) {
+	apollo_throw_error(apollo_make_error(type, message));
+}
+
+static inline bool apollo_error_is(int type, int ancestor) {
+	for (; type >= 0; type = apollo_error_parent(type)) {
+		if (type == ancestor) {
+			return true;
+		}
+	}
+	return false;
+}
+
+static inline char* apollo_format(const char* format, ...) {
+	va_list args;
+	va_start(args, format);
+	int length = vsnprintf(NULL, 0, format, args);
+	va_end(args);
+	char* result = malloc((size_t)length + 1);
+	va_start(args, format);
+	vsnprintf(result, (size_t)length + 1, format, args);
+	va_end(args);
+	return result;
+}
+
+static inline size_t apollo_check_index(int64_t index, size_t length) {
+	if (index < 0 || (uint64_t)index >= length) {
+		apollo_throw(APOLLO_IndexOutOfBounds, apollo_format("Index %lld is out of bounds for a length of %zu", (long long)index, length));
+	}
+	return (size_t)index;
+}
+
+static inline char* apollo_string_new(size_t length) {
+	return calloc(length + 1, 1);
+}
+
+static inline char* apollo_string_copy(const char* text, size_t length) {
+	char* result = apollo_string_new(length);
+	memcpy(result, text, length);
+	return result;
+}
+
+static inline char* apollo_string_concat(const char* left, const char* right) {
+	size_t left_length = strlen(left);
+	size_t right_length = strlen(right);
+	char* result = apollo_string_new(left_length + right_length);
+	memcpy(result, left, left_length);
+	memcpy(result + left_length, right, right_length);
+	return result;
+}
+
+static inline char* apollo_string_truncate(const char* text, int64_t length) {
+	size_t current = strlen(text);
+	size_t limit = length < 0 ? 0 : (size_t)length;
+	return apollo_string_copy(text, current < limit ? current : limit);
+}
+
+static inline char* apollo_string_strip(const char* text) {
+	size_t start = 0;
+	size_t end = strlen(text);
+	while (start < end && isspace((unsigned char)text[start])) {
+		start++;
+	}
+	while (end > start && isspace((unsigned char)text[end - 1])) {
+		end--;
+	}
+	return apollo_string_copy(text + start, end - start);
+}
+
+static inline char* apollo_string_lowercase(const char* text) {
+	char* result = apollo_string_copy(text, strlen(text));
+	for (char* c = result; *c != '\0'; c++) {
+		*c = (char)tolower((unsigned char)*c);
+	}
+	return result;
+}
+
+static inline slice_char apollo_string_slice(const char* text) {
+	slice_char slice = { apollo_string_copy(text, strlen(text)), strlen(text) };
+	return slice;
+}
+
+static inline char* apollo_string_at(char* text, int64_t index) {
+	return &text[apollo_check_index(index, strlen(text))];
+}
+
+static inline char* slice_char_at(slice_char slice, int64_t index) {
+	return &slice.data[apollo_check_index(index, slice.len)];
+}
+
+static inline uint32_t apollo_string_parse_int(const char* text) {
+	uint32_t value = 0;
+	if (*text == '\0') {
+		apollo_throw(APOLLO_IllegalArgument, "Cannot parse an empty string into an integer");
+	}
+	for (const char* c = text; *c != '\0'; c++) {
+		if (!isdigit((unsigned char)*c)) {
+			apollo_throw(APOLLO_IllegalArgument, apollo_format("\"%s\" is not an integer", text));
+		}
+		value = value * 10 + (uint32_t)(*c - '0');
+	}
+	return value;
+}
+
+static inline char* apollo_read_line(void) {
+	size_t length = 0;
+	size_t capacity = 64;
+	char* line = malloc(capacity);
+	int c;
+	while ((c = getchar()) != EOF && c != '\n') {
+		if (length + 1 == capacity) {
+			capacity *= 2;
+			line = realloc(line, capacity);
+		}
+		line[length++] = (char)c;
+	}
+	line[length] = '\0';
+	return line;
+}
+
+static inline char* apollo_prompt(const char* message) {
+	fputs(message, stdout);
+	fflush(stdout);
+	return apollo_read_line();
+}
+
+static inline char* apollo_char_to_string(char c) {
+	char* result = apollo_string_new(1);
+	result[0] = c;
+	return result;
+}
+
+static inline char* apollo_int_to_string(long long value) {
+	return apollo_format("%lld", value);
+}
+
+static inline char* apollo_uint_to_string(unsigned long long value) {
+	return apollo_format("%llu", value);
+}
+
+static inline char* apollo_float_to_string(double value) {
+	return apollo_format("%g", value);
+}
+
+static inline bool apollo_char_is_digit(char c) {
+	return isdigit((unsigned char)c) != 0;
+}
+
+static inline int64_t apollo_clamp(int64_t value, int64_t low, int64_t high) {
+	return value < low ? low : value > high ? high : value;
+}
+
+static inline int64_t apollo_random(int64_t low, int64_t high) {
+	if (high <= low) {
+		return low;
+	}
+	return low + rand() % (high - low + 1);
+}
+
+static inline void apollo_shuffle(void* data, size_t length, size_t size) {
+	unsigned char* bytes = data;
+	unsigned char* temp = malloc(size);
+	for (size_t i = length; i > 1; i--) {
+		size_t j = (size_t)rand() % i;
+		memcpy(temp, bytes + (i - 1) * size, size);
+		memcpy(bytes + (i - 1) * size, bytes + j * size, size);
+		memcpy(bytes + j * size, temp, size);
+	}
+	free(temp);
+}
+
+
+
+int main(void) {
+	srand((unsigned)time(NULL));
+	fputs("Hello World!", stdout);
+	return 0;
+}
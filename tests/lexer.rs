@@ -0,0 +1,336 @@
+use apollo::lexer::{KEYWORDS, Lexer, Metadata, NumberValue, Span, TokenType};
+
+/// the significant tokens of `source` as (type, value), without line breaks and the final `EOF`
+fn tokens(source: &str) -> Vec<(TokenType, String)> {
+	Lexer::from_source(source, "test.sun")
+		.map(|token| token.unwrap_or_else(|e| panic!("{source}: {}", e.message())))
+		.filter(|token| !matches!(token.token_type, TokenType::NEWLINE | TokenType::EOF))
+		.map(|token| (token.token_type, token.value))
+		.collect()
+}
+
+#[test]
+fn every_keyword_has_its_own_token() {
+	for (word, token_type) in KEYWORDS {
+		assert_eq!(tokens(word), [(*token_type, word.to_string())], "{word}");
+		assert!(token_type.is_keyword());
+	}
+}
+
+#[test]
+fn keywords_inside_longer_words_are_identifiers() {
+	for word in [
+		"fnord", "classy", "iffy", "returns", "self_", "_new", "Units", "asap",
+	] {
+		assert_eq!(tokens(word), [(TokenType::IDENTIFIER, word.to_string())]);
+	}
+}
+
+#[test]
+fn keywords_are_case_sensitive() {
+	assert_eq!(
+		tokens("Fn unit TRUE"),
+		[
+			(TokenType::IDENTIFIER, "Fn".to_string()),
+			(TokenType::IDENTIFIER, "unit".to_string()),
+			(TokenType::IDENTIFIER, "TRUE".to_string()),
+		]
+	);
+}
+
+/// the errors the lexer reports for `source` as `line:column: message`
+fn errors(source: &str) -> Vec<String> {
+	Lexer::from_source(source, "test.sun")
+		.filter_map(Result::err)
+		.map(|e| {
+			let (line, column) = e.position().unwrap_or((0, 0));
+			format!("{line}:{column}: {}", e.message())
+		})
+		.collect()
+}
+
+#[test]
+fn errors_inside_interpolations_point_at_themselves() {
+	assert_eq!(
+		errors("var s = \"a {'ab'} b {x $ y}\";\nvar t = 1;"),
+		["1:13: Invalid character literal 'ab', expected a single character"]
+	);
+	assert_eq!(errors("\"{x $ y}\""), ["1:5: Unknown character '$'"]);
+	// the rest of the string is still skipped, so lexing carries on after it
+	let after: Vec<_> = Lexer::from_source("\"{'ab'} text\" x", "test.sun")
+		.skip(1)
+		.map(|token| token.map(|token| token.token_type).ok())
+		.collect();
+	assert_eq!(after, [Some(TokenType::IDENTIFIER), Some(TokenType::EOF)]);
+}
+
+#[test]
+fn escapes_decode_in_strings_and_characters() {
+	assert_eq!(
+		tokens(r#""a\n\t\r\0\\\'\"\{\}b""#),
+		[(TokenType::STRING, "a\n\t\r\0\\'\"{}b".to_string())]
+	);
+	assert_eq!(
+		tokens(r"'\n' '\'' '\x41' '\u{7E}'"),
+		[
+			(TokenType::CHARACTER, "\n".to_string()),
+			(TokenType::CHARACTER, "'".to_string()),
+			(TokenType::CHARACTER, "A".to_string()),
+			(TokenType::CHARACTER, "~".to_string()),
+		]
+	);
+	// strings are UTF-8, so they take any unicode escape
+	assert_eq!(
+		tokens(r#""\x7F\u{1F600}""#),
+		[(TokenType::STRING, "\x7F\u{1F600}".to_string())]
+	);
+}
+
+#[test]
+fn characters_are_a_single_ascii_byte() {
+	assert_eq!(
+		errors(r"x = '\u{e9}';"),
+		["1:5: 'é' does not fit in a char, only ASCII characters are supported"]
+	);
+	assert_eq!(
+		errors("x = '😀';"),
+		["1:5: '😀' does not fit in a char, only ASCII characters are supported"]
+	);
+}
+
+#[test]
+fn invalid_escapes_point_at_the_backslash() {
+	assert_eq!(errors(r#""ab\q""#), ["1:4: Unknown escape sequence '\\q'"]);
+	assert_eq!(
+		errors(r"'\x4'"),
+		["1:2: Expected two hexadecimal digits after '\\x', found '\\x4'"]
+	);
+	assert_eq!(
+		errors(r"'\x80'"),
+		["1:2: Escape sequence '\\x80' is out of range, '\\x' only goes up to 7F"]
+	);
+	assert_eq!(errors(r"'\u41'"), ["1:2: Expected '{' after '\\u'"]);
+	assert_eq!(
+		errors(r"'\u{41'"),
+		["1:2: Expected '}' to close the unicode escape '\\u{41'"]
+	);
+	assert_eq!(
+		errors(r"'\u{}'"),
+		["1:2: Unicode escape '\\u{}' needs 1 to 6 hexadecimal digits"]
+	);
+	assert_eq!(
+		errors(r"'\u{D800}'"),
+		["1:2: '\\u{D800}' is not a valid unicode character"]
+	);
+	// only the first invalid escape in a string is reported
+	assert_eq!(errors(r#""\q\w""#), ["1:2: Unknown escape sequence '\\q'"]);
+}
+
+#[test]
+fn errors_inside_annotations_point_at_themselves() {
+	assert_eq!(
+		errors("#[extern(\"puts\" $)]\nfn puts();"),
+		["1:17: Unknown character '$'"]
+	);
+	assert_eq!(
+		errors("#[doc('ab')]"),
+		["1:7: Invalid character literal 'ab', expected a single character"]
+	);
+	assert_eq!(
+		errors("#[entry\nfn main() {}"),
+		["1:1: Unterminated annotation, expected ']'"]
+	);
+}
+
+/// the type, value and suffix of the single number in `source`
+fn number(source: &str) -> (TokenType, NumberValue, Option<String>) {
+	let token = Lexer::from_source(source, "test.sun")
+		.next()
+		.unwrap()
+		.unwrap_or_else(|e| panic!("{source}: {}", e.message()));
+	match token.metadata.as_slice() {
+		[Metadata::Number { value, suffix }] => (token.token_type, *value, suffix.clone()),
+		_ => panic!("{source}: expected a number, found {:?}", token.token_type),
+	}
+}
+
+#[test]
+fn separators_are_ignored_in_every_radix() {
+	use NumberValue::Integer;
+	assert_eq!(
+		number("1_000_000"),
+		(TokenType::NUMBER, Integer(1_000_000), None)
+	);
+	assert_eq!(
+		number("0xFF_FF"),
+		(TokenType::HEXADECIMAL, Integer(0xFFFF), None)
+	);
+	assert_eq!(number("0o7_7"), (TokenType::OCTAL, Integer(0o77), None));
+	assert_eq!(
+		number("0b1010_1010"),
+		(TokenType::BINARY, Integer(0b1010_1010), None)
+	);
+	assert_eq!(
+		number("1_0.2_5"),
+		(TokenType::FLOAT, NumberValue::Float(10.25), None)
+	);
+}
+
+#[test]
+fn exponents_make_floats() {
+	use NumberValue::Float;
+	assert_eq!(number("1e3"), (TokenType::FLOAT, Float(1000.0), None));
+	assert_eq!(number("1.5e-2"), (TokenType::FLOAT, Float(0.015), None));
+	assert_eq!(number("2E+2"), (TokenType::FLOAT, Float(200.0), None));
+	// without digits after it the `e` is a suffix, not an exponent
+	assert_eq!(
+		errors("1e"),
+		["1:1: Invalid number '1e', 'e' is not a numeric suffix"]
+	);
+}
+
+#[test]
+fn suffixes_pick_the_width() {
+	let suffix = |source| number(source).2;
+	assert_eq!(suffix("255u8"), Some("u8".to_string()));
+	assert_eq!(suffix("0xFFi64"), Some("i64".to_string()));
+	assert_eq!(suffix("1_000_u32"), Some("u32".to_string()));
+	assert_eq!(
+		number("2f32"),
+		(
+			TokenType::FLOAT,
+			NumberValue::Float(2.0),
+			Some("f32".to_string())
+		)
+	);
+	// the legacy `f` only makes a float
+	assert_eq!(
+		number("2f"),
+		(TokenType::FLOAT, NumberValue::Float(2.0), None)
+	);
+	// a point followed by a name is a method call on an integer
+	assert_eq!(
+		tokens("1.max"),
+		[
+			(TokenType::NUMBER, "1".to_string()),
+			(TokenType::DOT, ".".to_string()),
+			(TokenType::IDENTIFIER, "max".to_string()),
+		]
+	);
+}
+
+#[test]
+fn invalid_numbers_are_reported_whole() {
+	assert_eq!(
+		errors("12fxboa5"),
+		["1:1: Invalid number '12fxboa5', 'fxboa5' is not a numeric suffix"]
+	);
+	assert_eq!(errors("0xZZ"), ["1:1: Invalid hexadecimal number '0xZZ'"]);
+	assert_eq!(
+		errors("0b"),
+		["1:1: Invalid binary number '0b', expected digits after '0b'"]
+	);
+	assert_eq!(errors("0o9"), ["1:1: Invalid octal number '0o9'"]);
+	assert_eq!(
+		errors("1.5u8"),
+		["1:1: Invalid float '1.5u8', 'u8' is an integer suffix"]
+	);
+	assert_eq!(
+		errors("18446744073709551616"),
+		["1:1: Number '18446744073709551616' does not fit in 64 bits"]
+	);
+}
+
+#[test]
+fn doc_comments_drop_one_leading_space() {
+	let comments: Vec<_> =
+		Lexer::from_source("//: Adds one.\n//:  indented\n//:\n// plain", "test.sun")
+			.filter_map(Result::ok)
+			.filter(|token| {
+				matches!(
+					token.token_type,
+					TokenType::DOCCOMMENT | TokenType::LINECOMMENT
+				)
+			})
+			.map(|token| (token.token_type, token.value))
+			.collect();
+	assert_eq!(
+		comments,
+		[
+			(TokenType::DOCCOMMENT, "Adds one.".to_string()),
+			(TokenType::DOCCOMMENT, " indented".to_string()),
+			(TokenType::DOCCOMMENT, String::new()),
+			(TokenType::LINECOMMENT, " plain".to_string()),
+		]
+	);
+}
+
+#[test]
+fn block_comments_nest() {
+	assert_eq!(
+		Lexer::from_source("a /* outer /* inner */ still */ b", "test.sun")
+			.filter_map(Result::ok)
+			.map(|token| (token.token_type, token.value))
+			.collect::<Vec<_>>()[..3],
+		[
+			(TokenType::IDENTIFIER, "a".to_string()),
+			(
+				TokenType::BLOCKCOMMENT,
+				" outer /* inner */ still ".to_string()
+			),
+			(TokenType::IDENTIFIER, "b".to_string()),
+		]
+	);
+}
+
+#[test]
+fn unterminated_literals_point_at_their_start() {
+	assert_eq!(
+		errors("a /* outer /* inner */\nb"),
+		["1:3: Unterminated block comment"]
+	);
+	assert_eq!(errors("x = \"abc\ny"), ["1:5: Unterminated string"]);
+	assert_eq!(errors("x = 'a"), ["1:5: Unterminated character literal"]);
+	assert_eq!(errors("\"{x\""), ["1:2: Unterminated string interpolation"]);
+}
+
+/// every token of `source` with its span, line breaks included
+fn spans(source: &str) -> Vec<(TokenType, Span)> {
+	Lexer::from_source(source, "test.sun")
+		.filter_map(Result::ok)
+		.map(|token| (token.token_type, token.span))
+		.collect()
+}
+
+#[test]
+fn spans_cover_multi_line_comments() {
+	let source = "a /* one\ntwo */ b";
+	let spans = spans(source);
+	let comment = spans[1].1;
+	assert_eq!(spans[1].0, TokenType::BLOCKCOMMENT);
+	assert_eq!(
+		(
+			comment.start_line,
+			comment.start_column,
+			comment.end_line,
+			comment.end_column
+		),
+		(1, 3, 2, 7)
+	);
+	assert_eq!(&source[comment.start..comment.end], "/* one\ntwo */");
+	let b = spans[2].1;
+	assert_eq!((b.start_line, b.start_column), (2, 8));
+}
+
+#[test]
+fn spans_count_columns_in_characters_and_offsets_in_bytes() {
+	let source = "\"héllo\" \"é\" x";
+	let spans = spans(source);
+	let (string, other, name) = (spans[0].1, spans[1].1, spans[2].1);
+	assert_eq!((string.start_column, string.end_column), (1, 8));
+	assert_eq!((string.start, string.end), (0, 8));
+	assert_eq!(&source[string.start..string.end], "\"héllo\"");
+	assert_eq!((other.start_column, other.end_column), (9, 12));
+	assert_eq!(&source[other.start..other.end], "\"é\"");
+	assert_eq!((name.start_column, name.start), (13, 14));
+}
@@ -0,0 +1,58 @@
+use std::time::{Duration, Instant};
+
+use apollo::lexer::Lexer;
+
+/// one function per block, with comments, annotations and non-ASCII text so multi-byte characters are exercised
+fn synthetic_source(blocks: usize) -> String {
+	let mut source = String::new();
+	for i in 0..blocks {
+		source.push_str(&format!(
+			"// function número {i} ✓\n\
+			#[inline]\n\
+			public fn f{i}(a: u32, b: u32) -> u32 {{\n\
+			\tvar c: u32 = a + b * 0x1F - 0b101;\n\
+			\tif(c >= 10 && c != 42) {{ c <<= 2; }}\n\
+			\t/* héllo wörld */\n\
+			\tprintln(\"値 {{c}} ünïcødé\");\n\
+			\treturn c;\n\
+			}}\n\n"
+		));
+	}
+	source
+}
+
+/// lexes `source` a few times and keeps the fastest run to smooth out scheduler noise
+fn time_lexer(name: &str, source: &str) -> (Duration, usize) {
+	let mut best = Duration::MAX;
+	let mut count = 0;
+	for _ in 0..3 {
+		let start = Instant::now();
+		let tokens = Lexer::from_source(source, name)
+			.begin()
+			.unwrap_or_else(|_| panic!("failed to lex {name}"));
+		// invalid tokens make `begin` fail, so reaching this point means the source lexed cleanly
+		best = best.min(start.elapsed());
+		count = tokens.len() - 1; // without the EOF token
+	}
+	(best, count)
+}
+
+#[test]
+fn lexing_time_grows_linearly() {
+	const BLOCKS: usize = 2_000;
+	const FACTOR: usize = 8;
+
+	let (small, small_tokens) = time_lexer("small", &synthetic_source(BLOCKS));
+	let (large, large_tokens) = time_lexer("large", &synthetic_source(BLOCKS * FACTOR));
+	assert_eq!(large_tokens, small_tokens * FACTOR);
+
+	// linear growth gives a ratio near 8, a quadratic lexer would be closer to 64
+	let ratio = large.as_secs_f64() / small.as_secs_f64().max(1e-6);
+	println!(
+		"lexed {small_tokens} tokens in {small:?} and {large_tokens} tokens in {large:?} ({ratio:.1}x)"
+	);
+	assert!(
+		ratio < (FACTOR * 3) as f64,
+		"lexing {FACTOR}x the input took {ratio:.1}x as long ({small:?} vs {large:?})"
+	);
+}
@@ -0,0 +1,302 @@
+use apollo::lexer::Lexer;
+use apollo::parser::{
+	Expression, ExpressionKind, ItemKind, Literal, Param, Parser, Pattern, Statement, StatementKind,
+	Type,
+};
+
+fn parser(source: &str) -> Parser {
+	Parser::from_lexer(
+		Lexer::from_source(source, "test.sun"),
+		0,
+		false,
+		String::new(),
+	)
+}
+
+/// the expression as an s-expression, `(PLUS 1 (STAR 2 3))`
+fn expression(source: &str) -> String {
+	let expression = parser(source)
+		.parse_expression()
+		.unwrap_or_else(|e| panic!("{source}: {}", e.message()));
+	show(&expression)
+}
+
+/// the statements of `body` parsed as the body of a function
+fn statements(body: &str) -> Vec<Statement> {
+	let (program, errors) = parser(&format!("fn f() {{ {body} }}")).parse();
+	if let Some(error) = errors.first() {
+		panic!("{body}: {}", error.message());
+	}
+	match program.items.into_iter().next().map(|item| item.kind) {
+		Some(ItemKind::Function(function)) => function.body.unwrap().statements,
+		_ => panic!("{body}: expected a function"),
+	}
+}
+
+fn show(expression: &Expression) -> String {
+	let list = |items: &[Expression]| items.iter().map(show).collect::<Vec<_>>().join(" ");
+	match &expression.kind {
+		ExpressionKind::Literal(literal) => show_literal(literal),
+		ExpressionKind::Identifier(name) => name.clone(),
+		ExpressionKind::SelfValue => "self".to_string(),
+		ExpressionKind::Unary { operator, operand } => format!("({operator:?} {})", show(operand)),
+		ExpressionKind::Postfix { operator, operand } => format!("({} {operator:?})", show(operand)),
+		ExpressionKind::Binary {
+			left,
+			operator,
+			right,
+		} => format!("({operator:?} {} {})", show(left), show(right)),
+		ExpressionKind::Assign {
+			target,
+			operator,
+			value,
+		} => format!("({operator:?} {} {})", show(target), show(value)),
+		ExpressionKind::Call { callee, arguments } => {
+			format!("(call {} [{}])", show(callee), list(arguments))
+		}
+		ExpressionKind::Member { object, name } => format!("(. {} {name})", show(object)),
+		ExpressionKind::Index { object, index } => format!("([] {} {})", show(object), show(index)),
+		ExpressionKind::Cast { value, ty } => format!("(as {} {})", show(value), show_type(ty)),
+		ExpressionKind::Reference(inner) => format!("(@ {})", show(inner)),
+		ExpressionKind::Lambda { param, body } => format!("(=> {param} {})", show(body)),
+		ExpressionKind::Closure { params, body } => {
+			let params: Vec<_> = params.iter().map(show_param).collect();
+			format!(
+				"(closure [{}] {} statements)",
+				params.join(", "),
+				body.statements.len()
+			)
+		}
+		ExpressionKind::Match { subject, arms } => {
+			let arms: Vec<_> = arms
+				.iter()
+				.map(|arm| {
+					format!(
+						"{} -> {}",
+						show_pattern(&arm.pattern),
+						show_statement(&arm.body)
+					)
+				})
+				.collect();
+			format!("(match {} [{}])", show(subject), arms.join(", "))
+		}
+		other => format!("{other:?}"),
+	}
+}
+
+fn show_literal(literal: &Literal) -> String {
+	match literal {
+		Literal::Number { value, .. } => value.clone(),
+		Literal::String(value) => format!("\"{value}\""),
+		Literal::Character(value) => format!("'{value}'"),
+		Literal::Boolean(value) => value.to_string(),
+	}
+}
+
+fn show_pattern(pattern: &Pattern) -> String {
+	match pattern {
+		Pattern::Wildcard => "_".to_string(),
+		Pattern::Literal(literal) => show_literal(literal),
+		Pattern::Variant(path) => path.join("."),
+	}
+}
+
+fn show_statement(statement: &Statement) -> String {
+	match &statement.kind {
+		StatementKind::Expression(expression) => show(expression),
+		StatementKind::Block(block) => format!("{{{} statements}}", block.statements.len()),
+		other => format!("{other:?}"),
+	}
+}
+
+fn show_param(param: &Param) -> String {
+	let constant = if param.constant { "const " } else { "" };
+	format!("{constant}{}: {}", param.name, show_type(&param.ty))
+}
+
+fn show_type(ty: &Type) -> String {
+	match ty {
+		Type::Named { name, .. } => name.clone(),
+		other => format!("{other:?}"),
+	}
+}
+
+#[test]
+fn binary_operators_follow_the_precedence_table() {
+	assert_eq!(
+		expression("1+2*3-4<<1"),
+		"(LEFTSHIFT (MINUS (PLUS 1 (STAR 2 3)) 4) 1)"
+	);
+	assert_eq!(
+		expression("a || b && c | d"),
+		"(BOOLOR a (BOOLAND b (BAR c d)))"
+	);
+	assert_eq!(expression("a == b < c"), "(EQL a (LESS b c))");
+}
+
+#[test]
+fn assignment_is_right_associative() {
+	assert_eq!(expression("a=b=c"), "(ASSIGN a (ASSIGN b c))");
+	assert_eq!(expression("a += b = 1"), "(ADDASSIGN a (ASSIGN b 1))");
+}
+
+#[test]
+fn prefix_operators_bind_tighter_than_casts() {
+	assert_eq!(expression("-a as u8"), "(as (MINUS a) u8)");
+	assert_eq!(expression("(c as u16) - 48"), "(MINUS (as c u16) 48)");
+	assert_eq!(expression("!a && b"), "(BOOLAND (BANG a) b)");
+}
+
+#[test]
+fn postfix_operators_bind_tightest() {
+	assert_eq!(expression("a++ + 1"), "(PLUS (a INCREMENT) 1)");
+	assert_eq!(expression("-a++"), "(MINUS (a INCREMENT))");
+}
+
+#[test]
+fn call_index_and_member_chains_read_left_to_right() {
+	assert_eq!(
+		expression("a.b(1, 2)[i].c"),
+		"(. ([] (call (. a b) [1 2]) i) c)"
+	);
+	assert_eq!(expression("f()()"), "(call (call f []) [])");
+	assert_eq!(expression("x[0][1] * 2"), "(STAR ([] ([] x 0) 1) 2)");
+}
+
+#[test]
+fn lambdas_bind_looser_than_comparisons() {
+	assert_eq!(expression("n => n < times"), "(=> n (LESS n times))");
+}
+
+#[test]
+fn closures_take_typed_params_and_a_block() {
+	assert_eq!(
+		expression("{ |const c: char, i: u32| c; i++; }"),
+		"(closure [const c: char, i: u32] 2 statements)"
+	);
+	assert_eq!(expression("{ || return 1; }"), "(closure [] 1 statements)");
+}
+
+#[test]
+fn trailing_closures_become_the_last_argument() {
+	assert_eq!(
+		expression("cmds.foreach { |const cmd: char| cmd; }"),
+		"(call (. cmds foreach) [(closure [const cmd: char] 1 statements)])"
+	);
+	assert_eq!(
+		expression("xs.fold(0) { |a: u8| a; }"),
+		"(call (. xs fold) [0 (closure [a: u8] 1 statements)])"
+	);
+}
+
+#[test]
+fn trailing_closures_stay_on_the_same_line() {
+	assert_eq!(
+		expression("cmds.foreach\n{ |c: char| c; }"),
+		"(. cmds foreach)"
+	);
+}
+
+#[test]
+fn match_arms_pair_a_pattern_with_a_statement() {
+	assert_eq!(
+		expression("match cmd { '>' -> ptr++; '<' -> ptr--; _ -> { a; b; } }"),
+		"(match cmd ['>' -> (ptr INCREMENT), '<' -> (ptr DECREMENT), _ -> {2 statements}])"
+	);
+}
+
+#[test]
+fn commas_end_match_arms() {
+	assert_eq!(
+		expression("match x { 1 -> a++, 2 -> f(a, b), _ -> { c; d; }, }"),
+		"(match x [1 -> (a INCREMENT), 2 -> (call f [a b]), _ -> {2 statements}])"
+	);
+	// only the arm itself ends at a comma, statements in its block still need their own terminator
+	let (_, errors) = parser("fn f() { match x { 1 -> { a, b; } } }").parse();
+	let messages: Vec<_> = errors.iter().map(|e| e.message()).collect();
+	assert_eq!(messages, ["Expected ';', found ','"]);
+}
+
+#[test]
+fn match_arms_take_enum_variants_by_name_or_path() {
+	assert_eq!(
+		expression("match face { FACES.ACE -> 11; KING -> 10; 1 -> { one(); } }"),
+		"(match face [FACES.ACE -> 11, KING -> 10, 1 -> {1 statements}])"
+	);
+}
+
+#[test]
+fn interpolated_strings_are_not_patterns() {
+	let (_, errors) = parser("fn f() { match s { \"{x}\" -> 1; } }").parse();
+	let messages: Vec<_> = errors.iter().map(|e| e.message()).collect();
+	assert_eq!(messages, ["Interpolated strings can't be used as patterns"]);
+}
+
+#[test]
+fn catch_follows_a_bare_block() {
+	let statements = statements("{ a(); b(); } catch(IllegalArgument e) { retry; }");
+	let [
+		Statement {
+			kind: StatementKind::Catch {
+				body,
+				error_type,
+				binding,
+				handler,
+			},
+			..
+		},
+	] = &statements[..]
+	else {
+		panic!("expected a single catch statement");
+	};
+	assert_eq!(body.statements.len(), 2);
+	assert_eq!(show_type(error_type), "IllegalArgument");
+	assert_eq!(binding, "e");
+	assert!(matches!(
+		handler.statements[..],
+		[Statement {
+			kind: StatementKind::Retry,
+			..
+		}]
+	));
+}
+
+#[test]
+fn blocks_without_catch_stay_blocks() {
+	let statements = statements("{ a(); } retry;");
+	assert!(matches!(statements[0].kind, StatementKind::Block(_)));
+	assert!(matches!(statements[1].kind, StatementKind::Retry));
+}
+
+/// the doc lines of every top level declaration in `source`
+fn docs(source: &str) -> Vec<Vec<String>> {
+	let (program, errors) = parser(source).parse();
+	if let Some(error) = errors.first() {
+		panic!("{source}: {}", error.message());
+	}
+	program.items.into_iter().map(|item| item.docs).collect()
+}
+
+#[test]
+fn doc_comments_attach_to_the_next_declaration() {
+	assert_eq!(
+		docs("//: Adds one.\n//: Wraps at 255.\nfn inc(a: u8) -> u8 { return a + 1; }\nfn dec() {}"),
+		[vec!["Adds one.", "Wraps at 255."], vec![]]
+	);
+}
+
+#[test]
+fn doc_comments_carry_over_annotations() {
+	assert_eq!(
+		docs("//: Starts here.\n#[entry]\nfn main() {}"),
+		[vec!["Starts here."]]
+	);
+}
+
+#[test]
+fn doc_comments_inside_bodies_are_dropped() {
+	assert_eq!(
+		docs("fn f() {\n\t//: not a declaration\n\tf();\n}\nfn g() {}"),
+		[Vec::<String>::new(), vec![]]
+	);
+}
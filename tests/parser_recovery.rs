@@ -0,0 +1,157 @@
+use apollo::lexer::Lexer;
+use apollo::parser::{ExpressionKind, ItemKind, Parser, StatementKind};
+
+const SOURCE: &str = "fn main() {
+	var x: u8 = 1 2;
+	x = = 3;
+	println(\"still parsed\");
+}
+
+fn broken(a b) -> u8 {
+	return a;
+}
+
+fn fine() {}
+";
+
+fn parse(source: &str) -> (apollo::parser::Program, Vec<apollo::util::ApolloError>) {
+	Parser::from_lexer(
+		Lexer::from_source(source, "recovery.sun"),
+		0,
+		false,
+		String::new(),
+	)
+	.parse()
+}
+
+#[test]
+fn every_syntax_error_is_reported_with_a_span() {
+	let (_, errors) = parse(SOURCE);
+	let found: Vec<_> = errors
+		.iter()
+		.map(|e| {
+			let span = e.span().expect("syntax errors carry a span");
+			(
+				e.message().to_string(),
+				span.start_line,
+				span.start_column,
+				&SOURCE[span.start..span.end],
+			)
+		})
+		.collect();
+	assert_eq!(
+		found,
+		[
+			("Expected ';', found '2'".to_string(), 2, 16, "2"),
+			("Expected an expression, found '='".to_string(), 3, 6, "="),
+			("Expected ':', found 'b'".to_string(), 7, 13, "b"),
+		]
+	);
+}
+
+#[test]
+fn failed_declarations_and_statements_become_error_nodes() {
+	let (program, _) = parse(SOURCE);
+	let kinds: Vec<_> = program.items.iter().map(|i| &i.kind).collect();
+	let [
+		ItemKind::Function(main),
+		ItemKind::Error,
+		ItemKind::Function(fine),
+	] = kinds[..]
+	else {
+		panic!("expected main, an error node and fine");
+	};
+	assert_eq!(fine.name, "fine");
+	let statements: Vec<_> = main
+		.body
+		.as_ref()
+		.unwrap()
+		.statements
+		.iter()
+		.map(|s| &s.kind)
+		.collect();
+	assert!(matches!(
+		statements[..],
+		[
+			StatementKind::Error,
+			StatementKind::Error,
+			StatementKind::Expression(_)
+		]
+	));
+}
+
+#[test]
+fn begin_fails_with_every_error() {
+	let mut parser = Parser::from_lexer(
+		Lexer::from_source(SOURCE, "recovery.sun"),
+		0,
+		false,
+		String::new(),
+	);
+	assert_eq!(parser.begin().err().map(|e| e.len()), Some(3));
+}
+
+/// `line:column: message` for every error in `source`
+fn errors(source: &str) -> Vec<String> {
+	parse(source)
+		.1
+		.iter()
+		.map(|e| {
+			let (line, column) = e.position().unwrap_or((0, 0));
+			format!("{line}:{column}: {}", e.message())
+		})
+		.collect()
+}
+
+#[test]
+fn broken_match_arms_are_skipped() {
+	let source = "fn f() {
+	match x {
+		1 -> ;
+		2 -> y();
+		+ -> z(),
+		3 -> w(1 2)
+		4 -> { v(; }
+		_ -> u();
+	}
+	after();
+}
+fn g() {}
+";
+	assert_eq!(
+		errors(source),
+		[
+			"3:8: Expected an expression, found ';'",
+			"5:3: Expected a pattern, found '+'",
+			"6:12: Expected ')', found '2'",
+			"7:12: Expected an expression, found ';'",
+		]
+	);
+	let (program, _) = parse(source);
+	let ItemKind::Function(f) = &program.items[0].kind else {
+		panic!("expected f");
+	};
+	let statements = &f.body.as_ref().unwrap().statements;
+	let StatementKind::Expression(expression) = &statements[0].kind else {
+		panic!("expected the match");
+	};
+	let ExpressionKind::Match { arms, .. } = &expression.kind else {
+		panic!("expected the match");
+	};
+	// `2`, `4` and `_`, the block of `4` recovered on its own
+	assert_eq!(arms.len(), 3);
+	assert_eq!(statements.len(), 2);
+	assert_eq!(program.items.len(), 2);
+}
+
+#[test]
+fn running_into_the_end_after_an_invalid_token_is_not_reported() {
+	assert_eq!(
+		errors("fn f() {\n\tvar s = \"abc;\n}\n"),
+		["2:10: Unterminated string"]
+	);
+	assert_eq!(
+		errors("fn f() {\n\tg();\n}\n/* never closed\nfn h() {}\n"),
+		["4:1: Unterminated block comment"]
+	);
+}
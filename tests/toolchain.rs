@@ -0,0 +1,104 @@
+use std::path::Path;
+
+use apollo::toolchain::{Toolchain, parse_diagnostics};
+
+#[test]
+fn compiler_errors_and_warnings_keep_their_location() {
+	let stderr = "out/main.c: In function 'main':
+out/main.c:12:5: error: 'x' undeclared (first use in this function)
+   12 |     x = 1;
+      |     ^
+out/main.c:3:10: warning: unused variable 'y' [-Wunused-variable]
+out/main.c:4:1: note: declared here
+";
+	let diagnostics = parse_diagnostics(stderr);
+	let found: Vec<_> = diagnostics
+		.iter()
+		.map(|d| (d.is_warning(), d.position(), d.message().to_string()))
+		.collect();
+	assert_eq!(
+		found,
+		[
+			(
+				false,
+				Some((12, 5)),
+				"'x' undeclared (first use in this function) in out/main.c".to_string()
+			),
+			(
+				true,
+				Some((3, 10)),
+				"unused variable 'y' [-Wunused-variable] in out/main.c".to_string()
+			),
+		]
+	);
+}
+
+#[test]
+fn fatal_errors_count_as_errors() {
+	let diagnostics =
+		parse_diagnostics("main.c:1:10: fatal error: stdio.h: No such file or directory\n");
+	assert_eq!(diagnostics.len(), 1);
+	assert!(!diagnostics[0].is_warning());
+	assert_eq!(diagnostics[0].position(), Some((1, 10)));
+	assert_eq!(
+		diagnostics[0].message(),
+		"stdio.h: No such file or directory in main.c"
+	);
+}
+
+#[test]
+fn errors_without_a_location_are_kept_and_warnings_dropped() {
+	let stderr = "cc1: error: unrecognized command-line option '-foo'\ncc1: warning: something odd\n";
+	let diagnostics = parse_diagnostics(stderr);
+	assert_eq!(diagnostics.len(), 1);
+	assert_eq!(diagnostics[0].position(), None);
+	assert_eq!(
+		diagnostics[0].message(),
+		"cc1: unrecognized command-line option '-foo'"
+	);
+}
+
+#[test]
+fn windows_paths_keep_their_drive_letter() {
+	let diagnostics = parse_diagnostics("C:\\out\\main.c:7:2: error: expected ';'\n");
+	assert_eq!(diagnostics[0].position(), Some((7, 2)));
+	assert_eq!(diagnostics[0].message(), "expected ';' in C:\\out\\main.c");
+}
+
+fn find(requested: Option<&str>) -> Result<String, String> {
+	Toolchain::find(requested.map(str::to_string), 0, false, String::new())
+		.map(|toolchain| toolchain.cc)
+		.map_err(|e| e.message().to_string())
+}
+
+/// one test so nothing else reads `CC` while it changes
+#[test]
+fn cc_flag_wins_over_the_environment() {
+	let dir = Path::new(env!("CARGO_TARGET_TMPDIR")).join("toolchain");
+	std::fs::create_dir_all(&dir).unwrap();
+	let (flag, env) = (dir.join("flag-cc"), dir.join("env-cc"));
+	std::fs::write(&flag, "").unwrap();
+	std::fs::write(&env, "").unwrap();
+	let (flag, env) = (
+		flag.to_string_lossy().to_string(),
+		env.to_string_lossy().to_string(),
+	);
+
+	// SAFETY: no other test in this binary reads or writes `CC`
+	unsafe { std::env::set_var("CC", &env) };
+	assert_eq!(find(None), Ok(env.clone()));
+	assert_eq!(find(Some(&flag)), Ok(flag.clone()));
+
+	unsafe { std::env::set_var("CC", dir.join("missing-cc")) };
+	assert_eq!(find(Some(&flag)), Ok(flag.clone()));
+	assert!(
+		find(None)
+			.unwrap_err()
+			.starts_with("Could not find the C compiler")
+	);
+
+	// an empty `CC` is the same as none, the usual compilers are tried instead
+	unsafe { std::env::set_var("CC", "") };
+	assert_ne!(find(None), Ok(env));
+	unsafe { std::env::remove_var("CC") };
+}